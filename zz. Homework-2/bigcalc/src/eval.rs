@@ -0,0 +1,340 @@
+use solution::Bigint;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// An error with the column(1-based, in characters) where it happened
+#[derive(Debug, PartialEq, Eq)]
+pub struct CalcError {
+    pub column: usize,
+    pub message: String,
+}
+
+impl CalcError {
+    fn new(column: usize, message: &str) -> Self {
+        CalcError {
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for CalcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error at column {}: {}", self.column, self.message)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Token {
+    Number(String),
+    Ident(String),
+    Let,
+    Assign,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Caret,
+    Open,
+    Close,
+    End,
+}
+
+/// Splits the input into tokens, each one paired with the column where it starts
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, CalcError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let column = i + 1;
+        let ch = chars[i];
+        let token = match ch {
+            ' ' | '\t' | '\r' | '\n' => {
+                i += 1;
+                continue;
+            }
+            '0'..='9' => {
+                let start = i;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                tokens.push((Token::Number(chars[start..i].iter().collect()), column));
+                continue;
+            }
+            'a'..='z' | 'A'..='Z' | '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                let token = if word == "let" {
+                    Token::Let
+                } else {
+                    Token::Ident(word)
+                };
+                tokens.push((token, column));
+                continue;
+            }
+            '=' => Token::Assign,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '/' => Token::Slash,
+            '%' => Token::Percent,
+            '^' => Token::Caret,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            _ => return Err(CalcError::new(column, &format!("unexpected '{}'", ch))),
+        };
+        tokens.push((token, column));
+        i += 1;
+    }
+    tokens.push((Token::End, chars.len() + 1));
+    Ok(tokens)
+}
+
+/// The largest exponent of `^`, the powers of bigger ones take too long to compute
+const MAX_EXPONENT: u32 = 10_000;
+
+/// Keeps the variables between the evaluated lines
+#[derive(Debug, Default)]
+pub struct Calculator {
+    variables: HashMap<String, Bigint>,
+}
+
+/// Recursive descent over the tokens of a single line, evaluating as it goes
+///
+/// statement := 'let' IDENT '=' expr | expr
+/// expr      := term (('+' | '-') term)*
+/// term      := unary (('*' | '/' | '%') unary)*
+/// unary     := '-' unary | power
+/// power     := primary ('^' unary)?
+/// primary   := NUMBER | IDENT | '(' expr ')'
+struct Parser<'a> {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    variables: &'a HashMap<String, Bigint>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].0
+    }
+
+    fn column(&self) -> usize {
+        self.tokens[self.position].1
+    }
+
+    fn advance(&mut self) -> (Token, usize) {
+        let token = self.tokens[self.position].clone();
+        if token.0 != Token::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn expect_end(&self) -> Result<(), CalcError> {
+        match self.peek() {
+            Token::End => Ok(()),
+            Token::Close => Err(CalcError::new(self.column(), "unmatched ')'")),
+            _ => Err(CalcError::new(self.column(), "expected an operator")),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Bigint, CalcError> {
+        let mut res = self.term()?;
+        loop {
+            match self.peek() {
+                Token::Plus => {
+                    self.advance();
+                    res = res + self.term()?;
+                }
+                Token::Minus => {
+                    self.advance();
+                    res = res - self.term()?;
+                }
+                _ => return Ok(res),
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<Bigint, CalcError> {
+        let mut res = self.unary()?;
+        loop {
+            let operator = self.peek().clone();
+            if operator != Token::Star && operator != Token::Slash && operator != Token::Percent {
+                return Ok(res);
+            }
+            let (_, column) = self.advance();
+            let right = self.unary()?;
+            res = match operator {
+                Token::Star => res * right,
+                _ if right.is_zero() => return Err(CalcError::new(column, "division by zero")),
+                Token::Slash => res / right,
+                _ => res % right,
+            };
+        }
+    }
+
+    fn unary(&mut self) -> Result<Bigint, CalcError> {
+        if *self.peek() == Token::Minus {
+            self.advance();
+            return Ok(-&self.unary()?);
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Bigint, CalcError> {
+        let base = self.primary()?;
+        if *self.peek() != Token::Caret {
+            return Ok(base);
+        }
+        self.advance();
+        let column = self.column();
+        let exponent = self.unary()?;
+        if exponent.is_negative() {
+            return Err(CalcError::new(column, "the exponent cannot be negative"));
+        }
+        match exponent.to_string().parse::<u32>() {
+            Ok(exp) if exp <= MAX_EXPONENT => Ok(base.pow(exp)),
+            _ => Err(CalcError::new(
+                column,
+                &format!("the exponent cannot be larger than {}", MAX_EXPONENT),
+            )),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Bigint, CalcError> {
+        let (token, column) = self.advance();
+        match token {
+            Token::Number(digits) => Ok(Bigint::from_str(&digits).unwrap()), // the tokenizer only lets digits in
+            Token::Ident(name) => match self.variables.get(&name) {
+                Some(value) => Ok(value.clone()),
+                None => Err(CalcError::new(
+                    column,
+                    &format!("unknown variable '{}'", name),
+                )),
+            },
+            Token::Open => {
+                let res = self.expr()?;
+                match self.advance() {
+                    (Token::Close, _) => Ok(res),
+                    (_, column) => Err(CalcError::new(column, "expected ')'")),
+                }
+            }
+            Token::End => Err(CalcError::new(column, "unexpected end of input")),
+            _ => Err(CalcError::new(
+                column,
+                "expected a number, a variable or '('",
+            )),
+        }
+    }
+}
+
+impl Calculator {
+    pub fn new() -> Self {
+        Calculator::default()
+    }
+
+    /// Evaluates one line - either an expression or `let name = expression`.\
+    /// Returns the value, which is also stored in the variable for `let`
+    pub fn eval_line(&mut self, line: &str) -> Result<Bigint, CalcError> {
+        let tokens = tokenize(line)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            variables: &self.variables,
+        };
+
+        if *parser.peek() == Token::Let {
+            parser.advance();
+            let name = match parser.advance() {
+                (Token::Ident(name), _) => name,
+                (_, column) => return Err(CalcError::new(column, "expected a variable name")),
+            };
+            match parser.advance() {
+                (Token::Assign, _) => {}
+                (_, column) => return Err(CalcError::new(column, "expected '='")),
+            }
+            let value = parser.expr()?;
+            parser.expect_end()?;
+            self.variables.insert(name, value.clone());
+            return Ok(value);
+        }
+
+        let value = parser.expr()?;
+        parser.expect_end()?;
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(line: &str) -> Result<String, CalcError> {
+        Calculator::new().eval_line(line).map(|n| n.to_string())
+    }
+
+    #[test]
+    fn precedence_test() {
+        assert_eq!(eval("1 + 2 * 3").unwrap(), "7");
+        assert_eq!(eval("(1 + 2) * 3").unwrap(), "9");
+        assert_eq!(eval("10 - 4 - 3").unwrap(), "3");
+        assert_eq!(eval("2 ^ 3 ^ 2").unwrap(), "512");
+        assert_eq!(eval("2 * 3 ^ 2").unwrap(), "18");
+        assert_eq!(eval("17 % 5 * 2").unwrap(), "4");
+        assert_eq!(eval("-7 / 2").unwrap(), "-3");
+    }
+
+    #[test]
+    fn unary_minus_test() {
+        assert_eq!(eval("-2 ^ 2").unwrap(), "-4");
+        assert_eq!(eval("(-2) ^ 2").unwrap(), "4");
+        assert_eq!(eval("--5").unwrap(), "5");
+        assert_eq!(eval("3 - -5").unwrap(), "8");
+    }
+
+    #[test]
+    fn big_expression_test() {
+        assert_eq!(
+            eval("(123456789012345678901234567890 - 42) * 7 ^ 20 % 1000000007").unwrap(),
+            "700859175"
+        );
+    }
+
+    #[test]
+    fn variables_test() {
+        let mut calc = Calculator::new();
+        assert_eq!(
+            calc.eval_line("let x = 2 ^ 10").unwrap(),
+            Bigint::from(1024)
+        );
+        assert_eq!(
+            calc.eval_line("let y = x * x").unwrap(),
+            Bigint::from(1_048_576)
+        );
+        assert_eq!(calc.eval_line("y - x").unwrap(), Bigint::from(1_047_552));
+        assert_eq!(calc.eval_line("let x = x + 1").unwrap(), Bigint::from(1025));
+    }
+
+    #[test]
+    fn error_columns_test() {
+        assert_eq!(eval("1 + ").unwrap_err().column, 5);
+        assert_eq!(eval("1 + $").unwrap_err().column, 5);
+        assert_eq!(eval("(1 + 2").unwrap_err().column, 7);
+        assert_eq!(eval("1 + 2)").unwrap_err().column, 6);
+        assert_eq!(eval("1 2").unwrap_err().column, 3);
+        assert_eq!(eval("x + 1").unwrap_err().column, 1);
+        assert_eq!(eval("5 / (3 - 3)").unwrap_err().column, 3);
+        assert_eq!(eval("5 % 0").unwrap_err().column, 3);
+        assert_eq!(eval("2 ^ -1").unwrap_err().column, 5);
+        assert_eq!(eval("2 ^ 10000").unwrap().len(), 3011);
+        assert_eq!(eval("2 ^ 4000000000").unwrap_err().column, 5);
+        assert_eq!(eval("2 ^ (10000 + 1)").unwrap_err().column, 5);
+        assert_eq!(eval("let = 5").unwrap_err().column, 5);
+        assert_eq!(eval("let x 5").unwrap_err().column, 7);
+    }
+}
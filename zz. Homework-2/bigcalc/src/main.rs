@@ -0,0 +1,124 @@
+//! bigcalc - evaluates integer expressions with arbitrary precision
+//!
+//!   bigcalc "(123456789012345678901234567890 - 42) * 7 ^ 20 % 1000000007"
+//!   echo "let x = 2 ^ 100" | bigcalc
+//!   bigcalc        # interactive mode when stdin is a terminal
+//!
+//! In the interactive mode `history` lists the previous lines and `!n` evaluates line n again
+
+mod eval;
+
+use eval::{CalcError, Calculator};
+use std::io::{self, BufRead, IsTerminal, Write};
+
+/// Prints the error with a marker under the column where it happened,
+/// in the interactive mode the line is already on the screen after the prompt
+fn report(line: &str, error: &CalcError, prompt_width: usize) {
+    if prompt_width == 0 {
+        eprintln!("{}", line);
+    }
+    eprintln!("{}^", " ".repeat(prompt_width + error.column - 1));
+    eprintln!("{}", error);
+}
+
+/// Evaluates all the lines, stops at the first error
+fn run_batch<R: BufRead>(input: R, calc: &mut Calculator) -> io::Result<bool> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match calc.eval_line(&line) {
+            Ok(value) => println!("{}", value),
+            Err(error) => {
+                report(&line, &error, 0);
+                return Ok(false);
+            }
+        }
+    }
+    Ok(true)
+}
+
+fn run_repl(calc: &mut Calculator) -> io::Result<()> {
+    const PROMPT: &str = "> ";
+    let stdin = io::stdin();
+    let mut history: Vec<String> = Vec::new();
+    loop {
+        print!("{}", PROMPT);
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(());
+        }
+        let mut line = line.trim_end().to_string();
+        match line.trim() {
+            "" => continue,
+            "exit" | "quit" => return Ok(()),
+            "history" => {
+                for (i, entry) in history.iter().enumerate() {
+                    println!("{:>4}  {}", i + 1, entry);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        if let Some(index) = line.strip_prefix('!') {
+            match index
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| history.get(i.wrapping_sub(1)))
+            {
+                Some(entry) => {
+                    line = entry.clone();
+                    // after the prompt, where `report` expects the line to be
+                    println!("{}{}", PROMPT, line);
+                }
+                None => {
+                    eprintln!("no such history entry: {}", index);
+                    continue;
+                }
+            }
+        }
+
+        let result = calc.eval_line(&line);
+        history.push(line);
+        match result {
+            Ok(value) => println!("{}", value),
+            Err(error) => report(&history[history.len() - 1], &error, PROMPT.len()),
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut calc = Calculator::new();
+
+    let success = if !args.is_empty() {
+        let line = args.join(" ");
+        match calc.eval_line(&line) {
+            Ok(value) => {
+                println!("{}", value);
+                true
+            }
+            Err(error) => {
+                report(&line, &error, 0);
+                false
+            }
+        }
+    } else if io::stdin().is_terminal() {
+        run_repl(&mut calc).is_ok()
+    } else {
+        let stdin = io::stdin();
+        let res = run_batch(stdin.lock(), &mut calc);
+        res.unwrap_or_else(|error| {
+            eprintln!("could not read the input: {}", error);
+            false
+        })
+    };
+
+    if !success {
+        std::process::exit(1);
+    }
+}
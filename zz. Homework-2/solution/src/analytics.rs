@@ -0,0 +1,189 @@
+//! Properties of the decimal representation of a `Bigint`
+//!
+//! The digits are already stored one per element, so most of these just walk the vector
+//! without building a string. The sign is ignored everywhere, except for `reverse_digits`
+//! (which keeps it) and the perfect powers (where it matters).
+
+use super::{Bigint, Sign};
+
+impl Bigint {
+    /// The number of decimal digits, 0 has one digit
+    pub fn digit_count(&self) -> usize {
+        self.digits.len()
+    }
+
+    /// The sum of all the decimal digits
+    pub fn digit_sum(&self) -> u64 {
+        self.digits.iter().map(|&digit| digit as u64).sum()
+    }
+
+    /// Sums the digits repeatedly until a single digit is left, 0 only for 0
+    pub fn digital_root(&self) -> u8 {
+        if self.is_zero() {
+            return 0;
+        }
+        // the digital root is the same as the remainder by 9, only 9 instead of 0
+        let remainder = self
+            .digits
+            .iter()
+            .fold(0_u8, |acc, &digit| (acc + digit) % 9);
+        if remainder == 0 {
+            9
+        } else {
+            remainder
+        }
+    }
+
+    /// Returns true if the digits read the same both ways
+    pub fn is_palindrome(&self) -> bool {
+        self.digits.iter().eq(self.digits.iter().rev())
+    }
+
+    /// The digits in reverse order, with the same sign. The zeros at the end become leading
+    /// ones and are dropped, so 1200 becomes 21
+    pub fn reverse_digits(&self) -> Bigint {
+        Bigint::from_components(self.digits.clone(), self.sign)
+    }
+
+    /// Returns true if the number is the square of an integer
+    pub fn is_perfect_square(&self) -> bool {
+        if self.is_negative() {
+            return false;
+        }
+        // no square ends with 2, 3, 7 or 8
+        if let 2 | 3 | 7 | 8 = self.digits[0] {
+            return false;
+        }
+        self.floor_root(2).pow(2) == *self
+    }
+
+    /// Returns true if the number is a^b for some integer a and some b >= 2.\
+    /// 0 and 1 count, and so do negative numbers which are an odd power of a negative one
+    pub fn is_perfect_power(&self) -> bool {
+        let abs = self.abs();
+        if abs <= Bigint::from(1) {
+            return true;
+        }
+        // 2^exp > |self| for any exp above this one
+        let max_exp = (self.digits.len() as u32) * 10 / 3 + 1;
+        (2..=max_exp)
+            .filter(|exp| !self.is_negative() || exp % 2 == 1)
+            .any(|exp| abs.floor_root(exp).pow(exp) == abs)
+    }
+
+    /// The largest r with r^k <= self, for non-negative numbers only\
+    /// Newton's method starting from a power of ten which is surely above the root
+    fn floor_root(&self, k: u32) -> Bigint {
+        if self.is_zero() {
+            return Bigint::new();
+        }
+        let root_digits = self.digits.len().div_ceil(k as usize);
+        let mut start = vec![0; root_digits];
+        start.push(1);
+        let mut x = Bigint::from_reversed_digits(start, Sign::Positive);
+        let k_big = Bigint::from(k as i32);
+        let k_minus_one = Bigint::from(k as i32 - 1);
+        loop {
+            let next =
+                (k_minus_one.clone() * x.clone() + self.clone() / x.pow(k - 1)) / k_big.clone();
+            if next >= x {
+                return x;
+            }
+            x = next;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn big(s: &str) -> Bigint {
+        Bigint::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn digit_count_test() {
+        assert_eq!(Bigint::new().digit_count(), 1);
+        assert_eq!(Bigint::from(-12345).digit_count(), 5);
+        let number = big("123456789012345678901234567890");
+        assert_eq!(number.digit_count(), number.to_string().len());
+    }
+
+    #[test]
+    fn digit_sum_and_root_test() {
+        assert_eq!(Bigint::new().digit_sum(), 0);
+        assert_eq!(Bigint::new().digital_root(), 0);
+        assert_eq!(Bigint::from(-9875).digit_sum(), 29);
+        assert_eq!(Bigint::from(-9875).digital_root(), 2);
+        assert_eq!(Bigint::from(18).digital_root(), 9);
+        for i in 1..500 {
+            let expected = (i - 1) % 9 + 1;
+            assert_eq!(Bigint::from(i).digital_root() as i32, expected);
+        }
+    }
+
+    #[test]
+    fn palindrome_test() {
+        assert!(Bigint::new().is_palindrome());
+        assert!(Bigint::from(7).is_palindrome());
+        assert!(Bigint::from(-12321).is_palindrome());
+        assert!(big("123456789987654321").is_palindrome());
+        assert!(!Bigint::from(10).is_palindrome());
+        assert!(!Bigint::from(1232).is_palindrome());
+    }
+
+    #[test]
+    fn reverse_digits_test() {
+        assert_eq!(Bigint::from(123).reverse_digits(), Bigint::from(321));
+        assert_eq!(Bigint::from(-1200).reverse_digits(), Bigint::from(-21));
+        assert_eq!(Bigint::new().reverse_digits(), Bigint::new());
+        assert_eq!(
+            big("123456789012345678901234567890").reverse_digits(),
+            big("98765432109876543210987654321")
+        );
+    }
+
+    #[test]
+    fn perfect_square_test() {
+        let squares: Vec<i32> = (0..60).map(|i| i * i).collect();
+        for i in -10..3_000 {
+            assert_eq!(
+                Bigint::from(i).is_perfect_square(),
+                squares.contains(&i),
+                "{}",
+                i
+            );
+        }
+        let root = big("123456789012345678901234567890");
+        let square = root.clone() * root;
+        assert!(square.is_perfect_square());
+        assert!(!(square + Bigint::from(1)).is_perfect_square());
+    }
+
+    #[test]
+    fn perfect_power_test() {
+        let mut powers = vec![0, 1];
+        for base in 2..50_i32 {
+            let mut power = base * base;
+            while power < 2_000 {
+                powers.push(power);
+                power *= base;
+            }
+        }
+        for i in 0..2_000 {
+            assert_eq!(
+                Bigint::from(i).is_perfect_power(),
+                powers.contains(&i),
+                "{}",
+                i
+            );
+        }
+        assert!(Bigint::from(-8).is_perfect_power());
+        assert!(Bigint::from(-1).is_perfect_power());
+        assert!(!Bigint::from(-4).is_perfect_power());
+        assert!(big("2").pow(127).is_perfect_power());
+        assert!(!(big("2").pow(127) - Bigint::from(1)).is_perfect_power());
+    }
+}
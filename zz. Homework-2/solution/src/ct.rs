@@ -0,0 +1,263 @@
+//! Constant-time operations over fixed-width non-negative integers
+//!
+//! The usual operations on `Bigint` return as soon as they know the answer
+//! (different lengths, the first differing digit, ...), so the time they take depends on the values.
+//! Everything here touches every digit of a `FixedBigint` the same way, no matter what the digits are,
+//! and does not branch on them. The only things which may leak are the width and the final result.\
+//! \
+//! IMPORTANT: this is meant for toy crypto in the course material, NOT for real secrets.
+//! The compiler is still free to be clever with the code below.
+
+use super::{Bigint, Sign};
+use std::cmp::Ordering;
+
+/// A non-negative integer which always has exactly `width` digits(padded with zeros),
+/// the digits are in reverse order, the same way as in `Bigint`
+#[derive(Debug, Clone)]
+pub struct FixedBigint {
+    digits: Vec<u8>,
+}
+
+/// 1 if the digit is 0, 0 otherwise
+fn is_zero_mask(digit: u8) -> u8 {
+    ((digit as u16).wrapping_sub(1) >> 8) as u8 & 1
+}
+
+/// 1 if `left` < `right`, 0 otherwise. Works for values less than 128
+fn lt_mask(left: u8, right: u8) -> u8 {
+    (left.wrapping_sub(right) >> 7) & 1
+}
+
+/// Chooses `left` if `choice` is 1 and `right` if it is 0
+fn select_digit(choice: u8, left: u8, right: u8) -> u8 {
+    let mask = 0_u8.wrapping_sub(choice);
+    (left & mask) | (right & !mask)
+}
+
+impl FixedBigint {
+    /// Pads the digits of the number to `width` digits.\
+    /// Returns None if the number is negative or does not fit
+    pub fn new(number: &Bigint, width: usize) -> Option<Self> {
+        if number.sign == Sign::Negative || number.digits.len() > width {
+            return None;
+        }
+        let mut digits = number.digits.clone();
+        digits.resize(width, 0);
+        Some(FixedBigint { digits })
+    }
+
+    /// The number of digits, including the padding
+    pub fn width(&self) -> usize {
+        self.digits.len()
+    }
+
+    /// Converts back to a normal `Bigint`, this is NOT constant-time
+    pub fn to_bigint(&self) -> Bigint {
+        Bigint::from_reversed_digits(self.digits.clone(), Sign::Positive)
+    }
+
+    fn check_width(&self, other: &FixedBigint) {
+        assert_eq!(
+            self.width(),
+            other.width(),
+            "Constant-time operations need numbers of the same width"
+        );
+    }
+
+    /// Constant-time `==`
+    pub fn ct_eq(&self, other: &FixedBigint) -> bool {
+        self.check_width(other);
+        let mut difference = 0_u8;
+        for (&left, &right) in self.digits.iter().zip(other.digits.iter()) {
+            difference |= left ^ right;
+        }
+        is_zero_mask(difference) == 1
+    }
+
+    /// Constant-time comparison, goes through all the digits
+    /// and the most significant difference wins
+    pub fn ct_cmp(&self, other: &FixedBigint) -> Ordering {
+        self.check_width(other);
+        let mut less = 0_u8;
+        let mut greater = 0_u8;
+        for (&left, &right) in self.digits.iter().zip(other.digits.iter()) {
+            let digit_less = lt_mask(left, right);
+            let digit_greater = lt_mask(right, left);
+            let digit_equal = 1 ^ digit_less ^ digit_greater;
+            less = digit_less | (digit_equal & less);
+            greater = digit_greater | (digit_equal & greater);
+        }
+        match (less, greater) {
+            (1, _) => Ordering::Less,
+            (_, 1) => Ordering::Greater,
+            _ => Ordering::Equal,
+        }
+    }
+
+    /// Returns a copy of `left` if `condition` is true and of `right` otherwise,
+    /// without branching on the condition
+    pub fn ct_select(condition: bool, left: &FixedBigint, right: &FixedBigint) -> FixedBigint {
+        left.check_width(right);
+        let choice = condition as u8;
+        FixedBigint {
+            digits: left
+                .digits
+                .iter()
+                .zip(right.digits.iter())
+                .map(|(&l, &r)| select_digit(choice, l, r))
+                .collect(),
+        }
+    }
+
+    /// (self + other) mod modulus\
+    /// Both numbers should already be reduced(less than the modulus)
+    pub fn mod_add(&self, other: &FixedBigint, modulus: &FixedBigint) -> FixedBigint {
+        self.check_width(other);
+        self.check_width(modulus);
+
+        // the sum has one more digit than the operands
+        let mut sum = Vec::with_capacity(self.width() + 1);
+        let mut carry = 0_u8;
+        for (&left, &right) in self.digits.iter().zip(other.digits.iter()) {
+            let digit = left + right + carry;
+            carry = lt_mask(9, digit);
+            sum.push(digit - 10 * carry);
+        }
+        sum.push(carry);
+
+        // sum - modulus, if it borrows at the end the sum is already reduced
+        let mut reduced = Vec::with_capacity(sum.len());
+        let mut borrow = 0_u8;
+        for (i, &digit) in sum.iter().enumerate() {
+            let subtrahend = *modulus.digits.get(i).unwrap_or(&0) + borrow;
+            borrow = lt_mask(digit, subtrahend);
+            reduced.push(digit + 10 * borrow - subtrahend);
+        }
+
+        FixedBigint {
+            digits: sum
+                .iter()
+                .zip(reduced.iter())
+                .take(self.width())
+                .map(|(&s, &r)| select_digit(borrow, s, r))
+                .collect(),
+        }
+    }
+
+    /// (self * other) mod modulus, computed with Horner's scheme over the digits of `other`
+    /// using only `mod_add` and `ct_select`\
+    /// Both numbers should already be reduced(less than the modulus)
+    pub fn mod_mul(&self, other: &FixedBigint, modulus: &FixedBigint) -> FixedBigint {
+        self.check_width(other);
+        self.check_width(modulus);
+
+        let mut res = FixedBigint {
+            digits: vec![0; self.width()],
+        };
+        for &digit in other.digits.iter().rev() {
+            // res = res * 10
+            let mut times_ten = res.clone();
+            for _ in 1..10 {
+                times_ten = times_ten.mod_add(&res, modulus);
+            }
+            res = times_ten;
+            // res = res + self * digit, always doing all 9 additions
+            for k in 1..10 {
+                let added = res.mod_add(self, modulus);
+                res = FixedBigint::ct_select(lt_mask(k - 1, digit) == 1, &added, &res);
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const WIDTH: usize = 6;
+
+    fn fixed(n: i32) -> FixedBigint {
+        FixedBigint::new(&Bigint::from(n), WIDTH).unwrap()
+    }
+
+    #[test]
+    fn construction_test() {
+        assert!(FixedBigint::new(&Bigint::from(-1), WIDTH).is_none());
+        assert!(FixedBigint::new(&Bigint::from(1_000_000), WIDTH).is_none());
+        assert_eq!(fixed(999_999).to_bigint(), Bigint::from(999_999));
+        assert_eq!(fixed(0).to_bigint(), Bigint::new());
+        assert_eq!(fixed(42).width(), WIDTH);
+    }
+
+    #[test]
+    fn eq_and_cmp_match_normal_ops_test() {
+        let values = [0, 1, 9, 10, 11, 99, 100, 123, 321, 5_000, 98_765, 999_999];
+        for &i in values.iter() {
+            for &j in values.iter() {
+                let (left, right) = (Bigint::from(i), Bigint::from(j));
+                assert_eq!(fixed(i).ct_eq(&fixed(j)), left == right);
+                assert_eq!(fixed(i).ct_cmp(&fixed(j)), left.cmp(&right));
+            }
+        }
+    }
+
+    #[test]
+    fn select_test() {
+        assert_eq!(
+            FixedBigint::ct_select(true, &fixed(12), &fixed(345)).to_bigint(),
+            Bigint::from(12)
+        );
+        assert_eq!(
+            FixedBigint::ct_select(false, &fixed(12), &fixed(345)).to_bigint(),
+            Bigint::from(345)
+        );
+    }
+
+    #[test]
+    fn mod_add_matches_normal_ops_test() {
+        for &m in [1, 7, 10, 97, 1_000, 65_537].iter() {
+            let modulus = fixed(m);
+            for i in (0..m).step_by(1 + m as usize / 13) {
+                for j in (0..m).step_by(1 + m as usize / 11) {
+                    let expected = (Bigint::from(i) + Bigint::from(j)) % Bigint::from(m);
+                    assert_eq!(fixed(i).mod_add(&fixed(j), &modulus).to_bigint(), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mod_mul_matches_normal_ops_test() {
+        for &m in [1, 7, 10, 97, 1_000, 65_537].iter() {
+            let modulus = fixed(m);
+            for i in (0..m).step_by(1 + m as usize / 7) {
+                for j in (0..m).step_by(1 + m as usize / 5) {
+                    let expected = (Bigint::from(i) * Bigint::from(j)) % Bigint::from(m);
+                    assert_eq!(fixed(i).mod_mul(&fixed(j), &modulus).to_bigint(), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mod_mul_wide_test() {
+        let modulus = Bigint::from_str("1000000000000000000000000000057").unwrap();
+        let a = Bigint::from_str("123456789012345678901234567890").unwrap();
+        let b = Bigint::from_str("987654321098765432109876543210").unwrap();
+        let width = modulus.digits.len();
+        let res = FixedBigint::new(&a, width).unwrap().mod_mul(
+            &FixedBigint::new(&b, width).unwrap(),
+            &FixedBigint::new(&modulus, width).unwrap(),
+        );
+        assert_eq!(res.to_bigint(), (a * b) % modulus);
+    }
+
+    #[test]
+    #[should_panic]
+    fn different_widths_test() {
+        let narrow = FixedBigint::new(&Bigint::from(1), 2).unwrap();
+        fixed(1).ct_eq(&narrow);
+    }
+}
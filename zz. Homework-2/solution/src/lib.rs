@@ -1,4 +1,12 @@
-use std::{cmp::Ordering, fmt::Display, ops::Add, ops::Neg, ops::Sub, str::FromStr};
+use std::{
+    cmp::Ordering, fmt::Display, ops::Add, ops::Div, ops::Mul, ops::Neg, ops::Rem, ops::Sub,
+    str::FromStr,
+};
+
+pub mod analytics;
+pub mod ct;
+pub mod random;
+pub mod sequences;
 // at the bottom of the file there are tests,
 // which are disbled, they are very slow and
 // I didn't know if should include them
@@ -55,10 +63,7 @@ impl Bigint {
                 }
                 n => {
                     if n > 9 {
-                        panic!(
-                            String::from("A single digit cannot be greater than 9: ")
-                                + n.to_string().as_str()
-                        );
+                        panic!("A single digit cannot be greater than 9: {}", n);
                     }
                     leading_zero = false;
                     significant.insert(0, n);
@@ -79,28 +84,163 @@ impl Bigint {
     /// Pretty much self explanatory, but\
     /// Returns true if the number is positive, false if it is negative or zero
     pub fn is_positive(&self) -> bool {
-        match self.sign {
-            Sign::Positive => true,
-            _ => false,
-        }
+        matches!(self.sign, Sign::Positive)
     }
 
     /// Pretty much self explanatory, but\
     /// Returns true if it is negative, false if the number is positive or zero
     pub fn is_negative(&self) -> bool {
-        match self.sign {
-            Sign::Negative => true,
-            _ => false,
-        }
+        matches!(self.sign, Sign::Negative)
     }
 
     /// Returns the absolute value of this integer
     pub fn abs(&self) -> Bigint {
         Bigint {
             digits: self.digits.clone(),
-            sign: Sign::Positive,
+            sign: match self.sign {
+                Sign::None => Sign::None,
+                _ => Sign::Positive,
+            },
+        }
+    }
+
+    /// Returns true if the number is zero
+    pub fn is_zero(&self) -> bool {
+        self.sign == Sign::None
+    }
+
+    /// A new bigint from digits which are already in reverse order(least significant first)
+    /// NOT public - utility only, trailing zeros(the leading ones of the number) are dropped
+    fn from_reversed_digits(mut digits: Vec<u8>, sign: Sign) -> Self {
+        while digits.len() > 1 && *digits.last().unwrap() == 0 {
+            digits.pop();
+        }
+        if digits.is_empty() || digits == [0] {
+            Bigint::new()
+        } else {
+            Bigint { digits, sign }
+        }
+    }
+
+    /// Only used to move machine words into a `Bigint`. NOT public - utility only
+    fn from_u64(mut value: u64) -> Bigint {
+        let mut digits = Vec::new();
+        while value > 0 {
+            digits.push((value % 10) as u8);
+            value /= 10;
+        }
+        Bigint::from_reversed_digits(digits, Sign::Positive)
+    }
+
+    /// Returns the quotient and the remainder of the division.\
+    /// The quotient is truncated towards zero and the remainder has the sign of `self`,
+    /// the same way the primitive integers behave
+    ///
+    /// Panics if `divisor` is zero
+    pub fn div_rem(&self, divisor: &Bigint) -> (Bigint, Bigint) {
+        if divisor.is_zero() {
+            panic!("Attempt to divide by zero");
+        }
+        let (quotient, remainder) = div_rem_digits(&self.digits, &divisor.digits);
+        let quotient_sign = if self.sign == divisor.sign {
+            Sign::Positive
+        } else {
+            Sign::Negative
+        };
+        (
+            Bigint::from_reversed_digits(quotient, quotient_sign),
+            Bigint::from_reversed_digits(remainder, self.sign),
+        )
+    }
+
+    /// Raises the number to the given power, using exponentiation by squaring
+    pub fn pow(&self, mut exp: u32) -> Bigint {
+        let mut base = self.clone();
+        let mut res = Bigint::from(1);
+        while exp > 0 {
+            if exp % 2 == 1 {
+                res = res * base.clone();
+            }
+            exp /= 2;
+            if exp > 0 {
+                base = base.clone() * base;
+            }
         }
+        res
+    }
+}
+
+impl Default for Bigint {
+    fn default() -> Self {
+        Bigint::new()
+    }
+}
+
+/// Compares two numbers given as reversed digits, without leading zeros
+fn cmp_digits(left: &[u8], right: &[u8]) -> Ordering {
+    if left.len() != right.len() {
+        return left.len().cmp(&right.len());
     }
+    left.iter().rev().cmp(right.iter().rev())
+}
+
+/// Subtracts `right` from `left` in place, both are reversed digits and `left` >= `right`
+fn sub_digits_in_place(left: &mut Vec<u8>, right: &[u8]) {
+    let mut borrow = 0_i8;
+    for (i, cell) in left.iter_mut().enumerate() {
+        let mut digit = *cell as i8 - *right.get(i).unwrap_or(&0) as i8 - borrow;
+        borrow = 0;
+        if digit < 0 {
+            digit += 10;
+            borrow = 1;
+        }
+        *cell = digit as u8;
+    }
+    while left.len() > 1 && *left.last().unwrap() == 0 {
+        left.pop();
+    }
+}
+
+/// Schoolbook multiplication of two numbers given as reversed digits
+fn mul_digits(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut res = vec![0_u32; left.len() + right.len()];
+    for (i, &l) in left.iter().enumerate() {
+        if l == 0 {
+            continue;
+        }
+        for (j, &r) in right.iter().enumerate() {
+            res[i + j] += l as u32 * r as u32;
+        }
+        // keeps the accumulated values small enough so they never overflow
+        let mut carry = 0;
+        for cell in res.iter_mut().skip(i) {
+            let value = *cell + carry;
+            *cell = value % 10;
+            carry = value / 10;
+        }
+    }
+    res.into_iter().map(|digit| digit as u8).collect()
+}
+
+/// Long division of two numbers given as reversed digits, returns (quotient, remainder)
+fn div_rem_digits(dividend: &[u8], divisor: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let mut quotient = vec![0_u8; dividend.len()];
+    let mut remainder: Vec<u8> = vec![0];
+    for i in (0..dividend.len()).rev() {
+        // remainder = remainder * 10 + dividend[i]
+        if remainder == [0] {
+            remainder[0] = dividend[i];
+        } else {
+            remainder.insert(0, dividend[i]);
+        }
+        let mut digit = 0;
+        while cmp_digits(&remainder, divisor) != Ordering::Less {
+            sub_digits_in_place(&mut remainder, divisor);
+            digit += 1;
+        }
+        quotient[i] = digit;
+    }
+    (quotient, remainder)
 }
 
 impl Neg for &Bigint {
@@ -123,7 +263,7 @@ impl From<i32> for Bigint {
             _ => unreachable!("i32 is either less than, greater than or 0"),
         };
         let mut digits = Vec::<u8>::new();
-        let mut copy = val.abs() as usize;
+        let mut copy = val.unsigned_abs() as usize;
         while copy > 0 {
             digits.push((copy % 10) as u8);
             copy /= 10;
@@ -155,10 +295,10 @@ impl FromStr for Bigint {
     /// If any other sign is inputted, a parse error is emmitted
     ///
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s == "" {
+        if s.is_empty() {
             return Ok(Bigint::new());
         }
-        let potential_sign = s.chars().nth(0).unwrap(); // unwrap is safe, because the if-check before ensures that s has at least one char
+        let potential_sign = s.chars().next().unwrap(); // unwrap is safe, because the if-check before ensures that s has at least one char
         let skip_char = if potential_sign == '-' || potential_sign == '+' {
             1
         } else {
@@ -176,7 +316,6 @@ impl FromStr for Bigint {
 
         let significant: Vec<_> = s
             .chars()
-            .into_iter()
             .skip(skip_char)
             .skip_while(|&ch| ch == '0')
             .collect();
@@ -344,11 +483,11 @@ impl Sub for Bigint {
             }
             return Bigint::from_components(res_digits, Sign::Positive);
         } else if self.sign == Sign::Negative && other.sign == Sign::Negative {
-            return -&(self.abs() + other.abs());
+            return other.abs() - self.abs();
         } else if self.sign == Sign::Positive && other.sign == Sign::Negative {
-            return self - other.abs();
+            return self + other.abs();
         } else if self.sign == Sign::Negative && other.sign == Sign::Positive {
-            return other - self.abs();
+            return -&(self.abs() + other);
         } else if self.sign == Sign::None {
             return -&other;
         } else if other.sign == Sign::None {
@@ -360,6 +499,40 @@ impl Sub for Bigint {
     }
 }
 
+impl Mul for Bigint {
+    type Output = Bigint;
+
+    fn mul(self, other: Self) -> Self {
+        if self.is_zero() || other.is_zero() {
+            return Bigint::new();
+        }
+        let sign = if self.sign == other.sign {
+            Sign::Positive
+        } else {
+            Sign::Negative
+        };
+        Bigint::from_reversed_digits(mul_digits(&self.digits, &other.digits), sign)
+    }
+}
+
+impl Div for Bigint {
+    type Output = Bigint;
+
+    /// Truncates towards zero, panics on division by zero
+    fn div(self, other: Self) -> Self {
+        self.div_rem(&other).0
+    }
+}
+
+impl Rem for Bigint {
+    type Output = Bigint;
+
+    /// The result has the sign of the dividend, panics on division by zero
+    fn rem(self, other: Self) -> Self {
+        self.div_rem(&other).1
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -390,6 +563,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn sign_zero_test() {
         let real_zero = Bigint::new();
         let pos_zero = Bigint::from_components(vec![0, 0, 0], Sign::Positive);
@@ -503,6 +677,71 @@ mod tests {
         }
     }
 
+    #[test]
+    fn abs_of_zero_test() {
+        assert_eq!(Bigint::new().abs(), Bigint::new());
+        assert_eq!(Bigint::from(-5).abs(), Bigint::from(5));
+    }
+
+    #[test]
+    fn sub_test() {
+        for i in -64..64 {
+            for j in -64..64 {
+                assert_eq!(Bigint::from(i - j), Bigint::from(i) - Bigint::from(j));
+            }
+        }
+    }
+
+    #[test]
+    fn mul_test() {
+        for i in -64..64 {
+            for j in -64..64 {
+                assert_eq!(Bigint::from(i * j), Bigint::from(i) * Bigint::from(j));
+            }
+        }
+        let big = Bigint::from_str("123456789012345678901234567890").unwrap();
+        assert_eq!(
+            big.clone() * big,
+            Bigint::from_str("15241578753238836750495351562536198787501905199875019052100")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn div_rem_test() {
+        for i in -64..64 {
+            for j in -64..64 {
+                if j == 0 {
+                    continue;
+                }
+                assert_eq!(Bigint::from(i / j), Bigint::from(i) / Bigint::from(j));
+                assert_eq!(Bigint::from(i % j), Bigint::from(i) % Bigint::from(j));
+            }
+        }
+        let big = Bigint::from_str("15241578753238836750495351562536198787501905199875019052101")
+            .unwrap();
+        let divisor = Bigint::from_str("123456789012345678901234567890").unwrap();
+        assert_eq!(big.div_rem(&divisor), (divisor.clone(), Bigint::from(1)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn div_by_zero_test() {
+        let _ = Bigint::from(1) / Bigint::new();
+    }
+
+    #[test]
+    fn pow_test() {
+        assert_eq!(Bigint::from(7).pow(0), Bigint::from(1));
+        assert_eq!(Bigint::new().pow(0), Bigint::from(1));
+        assert_eq!(Bigint::from(-2).pow(3), Bigint::from(-8));
+        assert_eq!(Bigint::from(-2).pow(4), Bigint::from(16));
+        assert_eq!(
+            Bigint::from(7).pow(20),
+            Bigint::from_str("79792266297612001").unwrap()
+        );
+    }
+
     #[ignore = "display_test"]
     #[test]
     fn display_test() {
@@ -0,0 +1,184 @@
+//! Uniformly distributed random `Bigint`s
+//!
+//! The randomness comes from whatever implements `RandomSource`, so the caller decides
+//! if it should be a real generator or a seeded one(for tests and reproducible fuzzing).
+//! Every value is produced with rejection sampling, so there is no modulo bias.
+
+use super::{Bigint, Sign};
+use std::ops::Range;
+
+/// Anything which can produce uniformly distributed 64 bit values
+pub trait RandomSource {
+    fn next_u64(&mut self) -> u64;
+}
+
+/// A small deterministic generator(SplitMix64), the same seed always gives the same sequence\
+/// NOT suitable for anything secret
+#[derive(Debug, Clone)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+}
+
+impl RandomSource for SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+/// A uniform value in 0..=max, rejects the values which would make some results more likely
+fn random_up_to<R: RandomSource + ?Sized>(max: u8, rng: &mut R) -> u8 {
+    let range = max as u64 + 1;
+    let limit = u64::MAX - u64::MAX % range;
+    loop {
+        let value = rng.next_u64();
+        if value < limit {
+            return (value % range) as u8;
+        }
+    }
+}
+
+impl Bigint {
+    /// A uniformly distributed number in 0..bound
+    ///
+    /// Panics if the bound is not positive
+    pub fn random_below<R: RandomSource + ?Sized>(bound: &Bigint, rng: &mut R) -> Bigint {
+        if !bound.is_positive() {
+            panic!("The upper bound of a random number must be positive");
+        }
+        let top = bound.digits.len() - 1;
+        loop {
+            // the most significant digit never goes above the one of the bound,
+            // so at least half of the candidates are accepted
+            let mut digits: Vec<u8> = (0..top).map(|_| random_up_to(9, rng)).collect();
+            digits.push(random_up_to(bound.digits[top], rng));
+
+            let candidate = Bigint::from_reversed_digits(digits, Sign::Positive);
+            if &candidate < bound {
+                return candidate;
+            }
+        }
+    }
+
+    /// A uniformly distributed number in lo..hi
+    ///
+    /// Panics if the range is empty
+    pub fn random_range<R: RandomSource + ?Sized>(range: Range<Bigint>, rng: &mut R) -> Bigint {
+        let Range { start, end } = range;
+        if start >= end {
+            panic!("Cannot generate a random number in an empty range");
+        }
+        let width = end - start.clone();
+        start + Bigint::random_below(&width, rng)
+    }
+
+    /// A uniformly distributed number in 0..2^bits
+    pub fn random_bits<R: RandomSource + ?Sized>(bits: usize, rng: &mut R) -> Bigint {
+        let mut res = Bigint::new();
+        let mut remaining = bits;
+        while remaining > 0 {
+            let chunk = remaining.min(32);
+            let value = rng.next_u64() >> (64 - chunk);
+            // res = res * 2^chunk + value
+            res = res * Bigint::from_u64(1 << chunk) + Bigint::from_u64(value);
+            remaining -= chunk;
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn seeded_is_deterministic_test() {
+        let bound = Bigint::from_str("123456789012345678901234567890").unwrap();
+        let first: Vec<_> = {
+            let mut rng = SplitMix64::new(42);
+            (0..10)
+                .map(|_| Bigint::random_below(&bound, &mut rng))
+                .collect()
+        };
+        let second: Vec<_> = {
+            let mut rng = SplitMix64::new(42);
+            (0..10)
+                .map(|_| Bigint::random_below(&bound, &mut rng))
+                .collect()
+        };
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn random_below_bounds_test() {
+        let mut rng = SplitMix64::new(1);
+        for bound in [1, 2, 9, 10, 11, 100, 101, 999, 1000].iter() {
+            let bound = Bigint::from(*bound);
+            for _ in 0..200 {
+                let value = Bigint::random_below(&bound, &mut rng);
+                assert!(!value.is_negative());
+                assert!(value < bound);
+            }
+        }
+    }
+
+    #[test]
+    fn random_below_is_uniform_test() {
+        let mut rng = SplitMix64::new(7);
+        let mut counts = [0; 13];
+        for _ in 0..13_000 {
+            let value = Bigint::random_below(&Bigint::from(13), &mut rng);
+            let index = value.to_string().parse::<usize>().unwrap();
+            counts[index] += 1;
+        }
+        for &count in counts.iter() {
+            assert!(count > 850 && count < 1150, "{:?}", counts);
+        }
+    }
+
+    #[test]
+    fn random_range_test() {
+        let mut rng = SplitMix64::new(3);
+        let mut seen = [false; 11];
+        for _ in 0..1000 {
+            let value = Bigint::random_range(Bigint::from(-5)..Bigint::from(6), &mut rng);
+            assert!(value >= Bigint::from(-5) && value < Bigint::from(6));
+            seen[(value.to_string().parse::<i32>().unwrap() + 5) as usize] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+    }
+
+    #[test]
+    #[should_panic]
+    fn random_range_empty_test() {
+        let mut rng = SplitMix64::new(3);
+        Bigint::random_range(Bigint::from(5)..Bigint::from(5), &mut rng);
+    }
+
+    #[test]
+    fn random_bits_test() {
+        let mut rng = SplitMix64::new(11);
+        assert_eq!(Bigint::random_bits(0, &mut rng), Bigint::new());
+
+        let limit = Bigint::from_str("1267650600228229401496703205376").unwrap(); // 2^100
+        let mut above_half = 0;
+        for _ in 0..200 {
+            let value = Bigint::random_bits(100, &mut rng);
+            assert!(!value.is_negative() && value < limit);
+            if value * Bigint::from(2) >= limit {
+                above_half += 1;
+            }
+        }
+        assert!(above_half > 70 && above_half < 130);
+    }
+}
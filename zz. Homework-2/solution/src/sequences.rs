@@ -0,0 +1,268 @@
+//! Classic integer sequences, mostly used as test vectors for the multiplication
+//!
+//! Each one is available as a function for a single element and as an endless iterator.
+
+use super::Bigint;
+
+/// The product of all the numbers in lo..=hi (1 for an empty range)\
+/// Splits the range in halves, so the multiplied numbers have similar lengths
+fn product_tree(lo: u64, hi: u64) -> Bigint {
+    if lo > hi {
+        return Bigint::from(1);
+    }
+    if hi - lo < 8 {
+        return (lo..=hi).fold(Bigint::from(1), |acc, n| acc * Bigint::from_u64(n));
+    }
+    let mid = lo + (hi - lo) / 2;
+    product_tree(lo, mid) * product_tree(mid + 1, hi)
+}
+
+/// n!
+pub fn factorial(n: u64) -> Bigint {
+    product_tree(2, n)
+}
+
+/// n choose k, 0 if k > n
+pub fn binomial(n: u64, k: u64) -> Bigint {
+    if k > n {
+        return Bigint::new();
+    }
+    let k = k.min(n - k);
+    product_tree(n - k + 1, n) / factorial(k)
+}
+
+/// The n-th Fibonacci number with F(0) = 0 and F(1) = 1
+pub fn fibonacci(n: u64) -> Bigint {
+    fibonacci_pair(n).0
+}
+
+/// (F(n), F(n + 1)) using fast doubling:\
+/// F(2k) = F(k) * (2 * F(k + 1) - F(k))\
+/// F(2k + 1) = F(k)^2 + F(k + 1)^2
+fn fibonacci_pair(n: u64) -> (Bigint, Bigint) {
+    if n == 0 {
+        return (Bigint::new(), Bigint::from(1));
+    }
+    let (a, b) = fibonacci_pair(n / 2);
+    let even = a.clone() * (Bigint::from(2) * b.clone() - a.clone());
+    let odd = a.clone() * a + b.clone() * b;
+    if n % 2 == 1 {
+        let next = even + odd.clone();
+        (odd, next)
+    } else {
+        (even, odd)
+    }
+}
+
+/// The n-th Catalan number, (2n choose n) / (n + 1)
+pub fn catalan(n: u64) -> Bigint {
+    binomial(2 * n, n) / Bigint::from_u64(n + 1)
+}
+
+/// 0!, 1!, 2!, ...
+#[derive(Debug, Clone)]
+pub struct Factorials {
+    n: u64,
+    current: Bigint,
+}
+
+impl Factorials {
+    pub fn new() -> Self {
+        Factorials {
+            n: 0,
+            current: Bigint::from(1),
+        }
+    }
+}
+
+impl Default for Factorials {
+    fn default() -> Self {
+        Factorials::new()
+    }
+}
+
+impl Iterator for Factorials {
+    type Item = Bigint;
+    fn next(&mut self) -> Option<Self::Item> {
+        let res = self.current.clone();
+        self.n += 1;
+        self.current = self.current.clone() * Bigint::from_u64(self.n);
+        Some(res)
+    }
+}
+
+/// (n choose 0), (n choose 1), ..., (n choose n) - a single row of Pascal's triangle
+#[derive(Debug, Clone)]
+pub struct BinomialRow {
+    n: u64,
+    k: u64,
+    current: Bigint,
+}
+
+impl BinomialRow {
+    pub fn new(n: u64) -> Self {
+        BinomialRow {
+            n,
+            k: 0,
+            current: Bigint::from(1),
+        }
+    }
+}
+
+impl Iterator for BinomialRow {
+    type Item = Bigint;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.k > self.n {
+            return None;
+        }
+        let res = self.current.clone();
+        // C(n, k + 1) = C(n, k) * (n - k) / (k + 1), the division is always exact
+        self.current =
+            self.current.clone() * Bigint::from_u64(self.n - self.k) / Bigint::from_u64(self.k + 1);
+        self.k += 1;
+        Some(res)
+    }
+}
+
+/// F(0), F(1), F(2), ...
+#[derive(Debug, Clone)]
+pub struct Fibonacci {
+    current: Bigint,
+    next: Bigint,
+}
+
+impl Fibonacci {
+    pub fn new() -> Self {
+        Fibonacci {
+            current: Bigint::new(),
+            next: Bigint::from(1),
+        }
+    }
+}
+
+impl Default for Fibonacci {
+    fn default() -> Self {
+        Fibonacci::new()
+    }
+}
+
+impl Iterator for Fibonacci {
+    type Item = Bigint;
+    fn next(&mut self) -> Option<Self::Item> {
+        let after = self.current.clone() + self.next.clone();
+        let res = std::mem::replace(&mut self.current, std::mem::replace(&mut self.next, after));
+        Some(res)
+    }
+}
+
+/// C(0), C(1), C(2), ... - the Catalan numbers
+#[derive(Debug, Clone)]
+pub struct Catalan {
+    n: u64,
+    current: Bigint,
+}
+
+impl Catalan {
+    pub fn new() -> Self {
+        Catalan {
+            n: 0,
+            current: Bigint::from(1),
+        }
+    }
+}
+
+impl Default for Catalan {
+    fn default() -> Self {
+        Catalan::new()
+    }
+}
+
+impl Iterator for Catalan {
+    type Item = Bigint;
+    fn next(&mut self) -> Option<Self::Item> {
+        let res = self.current.clone();
+        // C(n + 1) = C(n) * 2(2n + 1) / (n + 2), the division is always exact
+        self.current = self.current.clone() * Bigint::from_u64(2 * (2 * self.n + 1))
+            / Bigint::from_u64(self.n + 2);
+        self.n += 1;
+        Some(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn big(s: &str) -> Bigint {
+        Bigint::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn factorial_test() {
+        assert_eq!(factorial(0), Bigint::from(1));
+        assert_eq!(factorial(1), Bigint::from(1));
+        assert_eq!(factorial(10), Bigint::from(3_628_800));
+        assert_eq!(factorial(100).to_string().len(), 158);
+        assert!(factorial(100)
+            .to_string()
+            .starts_with("93326215443944152681699238856266700490715968264381621468"));
+        assert!(factorial(100).to_string().ends_with(&"0".repeat(24)));
+    }
+
+    #[test]
+    fn factorials_iter_test() {
+        for (n, value) in Factorials::new().take(30).enumerate() {
+            assert_eq!(value, factorial(n as u64));
+        }
+    }
+
+    #[test]
+    fn binomial_test() {
+        assert_eq!(binomial(0, 0), Bigint::from(1));
+        assert_eq!(binomial(5, 6), Bigint::new());
+        assert_eq!(binomial(10, 3), Bigint::from(120));
+        assert_eq!(binomial(100, 50), big("100891344545564193334812497256"));
+        let row: Vec<_> = BinomialRow::new(30).collect();
+        assert_eq!(row.len(), 31);
+        for (k, value) in row.into_iter().enumerate() {
+            assert_eq!(value, binomial(30, k as u64));
+        }
+    }
+
+    #[test]
+    fn fibonacci_test() {
+        assert_eq!(fibonacci(0), Bigint::new());
+        assert_eq!(fibonacci(1), Bigint::from(1));
+        assert_eq!(fibonacci(2), Bigint::from(1));
+        assert_eq!(fibonacci(10), Bigint::from(55));
+        assert_eq!(
+            fibonacci(1000),
+            big("43466557686937456435688527675040625802564660517371780402481729089536555417949051890403879840079255169295922593080322634775209689623239873322471161642996440906533187938298969649928516003704476137795166849228875")
+        );
+    }
+
+    #[test]
+    fn fibonacci_iter_test() {
+        for (n, value) in Fibonacci::new().take(100).enumerate() {
+            assert_eq!(value, fibonacci(n as u64));
+        }
+    }
+
+    #[test]
+    fn catalan_test() {
+        let first: Vec<_> = Catalan::new().take(10).collect();
+        let expected: Vec<_> = [1, 1, 2, 5, 14, 42, 132, 429, 1430, 4862]
+            .iter()
+            .map(|&n| Bigint::from(n))
+            .collect();
+        assert_eq!(first, expected);
+        assert_eq!(
+            catalan(100),
+            big("896519947090131496687170070074100632420837521538745909320")
+        );
+        for (n, value) in Catalan::new().take(40).enumerate() {
+            assert_eq!(value, catalan(n as u64));
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! Compares reading with the `Iterator`(a `Row` per row, which shares the column names), with
+//! `Csv::read_record`(a single reused record) and with `Csv::parallel`. Run with `cargo bench`.\
+//! The parallel reading is measured with more and more threads, up to the number of cores
+
+use solution::*;
+use std::io::BufReader;
+use std::time::{Duration, Instant};
+
+const ROWS: usize = 200_000;
+const RUNS: usize = 5;
+
+fn generate() -> String {
+    let mut data = String::from("id,name,email,age,city,notes\n");
+    for i in 0..ROWS {
+        data += &format!(
+            "{},User {},user{}@example.com,{},City {},\"note, with a comma\"\n",
+            i,
+            i,
+            i,
+            20 + i % 50,
+            i % 100
+        );
+    }
+    data
+}
+
+/// The fastest of a few runs, along with the sum of the ages so nothing is optimized away
+fn measure<F: FnMut() -> u64>(mut run: F) -> (Duration, u64) {
+    let mut best = Duration::MAX;
+    let mut checksum = 0;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        checksum = run();
+        best = best.min(start.elapsed());
+    }
+    (best, checksum)
+}
+
+fn report(name: &str, bytes: usize, (time, checksum): (Duration, u64)) {
+    let seconds = time.as_secs_f64();
+    println!(
+        "{:<22} {:>8.1} ms {:>10.0} rows/s {:>8.1} MiB/s (checksum {})",
+        name,
+        seconds * 1000.0,
+        ROWS as f64 / seconds,
+        bytes as f64 / seconds / (1 << 20) as f64,
+        checksum
+    );
+}
+
+fn main() {
+    let data = generate();
+
+    report(
+        "Iterator",
+        data.len(),
+        measure(|| {
+            Csv::new(BufReader::new(data.as_bytes()))
+                .unwrap()
+                .map(|row| row.unwrap()["age"].parse::<u64>().unwrap())
+                .sum()
+        }),
+    );
+
+    let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get());
+    let mut threads = 1;
+    while threads <= cores {
+        report(
+            &format!("parallel, threads: {}", threads),
+            data.len(),
+            measure(|| {
+                Csv::new(BufReader::new(data.as_bytes()))
+                    .unwrap()
+                    .parallel()
+                    .threads(threads)
+                    .run()
+                    .unwrap()
+                    .map(|row| row.unwrap()["age"].parse::<u64>().unwrap())
+                    .sum()
+            }),
+        );
+        threads *= 2;
+    }
+
+    report(
+        "read_record",
+        data.len(),
+        measure(|| {
+            let mut csv = Csv::new(BufReader::new(data.as_bytes())).unwrap();
+            let age = csv.column("age").unwrap();
+            let mut record = StringRecord::new();
+            let mut sum = 0;
+            while csv.read_record(&mut record).unwrap() {
+                sum += record[age].parse::<u64>().unwrap();
+            }
+            sum
+        }),
+    );
+
+    report(
+        "read_byte_record",
+        data.len(),
+        measure(|| {
+            let mut csv = Csv::new(BufReader::new(data.as_bytes())).unwrap();
+            let age = csv.column("age").unwrap();
+            let mut record = ByteRecord::new();
+            let mut sum = 0;
+            while csv.read_byte_record(&mut record).unwrap() {
+                sum += record[age]
+                    .iter()
+                    .fold(0, |n, &digit| n * 10 + u64::from(digit - b'0'));
+            }
+            sum
+        }),
+    );
+}
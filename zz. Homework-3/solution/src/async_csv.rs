@@ -0,0 +1,224 @@
+//! Reading and writing over the async I/O of `futures`, with the `async` feature.\
+//! Only the reading of the lines is async: they are put together into records and parsed the
+//! same way `Csv` does it, and the rows are written with a `CsvWriter`
+
+use crate::reader::{RecordState, Step};
+use crate::{check_record, split_header, CsvBuilder, CsvError, CsvWriter, Dialect, Position};
+use crate::{Row, StringRecord};
+use futures::io::{AsyncBufRead, AsyncWrite, AsyncWriteExt};
+use futures::ready;
+use futures::stream::{Stream, StreamExt};
+use std::convert::TryFrom;
+use std::future::poll_fn;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// How many bytes `write_to` collects before it writes them
+const WRITE_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Reads the lines of the records. What has been read is kept between the polls, so nothing
+/// is lost if the future which is reading is dropped
+struct AsyncRecordReader<R: AsyncBufRead + Unpin> {
+    inner: R,
+    state: RecordState,
+    /// Where the record which is being read starts
+    position: Option<Position>,
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncRecordReader<R> {
+    fn new(inner: R) -> Self {
+        AsyncRecordReader {
+            inner,
+            state: RecordState::new(Position::default()),
+            position: None,
+        }
+    }
+
+    /// Reads the rest of the line(at most `RecordState::limit` bytes) into `RecordState::bytes`,
+    /// the same way `BufRead::read_until` does
+    fn poll_read_line(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let limit = usize::try_from(self.state.limit()).unwrap_or(usize::MAX);
+        loop {
+            let available = ready!(Pin::new(&mut self.inner).poll_fill_buf(cx))?;
+            if available.is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+            let room = limit - self.state.bytes.len();
+            let available = &available[..available.len().min(room)];
+            let (used, done) = match available.iter().position(|&byte| byte == b'\n') {
+                Some(i) => (i + 1, true),
+                None => (available.len(), available.len() == room),
+            };
+            self.state.bytes.extend_from_slice(&available[..used]);
+            Pin::new(&mut self.inner).consume(used);
+            if done {
+                return Poll::Ready(Ok(()));
+            }
+        }
+    }
+
+    /// The same as `RecordReader::read_record`
+    fn poll_read_record(
+        &mut self,
+        cx: &mut Context<'_>,
+        dialect: &Dialect,
+        record: &mut StringRecord,
+    ) -> Poll<Result<bool, CsvError>> {
+        loop {
+            let position = match self.position {
+                Some(position) => position,
+                None => *self.position.insert(self.state.start_record()),
+            };
+            let step = match ready!(self.poll_read_line(cx)) {
+                Ok(()) => self.state.add_line(position, dialect, record),
+                Err(error) => Err(CsvError::IO(error)),
+            };
+            self.state.bytes.clear();
+            if !matches!(step, Ok(Step::Continues)) {
+                self.position = None;
+            }
+            match step? {
+                Step::Done(found) => return Poll::Ready(Ok(found)),
+                Step::Skipped | Step::Continues => {}
+            }
+        }
+    }
+}
+
+/// The async counterpart of `Csv`, a `Stream` of its rows.\
+/// There is no pipeline, the combinators of `StreamExt` do the same
+pub struct AsyncCsv<R: AsyncBufRead + Unpin> {
+    /// The columns of the rows, from the header
+    pub columns: Vec<String>,
+    header: Vec<String>,
+    reader: AsyncRecordReader<R>,
+    dialect: Dialect,
+    /// Without a header the first record has to be read to know the number of columns
+    pending: Option<StringRecord>,
+    /// The record which the stream reads into, reused for every row
+    scratch: StringRecord,
+    /// The column names which the rows share
+    names: Arc<[String]>,
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncCsv<R> {
+    /// A new csv in the default dialect, see `Dialect::default`
+    pub async fn new(reader: R) -> Result<Self, CsvError> {
+        CsvBuilder::new().from_async_reader(reader).await
+    }
+
+    pub async fn with_dialect(reader: R, dialect: Dialect) -> Result<Self, CsvError> {
+        CsvBuilder::from_dialect(dialect)
+            .from_async_reader(reader)
+            .await
+    }
+
+    pub(crate) async fn build(
+        reader: R,
+        dialect: Dialect,
+        max_record_size: usize,
+        lossy: bool,
+    ) -> Result<Self, CsvError> {
+        let mut reader = AsyncRecordReader::new(reader);
+        reader.state.max_record_size = max_record_size;
+        reader.state.lossy = lossy;
+
+        let mut first = StringRecord::new();
+        let found = poll_fn(|cx| reader.poll_read_record(cx, &dialect, &mut first)).await?;
+        let (columns, pending) = split_header(first, found, &dialect)?;
+
+        Ok(AsyncCsv {
+            names: columns.as_slice().into(),
+            header: columns.clone(),
+            columns,
+            reader,
+            dialect,
+            pending,
+            scratch: StringRecord::new(),
+        })
+    }
+
+    /// See `Csv::set_max_record_size`
+    pub fn set_max_record_size(&mut self, max_record_size: usize) {
+        self.reader.state.max_record_size = max_record_size;
+    }
+
+    /// See `Csv::set_lossy`
+    pub fn set_lossy(&mut self, lossy: bool) {
+        self.reader.state.lossy = lossy;
+    }
+
+    /// See `Csv::replaced_records`
+    pub fn replaced_records(&self) -> &[Position] {
+        &self.reader.state.replaced
+    }
+
+    pub fn dialect(&self) -> &Dialect {
+        &self.dialect
+    }
+
+    /// The same as the `columns` field
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Writes the header(if the dialect has one) and the rows in the dialect of the csv.
+    /// The text is written in pieces, not a write for every row
+    pub async fn write_to<W: AsyncWrite + Unpin>(mut self, mut writer: W) -> Result<(), CsvError> {
+        let mut buffer = CsvWriter::with_dialect(Vec::new(), self.dialect.clone())?;
+        if self.dialect.has_header {
+            buffer.write_header(&self.columns)?;
+        }
+        while let Some(row) = self.next().await {
+            buffer.write_row(&row?)?;
+            if buffer.get_mut().len() >= WRITE_BUFFER_SIZE {
+                writer.write_all(buffer.get_mut()).await?;
+                buffer.get_mut().clear();
+            }
+        }
+        writer.write_all(buffer.get_mut()).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+
+    /// The column names for the rows, made again only if `columns` was changed
+    fn shared_columns(&mut self) -> Arc<[String]> {
+        if *self.names != *self.columns {
+            self.names = self.columns.as_slice().into();
+        }
+        Arc::clone(&self.names)
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> Stream for AsyncCsv<R> {
+    type Item = Result<Row, CsvError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let csv = self.get_mut();
+        let found = match csv.pending.take() {
+            Some(pending) => {
+                csv.scratch = pending;
+                Ok(true)
+            }
+            None => ready!(csv
+                .reader
+                .poll_read_record(cx, &csv.dialect, &mut csv.scratch))
+            .map_err(|error| error.name_column(&csv.header)),
+        };
+
+        let row = match found {
+            Ok(true) => match check_record(&csv.header, &csv.scratch, &csv.dialect) {
+                Ok(()) => {
+                    let values = csv.scratch.to_vec();
+                    Some(Ok(Row::with_columns(csv.shared_columns(), values)))
+                }
+                Err(error) => Some(Err(error)),
+            },
+            Ok(false) => None,
+            Err(error) => Some(Err(error)),
+        };
+        Poll::Ready(row)
+    }
+}
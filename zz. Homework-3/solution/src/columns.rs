@@ -0,0 +1,90 @@
+//! Choosing, renaming and reordering the columns of the rows.\
+//! The input is still read whole, only the rows(and so what `write_to` writes) change.
+//! The pipeline gets the rows with the new columns, so it can only use the names
+//! of the columns which are kept
+
+use crate::{Csv, CsvError, StringRecord};
+use std::io::BufRead;
+
+impl<R: BufRead> Csv<R> {
+    /// The values of the record for the columns of the rows
+    pub(crate) fn project(&self, record: &StringRecord) -> Vec<String> {
+        self.fields.iter().map(|&i| record[i].to_string()).collect()
+    }
+
+    /// True if the columns are not exactly the ones of the input
+    #[cfg(feature = "serde")]
+    pub(crate) fn is_projected(&self) -> bool {
+        self.fields.len() != self.header.len()
+            || self.fields.iter().enumerate().any(|(i, &field)| i != field)
+    }
+
+    /// Puts the values of `record` for the columns of the rows in `projected`
+    #[cfg(feature = "serde")]
+    pub(crate) fn project_into(&self, record: &StringRecord, projected: &mut StringRecord) {
+        projected.clear();
+        for &i in &self.fields {
+            projected.push_field(&record[i]);
+        }
+        if let Some(position) = record.position() {
+            projected.set_position(position);
+        }
+    }
+
+    /// The indices of the given columns, each of them must exist and be given only once
+    fn positions(&self, names: &[&str]) -> Result<Vec<usize>, CsvError> {
+        let mut positions = Vec::with_capacity(names.len());
+        for &name in names {
+            match self.columns.iter().position(|column| column == name) {
+                Some(i) if !positions.contains(&i) => positions.push(i),
+                _ => return Err(CsvError::InvalidColumn(name.into())),
+            }
+        }
+        Ok(positions)
+    }
+
+    fn keep_positions(&mut self, positions: &[usize]) {
+        self.fields = positions.iter().map(|&i| self.fields[i]).collect();
+        self.columns = positions.iter().map(|&i| self.columns[i].clone()).collect();
+    }
+
+    /// Keeps only the given columns, in the given order
+    pub fn select_columns(&mut self, names: &[&str]) -> Result<(), CsvError> {
+        let positions = self.positions(names)?;
+        self.keep_positions(&positions);
+        Ok(())
+    }
+
+    /// The new name must not be taken by another column
+    pub fn rename_column(&mut self, from: &str, to: &str) -> Result<(), CsvError> {
+        let i = self.positions(&[from])?[0];
+        if from != to && self.columns.iter().any(|column| column == to) {
+            return Err(CsvError::InvalidColumn(to.into()));
+        }
+        self.columns[i] = to.into();
+        Ok(())
+    }
+
+    /// Puts the columns in the given order, every column must be given exactly once
+    pub fn reorder(&mut self, names: &[&str]) -> Result<(), CsvError> {
+        let positions = self.positions(names)?;
+        if let Some(missing) = self
+            .columns
+            .iter()
+            .find(|column| !names.contains(&column.as_str()))
+        {
+            return Err(CsvError::InvalidColumn(missing.clone()));
+        }
+        self.keep_positions(&positions);
+        Ok(())
+    }
+
+    pub fn drop_columns(&mut self, names: &[&str]) -> Result<(), CsvError> {
+        self.positions(names)?;
+        let kept: Vec<usize> = (0..self.columns.len())
+            .filter(|&i| !names.contains(&self.columns[i].as_str()))
+            .collect();
+        self.keep_positions(&kept);
+        Ok(())
+    }
+}
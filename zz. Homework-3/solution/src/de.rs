@@ -0,0 +1,326 @@
+//! Reads the records straight into a type with serde(behind the `serde` feature).\
+//! A struct gets its fields from the columns with the same names, a tuple or a `Vec`
+//! gets the values in order. The values are parsed as the fields need them:
+//! numbers, booleans(`true`/`false`/`1`/`0`), characters, unit enum variants by name
+//! and `Option`s, which are None for empty values
+
+use crate::{Csv, CsvError, ErrorDetails, StringRecord};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+use std::fmt;
+use std::io::BufRead;
+use std::marker::PhantomData;
+
+/// An iterator over the records converted to `T`, see `Csv::deserialize`
+pub struct DeserializeRecords<'a, R: BufRead, T> {
+    csv: &'a mut Csv<R>,
+    record: StringRecord,
+    /// The values of `record` for the columns of the rows, if they are not all the columns
+    projected: StringRecord,
+    _type: PhantomData<T>,
+}
+
+impl<R: BufRead> Csv<R> {
+    /// The records converted to `T`. If there is a pipeline, the rows which come out of it are
+    /// converted, the same ones as when iterating
+    pub fn deserialize<T: DeserializeOwned>(&mut self) -> DeserializeRecords<'_, R, T> {
+        DeserializeRecords {
+            csv: self,
+            record: StringRecord::new(),
+            projected: StringRecord::new(),
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<R: BufRead, T: DeserializeOwned> Iterator for DeserializeRecords<'_, R, T> {
+    type Item = Result<T, CsvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // the pipeline and the schema work on rows, so they are only made if they are needed
+        if !self.csv.pipeline.is_empty() || self.csv.schema.is_some() {
+            let row = match self.csv.next()? {
+                Ok(row) => row,
+                Err(error) => return Some(Err(error)),
+            };
+            self.record.clear();
+            for value in row.values() {
+                self.record.push_field(value);
+            }
+            return Some(self.record.deserialize(row.columns()));
+        }
+
+        match self.csv.read_record(&mut self.record) {
+            Ok(true) => {}
+            Ok(false) => return None,
+            Err(error) => return Some(Err(error)),
+        }
+
+        let record = if self.csv.is_projected() {
+            self.csv.project_into(&self.record, &mut self.projected);
+            &self.projected
+        } else {
+            &self.record
+        };
+        Some(record.deserialize(&self.csv.columns))
+    }
+}
+
+impl StringRecord {
+    /// Converts the record to `T`, the columns are used to find the fields of structs
+    pub fn deserialize<T: DeserializeOwned>(&self, columns: &[String]) -> Result<T, CsvError> {
+        T::deserialize(RecordDeserializer {
+            record: self,
+            columns,
+            next: 0,
+        })
+        .map_err(|error| {
+            let mut details = ErrorDetails::new(error.message);
+            details.position = self.position();
+            if let Some(column) = error.column {
+                details.column = Some(column);
+                details.column_name = columns.get(column).cloned();
+                details.excerpt = self.get(column).map(String::from);
+            }
+            CsvError::InvalidRow(details.into())
+        })
+    }
+}
+
+/// A value which does not fit the type, in the field with the given index
+#[derive(Debug)]
+struct DeError {
+    message: String,
+    column: Option<usize>,
+}
+
+impl DeError {
+    fn in_column(mut self, column: usize) -> Self {
+        self.column.get_or_insert(column);
+        self
+    }
+}
+
+impl fmt::Display for DeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for DeError {}
+
+impl de::Error for DeError {
+    fn custom<M: fmt::Display>(message: M) -> Self {
+        DeError {
+            message: message.to_string(),
+            column: None,
+        }
+    }
+}
+
+/// The whole record - a map from the column names to the values or a sequence of the values
+struct RecordDeserializer<'r> {
+    record: &'r StringRecord,
+    columns: &'r [String],
+    /// The index of the next field to give out
+    next: usize,
+}
+
+impl<'de, 'r> de::Deserializer<'de> for RecordDeserializer<'r> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_map(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_seq(self)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_seq(self)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_seq(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de, 'r> de::MapAccess<'de> for RecordDeserializer<'r> {
+    type Error = DeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, DeError> {
+        match self.columns.get(self.next) {
+            Some(column) if self.next < self.record.len() => seed
+                .deserialize(column.as_str().into_deserializer())
+                .map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, DeError> {
+        let column = self.next;
+        self.next += 1;
+        seed.deserialize(FieldDeserializer(&self.record[column]))
+            .map_err(|error| error.in_column(column))
+    }
+}
+
+impl<'de, 'r> de::SeqAccess<'de> for RecordDeserializer<'r> {
+    type Error = DeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, DeError> {
+        let column = self.next;
+        match self.record.get(column) {
+            Some(value) => {
+                self.next += 1;
+                seed.deserialize(FieldDeserializer(value))
+                    .map(Some)
+                    .map_err(|error| error.in_column(column))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// A single value, parsed as whatever the field needs
+struct FieldDeserializer<'r>(&'r str);
+
+impl FieldDeserializer<'_> {
+    fn parse<T>(&self, type_name: &str) -> Result<T, DeError>
+    where
+        T: std::str::FromStr,
+        T::Err: fmt::Display,
+    {
+        self.0.trim().parse().map_err(|error| {
+            de::Error::custom(format!(
+                "Cannot convert {:?} to {}: {}",
+                self.0, type_name, error
+            ))
+        })
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident: $type:ty,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+                visitor.$visit(self.parse::<$type>(stringify!($type))?)
+            }
+        )*
+    };
+}
+
+impl<'de, 'r> de::Deserializer<'de> for FieldDeserializer<'r> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_str(self.0)
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_i128 => visit_i128: i128,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_u128 => visit_u128: u128,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64,
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.0.trim().to_lowercase().as_str() {
+            "true" | "1" => visitor.visit_bool(true),
+            "false" | "0" => visitor.visit_bool(false),
+            _ => Err(de::Error::custom(format!(
+                "Cannot convert {:?} to bool",
+                self.0
+            ))),
+        }
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let mut chars = self.0.chars();
+        match (chars.next(), chars.next()) {
+            (Some(ch), None) => visitor.visit_char(ch),
+            _ => Err(de::Error::custom(format!(
+                "Cannot convert {:?} to char",
+                self.0
+            ))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        if self.0.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        if self.0.is_empty() {
+            visitor.visit_unit()
+        } else {
+            Err(de::Error::custom(format!(
+                "Expected an empty value, got {:?}",
+                self.0
+            )))
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
@@ -0,0 +1,236 @@
+//! The flavour of CSV being read and written - which characters separate and quote the fields,
+//! if there is a header, which lines are comments and so on
+
+use crate::parser::ParseMode;
+use crate::reader::DEFAULT_MAX_RECORD_SIZE;
+#[cfg(feature = "async")]
+use crate::AsyncCsv;
+use crate::{Csv, CsvError, DELIMITER, QUOTE};
+#[cfg(feature = "async")]
+use futures::io::AsyncBufRead;
+use std::io::BufRead;
+
+/// What ends a record when writing, both are accepted when reading
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineTerminator {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineTerminator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineTerminator::Lf => "\n",
+            LineTerminator::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Which values are quoted when writing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuoteStyle {
+    /// Every value is quoted(the column names in the header only when needed)
+    #[default]
+    Always,
+    /// Only the values which would be read back differently without quotes
+    Necessary,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dialect {
+    /// Separates the fields - ',' by default, '\t', ';' and '|' are also common
+    pub delimiter: char,
+    /// Encloses fields which contain the delimiter, the quote itself or line breaks
+    pub quote: char,
+    /// If set, the character after it is taken literally(e.g. `\"`),
+    /// otherwise a quote inside a quoted field is written twice
+    pub escape: Option<char>,
+    /// Lines starting with any of these are skipped(only between records)
+    pub comment_prefixes: Vec<String>,
+    /// If false, the first record is data and the columns are named "0", "1", ...
+    pub has_header: bool,
+    /// Trims the whitespace around the fields and the header names(not inside the quotes).
+    /// When writing, a space is put after each delimiter
+    pub trim: bool,
+    pub mode: ParseMode,
+    pub line_terminator: LineTerminator,
+    pub quote_style: QuoteStyle,
+}
+
+impl Default for Dialect {
+    /// The dialect which has always been used: comma separated, double quotes,
+    /// a header row, trimmed values and lenient parsing
+    fn default() -> Self {
+        Dialect::with_mode(ParseMode::default())
+    }
+}
+
+impl Dialect {
+    /// The default dialect in the given mode, trimming is only on in the lenient mode
+    pub fn with_mode(mode: ParseMode) -> Self {
+        Dialect {
+            delimiter: DELIMITER,
+            quote: QUOTE,
+            escape: None,
+            comment_prefixes: Vec::new(),
+            has_header: true,
+            trim: mode == ParseMode::Lenient,
+            mode,
+            line_terminator: LineTerminator::default(),
+            quote_style: QuoteStyle::default(),
+        }
+    }
+
+    /// Tab separated values
+    pub fn tsv() -> Self {
+        Dialect {
+            delimiter: '\t',
+            trim: false,
+            ..Dialect::default()
+        }
+    }
+
+    /// Semicolon separated values, which spreadsheets produce where ',' is the decimal separator
+    pub fn semicolon() -> Self {
+        Dialect {
+            delimiter: ';',
+            ..Dialect::default()
+        }
+    }
+
+    /// Checks that the special characters can be told apart
+    pub fn validate(&self) -> Result<(), CsvError> {
+        let invalid = |message: &str| Err(CsvError::InvalidDialect(message.into()));
+        if self.delimiter == self.quote {
+            return invalid("The delimiter and the quote must be different!");
+        }
+        if let Some(escape) = self.escape {
+            if escape == self.delimiter || escape == self.quote {
+                return invalid(
+                    "The escape character must differ from the delimiter and the quote!",
+                );
+            }
+        }
+        for &special in [self.delimiter, self.quote].iter() {
+            if special == '\n' || special == '\r' {
+                return invalid("Line breaks cannot be used as delimiters or quotes!");
+            }
+        }
+        if self.comment_prefixes.iter().any(String::is_empty) {
+            return invalid("A comment prefix must not be empty!");
+        }
+        Ok(())
+    }
+}
+
+/// Configures a `Csv` before it reads its header
+///
+///   let csv = CsvBuilder::new()
+///       .delimiter(';')
+///       .comment("#")
+///       .from_reader(reader)?;
+#[derive(Debug, Clone)]
+pub struct CsvBuilder {
+    dialect: Dialect,
+    max_record_size: usize,
+    lossy: bool,
+}
+
+impl Default for CsvBuilder {
+    fn default() -> Self {
+        CsvBuilder::new()
+    }
+}
+
+impl CsvBuilder {
+    pub fn new() -> Self {
+        CsvBuilder::from_dialect(Dialect::default())
+    }
+
+    pub fn from_dialect(dialect: Dialect) -> Self {
+        CsvBuilder {
+            dialect,
+            max_record_size: DEFAULT_MAX_RECORD_SIZE,
+            lossy: false,
+        }
+    }
+
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.dialect.delimiter = delimiter;
+        self
+    }
+
+    pub fn quote(mut self, quote: char) -> Self {
+        self.dialect.quote = quote;
+        self
+    }
+
+    pub fn escape(mut self, escape: Option<char>) -> Self {
+        self.dialect.escape = escape;
+        self
+    }
+
+    /// Adds a prefix for comment lines, can be called more than once
+    pub fn comment(mut self, prefix: &str) -> Self {
+        self.dialect.comment_prefixes.push(prefix.into());
+        self
+    }
+
+    pub fn has_header(mut self, has_header: bool) -> Self {
+        self.dialect.has_header = has_header;
+        self
+    }
+
+    pub fn trim(mut self, trim: bool) -> Self {
+        self.dialect.trim = trim;
+        self
+    }
+
+    pub fn mode(mut self, mode: ParseMode) -> Self {
+        self.dialect.mode = mode;
+        self
+    }
+
+    pub fn line_terminator(mut self, line_terminator: LineTerminator) -> Self {
+        self.dialect.line_terminator = line_terminator;
+        self
+    }
+
+    pub fn quote_style(mut self, quote_style: QuoteStyle) -> Self {
+        self.dialect.quote_style = quote_style;
+        self
+    }
+
+    /// See `Csv::set_max_record_size`
+    pub fn max_record_size(mut self, max_record_size: usize) -> Self {
+        self.max_record_size = max_record_size;
+        self
+    }
+
+    /// See `Csv::set_lossy`
+    pub fn lossy(mut self, lossy: bool) -> Self {
+        self.lossy = lossy;
+        self
+    }
+
+    pub fn dialect(&self) -> &Dialect {
+        &self.dialect
+    }
+
+    /// Validates the dialect and reads the header(if there is one)
+    pub fn from_reader<R: BufRead>(self, reader: R) -> Result<Csv<R>, CsvError> {
+        self.dialect.validate()?;
+        Csv::build(reader, self.dialect, self.max_record_size, self.lossy)
+    }
+
+    /// The same as `from_reader`, for an async reader
+    #[cfg(feature = "async")]
+    pub async fn from_async_reader<R: AsyncBufRead + Unpin>(
+        self,
+        reader: R,
+    ) -> Result<AsyncCsv<R>, CsvError> {
+        self.dialect.validate()?;
+        AsyncCsv::build(reader, self.dialect, self.max_record_size, self.lossy).await
+    }
+}
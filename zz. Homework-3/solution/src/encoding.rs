@@ -0,0 +1,265 @@
+//! Reading input which is not UTF-8. A `Decoder` turns UTF-16 and the single byte encodings
+//! into UTF-8 while it is read, so a `Csv` can read it
+//!
+//! What cannot be decoded(e.g. a lone surrogate in UTF-16) is passed on as the byte 0xFF,
+//! which is never valid UTF-8, so whoever reads the text decides what to do with it - `Csv`
+//! fails the record or, in lossy mode, replaces it with U+FFFD, see `Csv::set_lossy`
+
+use std::io::{self, BufRead, Read};
+
+/// How many bytes of the input are read at once
+const BLOCK_SIZE: usize = 8 * 1024;
+
+/// How many bytes from the start of the input are used to detect the encoding
+const DETECT_SIZE: usize = 64;
+
+/// Stands for what could not be decoded
+const INVALID: u8 = 0xFF;
+
+/// The characters of Windows-1251 from 0x80 to 0xBF(0 for 0x98, which is not used),
+/// the ones from 0xC0 are 'А'..='я'
+const WINDOWS_1251: [u16; 64] = [
+    0x0402, 0x0403, 0x201A, 0x0453, 0x201E, 0x2026, 0x2020, 0x2021, // 0x80
+    0x20AC, 0x2030, 0x0409, 0x2039, 0x040A, 0x040C, 0x040B, 0x040F, // 0x88
+    0x0452, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014, // 0x90
+    0x0000, 0x2122, 0x0459, 0x203A, 0x045A, 0x045C, 0x045B, 0x045F, // 0x98
+    0x00A0, 0x040E, 0x045E, 0x0408, 0x00A4, 0x0490, 0x00A6, 0x00A7, // 0xA0
+    0x0401, 0x00A9, 0x0404, 0x00AB, 0x00AC, 0x00AD, 0x00AE, 0x0407, // 0xA8
+    0x00B0, 0x00B1, 0x0406, 0x0456, 0x0491, 0x00B5, 0x00B6, 0x00B7, // 0xB0
+    0x0451, 0x2116, 0x0454, 0x00BB, 0x0458, 0x0405, 0x0455, 0x0457, // 0xB8
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// Cyrillic, what Windows uses for Bulgarian and Russian text
+    Windows1251,
+    /// ISO-8859-1, every byte is the character with the same code
+    Latin1,
+}
+
+impl Encoding {
+    /// Guesses the encoding from the start of the input: by the byte order mark or, for
+    /// UTF-16 without one, by the zero bytes which its ASCII characters(at least the
+    /// delimiters and the line breaks) have - text in the other encodings has none.
+    /// Anything else is taken for UTF-8, the single byte encodings cannot be told apart
+    pub fn detect(start: &[u8]) -> Encoding {
+        let marked = [Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be];
+        if let Some(&encoding) = marked
+            .iter()
+            .find(|encoding| start.starts_with(encoding.bom()))
+        {
+            return encoding;
+        }
+
+        let start = &start[..start.len().min(DETECT_SIZE)];
+        let zeros = |at: usize| start.chunks_exact(2).filter(|unit| unit[at] == 0).count();
+        match (zeros(0), zeros(1)) {
+            (0, second) if second > 0 => Encoding::Utf16Le,
+            (first, 0) if first > 0 => Encoding::Utf16Be,
+            _ => Encoding::Utf8,
+        }
+    }
+
+    /// The byte order mark, which is skipped at the start of the input
+    pub fn bom(&self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => b"\xEF\xBB\xBF",
+            Encoding::Utf16Le => b"\xFF\xFE",
+            Encoding::Utf16Be => b"\xFE\xFF",
+            Encoding::Windows1251 | Encoding::Latin1 => b"",
+        }
+    }
+}
+
+fn windows_1251(byte: u8) -> Option<char> {
+    match byte {
+        0x00..=0x7F => Some(char::from(byte)),
+        0xC0..=0xFF => char::from_u32(0x0410 + u32::from(byte - 0xC0)),
+        _ => match WINDOWS_1251[usize::from(byte - 0x80)] {
+            0 => None,
+            code => char::from_u32(u32::from(code)),
+        },
+    }
+}
+
+fn push_char(output: &mut Vec<u8>, ch: char) {
+    output.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
+}
+
+/// Decodes the whole code units of `input` and returns how many bytes were used. Unless the
+/// input has ended, a high surrogate at the end waits for the rest of its character
+fn decode_utf16(input: &[u8], little_endian: bool, last: bool, output: &mut Vec<u8>) -> usize {
+    let unit = |pair: &[u8]| {
+        let pair = [pair[0], pair[1]];
+        if little_endian {
+            u16::from_le_bytes(pair)
+        } else {
+            u16::from_be_bytes(pair)
+        }
+    };
+
+    let mut end = input.len() - input.len() % 2;
+    if !last && end >= 2 && (0xD800..0xDC00).contains(&unit(&input[end - 2..end])) {
+        end -= 2;
+    }
+    for ch in char::decode_utf16(input[..end].chunks_exact(2).map(unit)) {
+        match ch {
+            Ok(ch) => push_char(output, ch),
+            Err(_) => output.push(INVALID),
+        }
+    }
+    if last && end < input.len() {
+        // half a code unit
+        output.push(INVALID);
+        return input.len();
+    }
+    end
+}
+
+/// Reads input in some encoding and gives it out as UTF-8, which `Csv` can read:
+///
+///   let csv = Csv::new(Decoder::with_encoding(file, Encoding::Windows1251))?;
+///
+/// The byte offsets in the positions of the records are then the ones in the UTF-8 text
+pub struct Decoder<R: Read> {
+    inner: R,
+    /// None until it is detected from the start of the input
+    encoding: Option<Encoding>,
+    started: bool,
+    /// What has been read and not decoded yet - the rest of a character may be missing
+    input: Vec<u8>,
+    /// The decoded text and how much of it has been given out
+    output: Vec<u8>,
+    consumed: usize,
+    done: bool,
+}
+
+impl<R: Read> Decoder<R> {
+    /// Detects the encoding from the start of the input, see `Encoding::detect`
+    pub fn new(inner: R) -> Self {
+        Decoder {
+            inner,
+            encoding: None,
+            started: false,
+            input: Vec::new(),
+            output: Vec::new(),
+            consumed: 0,
+            done: false,
+        }
+    }
+
+    /// The byte order mark of the encoding is still skipped, if the input starts with it
+    pub fn with_encoding(inner: R, encoding: Encoding) -> Self {
+        Decoder {
+            encoding: Some(encoding),
+            ..Decoder::new(inner)
+        }
+    }
+
+    /// None if it is detected and nothing has been read yet
+    pub fn encoding(&self) -> Option<Encoding> {
+        self.encoding
+    }
+
+    /// What has been read from it and not given out yet is lost
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Reads the next block of the input, 0 at its end
+    fn read_input(&mut self) -> io::Result<usize> {
+        let mut block = [0; BLOCK_SIZE];
+        let read = loop {
+            match self.inner.read(&mut block) {
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                res => break res?,
+            }
+        };
+        self.input.extend_from_slice(&block[..read]);
+        Ok(read)
+    }
+
+    /// Finds the encoding(unless it is given) and skips the byte order mark
+    fn start(&mut self) -> io::Result<Encoding> {
+        while self.input.len() < DETECT_SIZE && self.read_input()? > 0 {}
+        let encoding = match self.encoding {
+            Some(encoding) => encoding,
+            None => Encoding::detect(&self.input),
+        };
+        if self.input.starts_with(encoding.bom()) {
+            self.input.drain(..encoding.bom().len());
+        }
+        self.encoding = Some(encoding);
+        self.started = true;
+        Ok(encoding)
+    }
+
+    /// Decodes as much of the input as there are whole characters for
+    fn decode(&mut self, encoding: Encoding, last: bool) {
+        let used = match encoding {
+            // what is not UTF-8 is left for the reader to find
+            Encoding::Utf8 => {
+                self.output.extend_from_slice(&self.input);
+                self.input.len()
+            }
+            Encoding::Utf16Le | Encoding::Utf16Be => decode_utf16(
+                &self.input,
+                encoding == Encoding::Utf16Le,
+                last,
+                &mut self.output,
+            ),
+            Encoding::Windows1251 => {
+                for &byte in &self.input {
+                    match windows_1251(byte) {
+                        Some(ch) => push_char(&mut self.output, ch),
+                        None => self.output.push(INVALID),
+                    }
+                }
+                self.input.len()
+            }
+            Encoding::Latin1 => {
+                for &byte in &self.input {
+                    push_char(&mut self.output, char::from(byte));
+                }
+                self.input.len()
+            }
+        };
+        self.input.drain(..used);
+    }
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let read = available.len().min(buf.len());
+        buf[..read].copy_from_slice(&available[..read]);
+        self.consume(read);
+        Ok(read)
+    }
+}
+
+impl<R: Read> BufRead for Decoder<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.consumed == self.output.len() && !self.done {
+            self.output.clear();
+            self.consumed = 0;
+            let encoding = match self.encoding {
+                Some(encoding) if self.started => encoding,
+                _ => self.start()?,
+            };
+            self.decode(encoding, false);
+            if self.output.is_empty() && self.read_input()? == 0 {
+                self.decode(encoding, true);
+                self.done = true;
+            }
+        }
+        Ok(&self.output[self.consumed..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.consumed = (self.consumed + amt).min(self.output.len());
+    }
+}
@@ -0,0 +1,200 @@
+//! The errors of the crate - the ones about a record also tell where in the input it is,
+//! which field is wrong and what the text around the problem looks like
+
+use crate::{FilterError, StageError};
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// The details are boxed to keep `Result<_, CsvError>` small
+#[derive(Debug)]
+pub enum CsvError {
+    IO(io::Error),
+    ParseError(Box<ErrorDetails>),
+    InvalidHeader(String),
+    InvalidRow(Box<ErrorDetails>),
+    InvalidColumn(String),
+    InvalidDialect(String),
+    /// A stage of the pipeline returned an error, see `Csv::filter_rows` and the others
+    StageError(StageError),
+    /// A filter expression which cannot be compiled, see `Filter`
+    InvalidFilter(FilterError),
+    /// A schema which cannot be read or written
+    InvalidSchema(String),
+}
+
+/// Where a record starts in the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    /// The number of the record, the first one is 1(the header counts as a record)
+    pub record: usize,
+    /// The physical line where the record starts, the first line is 1
+    pub line: usize,
+    /// The number of bytes before the start of the record
+    pub byte: u64,
+}
+
+/// What is wrong with a record and where
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ErrorDetails {
+    pub message: String,
+    /// None if the record was not read from the input(e.g. `Csv::parse_line`)
+    pub position: Option<Position>,
+    /// The index of the field with the problem, the first one is 0
+    pub column: Option<usize>,
+    /// The name of that column, if there is such a column
+    pub column_name: Option<String>,
+    /// The text around the problem
+    pub excerpt: Option<String>,
+}
+
+/// How many characters on each side of the problem go in an excerpt
+const EXCERPT_CHARS: usize = 20;
+
+/// The text around the byte index `at`, which must be on a character boundary
+pub(crate) fn excerpt(text: &str, at: usize) -> String {
+    let (before, after) = text.split_at(at);
+    let start = before
+        .char_indices()
+        .rev()
+        .nth(EXCERPT_CHARS - 1)
+        .map_or(0, |(i, _)| i);
+    let end = after
+        .char_indices()
+        .nth(EXCERPT_CHARS)
+        .map_or(after.len(), |(i, _)| i);
+    text[start..at + end].to_string()
+}
+
+impl ErrorDetails {
+    pub fn new(message: impl Into<String>) -> Self {
+        ErrorDetails {
+            message: message.into(),
+            ..ErrorDetails::default()
+        }
+    }
+
+    pub fn with_column(mut self, column: usize) -> Self {
+        self.column = Some(column);
+        self
+    }
+
+    pub fn with_excerpt(mut self, excerpt: impl Into<String>) -> Self {
+        self.excerpt = Some(excerpt.into());
+        self
+    }
+}
+
+impl From<String> for Box<ErrorDetails> {
+    fn from(message: String) -> Self {
+        Box::new(ErrorDetails::new(message))
+    }
+}
+
+impl From<&str> for Box<ErrorDetails> {
+    fn from(message: &str) -> Self {
+        Box::new(ErrorDetails::new(message))
+    }
+}
+
+impl CsvError {
+    /// The details of a parse error or an invalid row
+    pub fn details(&self) -> Option<&ErrorDetails> {
+        match self {
+            CsvError::ParseError(details) | CsvError::InvalidRow(details) => Some(details),
+            _ => None,
+        }
+    }
+
+    fn details_mut(&mut self) -> Option<&mut ErrorDetails> {
+        match self {
+            CsvError::ParseError(details) | CsvError::InvalidRow(details) => Some(details),
+            _ => None,
+        }
+    }
+
+    /// Sets the position of the record, unless the error already has one
+    pub(crate) fn locate(mut self, position: Position) -> Self {
+        if let Some(details) = self.details_mut() {
+            details.position.get_or_insert(position);
+        }
+        self
+    }
+
+    /// Finds the name of the column with the problem
+    pub(crate) fn name_column(mut self, columns: &[String]) -> Self {
+        if let Some(details) = self.details_mut() {
+            if let Some(column) = details.column {
+                if details.column_name.is_none() {
+                    details.column_name = columns.get(column).cloned();
+                }
+            }
+        }
+        self
+    }
+}
+
+impl fmt::Display for ErrorDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+
+        let mut location = Vec::new();
+        if let Some(position) = &self.position {
+            location.push(format!(
+                "record {} starting at line {}, byte offset {}",
+                position.record, position.line, position.byte
+            ));
+        }
+        match (self.column, &self.column_name) {
+            (Some(column), Some(name)) => location.push(format!("column {} {:?}", column, name)),
+            (Some(column), None) => location.push(format!("column {}", column)),
+            _ => {}
+        }
+        if !location.is_empty() {
+            write!(f, " ({})", location.join(", "))?;
+        }
+
+        if let Some(excerpt) = &self.excerpt {
+            write!(f, " near {:?}", excerpt)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::IO(error) => write!(f, "I/O error: {}", error),
+            CsvError::ParseError(details) => write!(f, "Parse error: {}", details),
+            CsvError::InvalidHeader(message) => write!(f, "Invalid header: {}", message),
+            CsvError::InvalidRow(details) => write!(f, "Invalid row: {}", details),
+            CsvError::InvalidColumn(column) => write!(f, "Invalid column: {}", column),
+            CsvError::InvalidDialect(message) => write!(f, "Invalid dialect: {}", message),
+            CsvError::StageError(error) => write!(f, "Pipeline error: {}", error),
+            CsvError::InvalidFilter(error) => write!(f, "Invalid filter: {}", error),
+            CsvError::InvalidSchema(message) => write!(f, "Invalid schema: {}", message),
+        }
+    }
+}
+
+impl Error for CsvError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CsvError::IO(error) => Some(error),
+            CsvError::StageError(error) => Some(error.source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<FilterError> for CsvError {
+    fn from(error: FilterError) -> Self {
+        CsvError::InvalidFilter(error)
+    }
+}
+
+impl From<io::Error> for CsvError {
+    fn from(error: io::Error) -> Self {
+        CsvError::IO(error)
+    }
+}
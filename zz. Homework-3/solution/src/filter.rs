@@ -0,0 +1,589 @@
+//! Filter expressions, compiled once and then evaluated for every row
+
+use crate::{Csv, CsvError, ErrorDetails, Row};
+use regex::Regex;
+use std::cmp::Ordering;
+use std::fmt;
+use std::io::BufRead;
+
+/// A compiled filter expression, for choosing rows without writing a closure, e.g.
+/// `age > 30 && name ~ "^Ada"` or `city in ["Sofia", "London"] || country is null`.
+///
+/// - a column is a name(`birth_date`) or any text in backticks(`` `birth date` ``)
+/// - `==`, `!=`, `<`, `<=`, `>`, `>=` compare as numbers if both sides are numbers(decimal
+///   literals, "NaN" and "inf" are text) and as text otherwise, a number compared with a value which is not one is an error
+/// - `~` and `!~` match(or do not match) a regular expression, which must be a string
+/// - `in [...]` checks for any of the values in the list
+/// - `is null` and `is not null` check for an empty value, every other comparison with an
+///   empty value is false
+/// - `&&`, `||`, `!` and parentheses, `&&` binds tighter than `||`
+#[derive(Debug, Clone)]
+pub struct Filter {
+    expression: String,
+    root: Expr,
+}
+
+/// A filter expression which cannot be compiled, `offset` is the byte where the problem is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterError {
+    pub message: String,
+    pub expression: String,
+    pub offset: usize,
+}
+
+impl fmt::Display for FilterError {
+    /// The message, then the expression with a `^` under the problem
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let column = self.expression[..self.offset].chars().count();
+        write!(
+            f,
+            "{} at character {}\n    {}\n    {}^",
+            self.message,
+            column + 1,
+            self.expression,
+            " ".repeat(column)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    /// None if the values cannot be ordered(NaN), then they are only unequal
+    fn holds(self, ordering: Option<Ordering>) -> bool {
+        let ordering = match ordering {
+            Some(ordering) => ordering,
+            None => return self == CompareOp::Ne,
+        };
+        match self {
+            CompareOp::Eq => ordering == Ordering::Equal,
+            CompareOp::Ne => ordering != Ordering::Equal,
+            CompareOp::Lt => ordering == Ordering::Less,
+            CompareOp::Le => ordering != Ordering::Greater,
+            CompareOp::Gt => ordering == Ordering::Greater,
+            CompareOp::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    /// The offset is where the name is in the expression
+    Column {
+        name: String,
+        offset: usize,
+    },
+    Text(String),
+    Number(f64),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare {
+        left: Operand,
+        op: CompareOp,
+        right: Operand,
+    },
+    Matches {
+        operand: Operand,
+        regex: Regex,
+        negated: bool,
+    },
+    In {
+        operand: Operand,
+        list: Vec<Operand>,
+    },
+    IsNull {
+        operand: Operand,
+        negated: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Text(String),
+    Number(f64),
+    Op(&'static str),
+    End,
+}
+
+/// The symbols, the longer ones first so that `<=` is not read as `<`
+const SYMBOLS: [&str; 16] = [
+    "&&", "||", "==", "!=", "<=", ">=", "!~", "<", ">", "~", "!", "(", ")", "[", "]", ",",
+];
+
+/// Splits the expression into tokens, each with the byte where it starts
+fn tokenize(expression: &str) -> Result<Vec<(Token, usize)>, (String, usize)> {
+    let mut tokens = Vec::new();
+    let mut rest = expression.char_indices().peekable();
+
+    while let Some(&(start, ch)) = rest.peek() {
+        if ch.is_whitespace() {
+            rest.next();
+        } else if ch == '"' || ch == '`' {
+            rest.next();
+            let mut text = String::new();
+            loop {
+                match rest.next() {
+                    Some((_, '\\')) if ch == '"' => match rest.next() {
+                        Some((_, escaped)) => text.push(escaped),
+                        None => return Err(("Unterminated string".into(), start)),
+                    },
+                    Some((_, end)) if end == ch => break,
+                    Some((_, other)) => text.push(other),
+                    None if ch == '"' => return Err(("Unterminated string".into(), start)),
+                    None => return Err(("Unterminated column name".into(), start)),
+                }
+            }
+            let token = if ch == '"' {
+                Token::Text(text)
+            } else {
+                Token::Name(text)
+            };
+            tokens.push((token, start));
+        } else if ch.is_ascii_digit() || ch == '-' || ch == '.' {
+            let mut end = start;
+            while let Some(&(i, digit)) = rest.peek() {
+                if !(digit.is_ascii_alphanumeric() || digit == '.' || (i == start && digit == '-'))
+                {
+                    break;
+                }
+                end = i + digit.len_utf8();
+                rest.next();
+            }
+            match parse_number(&expression[start..end]) {
+                Some(number) => tokens.push((Token::Number(number), start)),
+                None => return Err(("Invalid number".into(), start)),
+            }
+        } else if ch.is_alphabetic() || ch == '_' {
+            let mut name = String::new();
+            while let Some(&(_, letter)) = rest.peek() {
+                if !(letter.is_alphanumeric() || letter == '_') {
+                    break;
+                }
+                name.push(letter);
+                rest.next();
+            }
+            tokens.push((Token::Name(name), start));
+        } else {
+            match SYMBOLS
+                .iter()
+                .find(|symbol| expression[start..].starts_with(*symbol))
+            {
+                Some(symbol) => {
+                    for _ in 0..symbol.len() {
+                        rest.next();
+                    }
+                    tokens.push((Token::Op(symbol), start));
+                }
+                None => return Err((format!("Unexpected character {:?}", ch), start)),
+            }
+        }
+    }
+
+    tokens.push((Token::End, expression.len()));
+    Ok(tokens)
+}
+
+/// A recursive descent parser over the tokens
+struct Parser<'e> {
+    tokens: Vec<(Token, usize)>,
+    next: usize,
+    expression: &'e str,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.next].0
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens[self.next].1
+    }
+
+    fn advance(&mut self) -> (Token, usize) {
+        let token = self.tokens[self.next].clone();
+        if self.next + 1 < self.tokens.len() {
+            self.next += 1;
+        }
+        token
+    }
+
+    fn error<T>(&self, message: impl Into<String>, offset: usize) -> Result<T, FilterError> {
+        Err(FilterError {
+            message: message.into(),
+            expression: self.expression.into(),
+            offset,
+        })
+    }
+
+    /// Moves on if the next token is the given symbol
+    fn eat(&mut self, symbol: &str) -> bool {
+        if matches!(self.peek(), Token::Op(op) if *op == symbol) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Moves on if the next token is the given keyword, in any case
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Token::Name(name) if name.eq_ignore_ascii_case(keyword) => {
+                self.advance();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), FilterError> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            self.error(format!("Expected `{}`", symbol), self.offset())
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, FilterError> {
+        let mut left = self.and()?;
+        while self.eat("||") {
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, FilterError> {
+        let mut left = self.unary()?;
+        while self.eat("&&") {
+            left = Expr::And(Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, FilterError> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("(") {
+            let inner = self.or()?;
+            self.expect(")")?;
+            return Ok(inner);
+        }
+        self.condition()
+    }
+
+    fn condition(&mut self) -> Result<Expr, FilterError> {
+        let operand = self.operand()?;
+        let offset = self.offset();
+
+        if self.eat_keyword("is") {
+            let negated = self.eat_keyword("not");
+            if !self.eat_keyword("null") {
+                return self.error("Expected `null`", self.offset());
+            }
+            return Ok(Expr::IsNull { operand, negated });
+        }
+
+        if self.eat_keyword("in") {
+            self.expect("[")?;
+            let mut list = Vec::new();
+            if !self.eat("]") {
+                loop {
+                    list.push(self.literal()?);
+                    if self.eat("]") {
+                        break;
+                    }
+                    self.expect(",")?;
+                }
+            }
+            return Ok(Expr::In { operand, list });
+        }
+
+        let op = match self.advance() {
+            (Token::Op("=="), _) => CompareOp::Eq,
+            (Token::Op("!="), _) => CompareOp::Ne,
+            (Token::Op("<"), _) => CompareOp::Lt,
+            (Token::Op("<="), _) => CompareOp::Le,
+            (Token::Op(">"), _) => CompareOp::Gt,
+            (Token::Op(">="), _) => CompareOp::Ge,
+            (Token::Op(symbol), _) if symbol == "~" || symbol == "!~" => {
+                let pattern_offset = self.offset();
+                let pattern = match self.advance() {
+                    (Token::Text(pattern), _) => pattern,
+                    _ => return self.error("Expected a regular expression string", pattern_offset),
+                };
+                let regex = match Regex::new(&pattern) {
+                    Ok(regex) => regex,
+                    Err(error) => {
+                        return self.error(
+                            format!("Invalid regular expression: {}", error),
+                            pattern_offset,
+                        )
+                    }
+                };
+                return Ok(Expr::Matches {
+                    operand,
+                    regex,
+                    negated: symbol == "!~",
+                });
+            }
+            _ => return self.error("Expected a comparison, `~`, `in` or `is`", offset),
+        };
+        let right = self.operand()?;
+        Ok(Expr::Compare {
+            left: operand,
+            op,
+            right,
+        })
+    }
+
+    fn operand(&mut self) -> Result<Operand, FilterError> {
+        match self.peek() {
+            Token::Name(_) => match self.advance() {
+                (Token::Name(name), offset) => Ok(Operand::Column { name, offset }),
+                _ => unreachable!(),
+            },
+            _ => self.literal(),
+        }
+    }
+
+    fn literal(&mut self) -> Result<Operand, FilterError> {
+        match self.advance() {
+            (Token::Text(text), _) => Ok(Operand::Text(text)),
+            (Token::Number(number), _) => Ok(Operand::Number(number)),
+            (Token::End, offset) => self.error("Unexpected end of the expression", offset),
+            (_, offset) => self.error("Expected a value", offset),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Value<'r> {
+    Text(&'r str),
+    Number(f64),
+}
+
+impl Filter {
+    pub fn compile(expression: &str) -> Result<Self, FilterError> {
+        let tokens = tokenize(expression).map_err(|(message, offset)| FilterError {
+            message,
+            expression: expression.into(),
+            offset,
+        })?;
+        let mut parser = Parser {
+            tokens,
+            next: 0,
+            expression,
+        };
+        let root = parser.or()?;
+        if *parser.peek() != Token::End {
+            return parser.error("Expected `&&`, `||` or the end", parser.offset());
+        }
+        Ok(Filter {
+            expression: expression.into(),
+            root,
+        })
+    }
+
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// Checks that every column in the expression is one of the given columns
+    pub fn check_columns(&self, columns: &[String]) -> Result<(), FilterError> {
+        let mut used = Vec::new();
+        self.root.columns(&mut used);
+        match used
+            .into_iter()
+            .find(|(name, _)| !columns.iter().any(|column| column == name))
+        {
+            Some((name, offset)) => Err(FilterError {
+                message: format!("There is no column {:?}", name),
+                expression: self.expression.clone(),
+                offset,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Evaluates the expression for the row, a value which cannot be compared the way the
+    /// expression needs is an invalid row
+    pub fn matches(&self, row: &Row) -> Result<bool, CsvError> {
+        self.root.eval(row)
+    }
+}
+
+impl Expr {
+    fn columns<'e>(&'e self, used: &mut Vec<(&'e str, usize)>) {
+        let mut push = |operand: &'e Operand| {
+            if let Operand::Column { name, offset } = operand {
+                used.push((name, *offset));
+            }
+        };
+        match self {
+            Expr::And(left, right) | Expr::Or(left, right) => {
+                left.columns(used);
+                right.columns(used);
+            }
+            Expr::Not(inner) => inner.columns(used),
+            Expr::Compare { left, right, .. } => {
+                push(left);
+                push(right);
+            }
+            Expr::Matches { operand, .. }
+            | Expr::In { operand, .. }
+            | Expr::IsNull { operand, .. } => push(operand),
+        }
+    }
+
+    fn eval(&self, row: &Row) -> Result<bool, CsvError> {
+        match self {
+            Expr::And(left, right) => Ok(left.eval(row)? && right.eval(row)?),
+            Expr::Or(left, right) => Ok(left.eval(row)? || right.eval(row)?),
+            Expr::Not(inner) => Ok(!inner.eval(row)?),
+            Expr::Compare { left, op, right } => match (value(left, row)?, value(right, row)?) {
+                (Some(left_value), Some(right_value)) => {
+                    let ordering = compare(left, left_value, right, right_value)?;
+                    Ok(op.holds(ordering))
+                }
+                _ => Ok(false),
+            },
+            Expr::Matches {
+                operand,
+                regex,
+                negated,
+            } => match value(operand, row)? {
+                Some(Value::Text(text)) => Ok(regex.is_match(text) != *negated),
+                Some(Value::Number(number)) => Ok(regex.is_match(&number.to_string()) != *negated),
+                None => Ok(false),
+            },
+            Expr::In { operand, list } => {
+                let operand_value = match value(operand, row)? {
+                    Some(operand_value) => operand_value,
+                    None => return Ok(false),
+                };
+                for item in list {
+                    if let Some(item_value) = value(item, row)? {
+                        let ordering = compare(operand, operand_value, item, item_value)?;
+                        if ordering == Some(Ordering::Equal) {
+                            return Ok(true);
+                        }
+                    }
+                }
+                Ok(false)
+            }
+            Expr::IsNull { operand, negated } => Ok(value(operand, row)?.is_none() != *negated),
+        }
+    }
+}
+
+/// The value of an operand for the row, None if it is empty(null)
+fn value<'r>(operand: &'r Operand, row: &'r Row) -> Result<Option<Value<'r>>, CsvError> {
+    match operand {
+        Operand::Column { name, .. } => match row.get(name) {
+            Some(text) if text.is_empty() => Ok(None),
+            Some(text) => Ok(Some(Value::Text(text))),
+            None => Err(CsvError::InvalidColumn(name.clone())),
+        },
+        Operand::Text(text) => Ok(Some(Value::Text(text))),
+        Operand::Number(number) => Ok(Some(Value::Number(*number))),
+    }
+}
+
+/// Numbers if either side is a number literal(or both values are numbers), text otherwise.
+/// None if a number is NaN, which is not in any order with the others
+fn compare(
+    left: &Operand,
+    left_value: Value<'_>,
+    right: &Operand,
+    right_value: Value<'_>,
+) -> Result<Option<Ordering>, CsvError> {
+    let as_number = |operand: &Operand, value: &Value<'_>| match value {
+        Value::Number(number) => Ok(*number),
+        Value::Text(text) => parse_number(text.trim()).ok_or_else(|| {
+            let mut details = ErrorDetails::new(format!("Cannot compare {:?} with a number", text));
+            if let Operand::Column { name, .. } = operand {
+                details.column_name = Some(name.clone());
+            }
+            details.excerpt = Some(text.to_string());
+            CsvError::InvalidRow(details.into())
+        }),
+    };
+
+    let numeric = match (&left_value, &right_value) {
+        (Value::Number(_), _) | (_, Value::Number(_)) => true,
+        (Value::Text(left_text), Value::Text(right_text)) => {
+            parse_number(left_text.trim()).is_some() && parse_number(right_text.trim()).is_some()
+        }
+    };
+    if numeric {
+        let left_number = as_number(left, &left_value)?;
+        let right_number = as_number(right, &right_value)?;
+        return Ok(left_number.partial_cmp(&right_number));
+    }
+    match (left_value, right_value) {
+        (Value::Text(left_text), Value::Text(right_text)) => Ok(Some(left_text.cmp(right_text))),
+        _ => unreachable!(),
+    }
+}
+
+/// The number if the text is a decimal literal(`-12`, `3.5`, `1e-3`), unlike `f64::from_str`
+/// "NaN", "inf" and "infinity" are not numbers but text
+pub(crate) fn parse_number(text: &str) -> Option<f64> {
+    let bytes = text.as_bytes();
+    let digits = |from: usize| {
+        from + bytes[from..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count()
+    };
+    let mut end = usize::from(matches!(bytes.first(), Some(b'+' | b'-')));
+    let integer_end = digits(end);
+    let mut mantissa = integer_end - end;
+    end = integer_end;
+    if bytes.get(end) == Some(&b'.') {
+        let fraction_end = digits(end + 1);
+        mantissa += fraction_end - end - 1;
+        end = fraction_end;
+    }
+    if mantissa == 0 {
+        return None;
+    }
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(end + 1), Some(b'+' | b'-')));
+        let exponent_end = digits(end + 1 + sign);
+        if exponent_end == end + 1 + sign {
+            return None;
+        }
+        end = exponent_end;
+    }
+    if end != bytes.len() {
+        return None;
+    }
+    text.parse().ok()
+}
+
+impl<R: BufRead> Csv<R> {
+    /// Compiles the expression(see `Filter`) and adds it as a filter stage.
+    /// The columns in it must be columns of the rows at this point of the pipeline
+    pub fn apply_filter(&mut self, expression: &str) -> Result<&mut Self, CsvError> {
+        let filter = Filter::compile(expression)?;
+        filter.check_columns(&self.output_columns())?;
+        self.apply_selection(move |row| filter.matches(row));
+        Ok(self)
+    }
+}
@@ -0,0 +1,362 @@
+//! Grouping the rows by some columns and aggregating the others, like `GROUP BY` in SQL.\
+//! Only the state of the aggregates is kept for every group(not the rows), the groups are
+//! given out in the order in which they were first seen
+
+use crate::{write_rows, Csv, CsvError, Dialect, ErrorDetails, Row};
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, Write};
+use std::sync::Arc;
+
+/// Integer sums, which never overflow with the `bigint` feature
+#[cfg(feature = "bigint")]
+type Integer = bigint::Bigint;
+#[cfg(not(feature = "bigint"))]
+type Integer = i128;
+
+#[cfg(feature = "bigint")]
+fn add_integers(sum: Integer, value: Integer) -> Option<Integer> {
+    Some(sum + value)
+}
+
+#[cfg(not(feature = "bigint"))]
+fn add_integers(sum: Integer, value: Integer) -> Option<Integer> {
+    sum.checked_add(value)
+}
+
+/// What to compute for every group. Empty values(or only whitespace) are skipped by all of
+/// them, except `Count`(which counts the rows) and `First`/`Last`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Aggregate {
+    Count,
+    /// Exact for integers, but with the `bigint` feature they cannot overflow
+    Sum(String),
+    /// Numbers are compared as numbers if all the values are numbers, as text otherwise
+    Min(String),
+    Max(String),
+    Avg(String),
+    /// Keeps a hash of every distinct value, so it is the only one which grows with the group
+    DistinctCount(String),
+    First(String),
+    Last(String),
+}
+
+impl Aggregate {
+    fn column(&self) -> Option<&str> {
+        match self {
+            Aggregate::Count => None,
+            Aggregate::Sum(column)
+            | Aggregate::Min(column)
+            | Aggregate::Max(column)
+            | Aggregate::Avg(column)
+            | Aggregate::DistinctCount(column)
+            | Aggregate::First(column)
+            | Aggregate::Last(column) => Some(column),
+        }
+    }
+
+    /// The name of the column of the result, e.g. `sum(population)`
+    pub fn name(&self) -> String {
+        let function = match self {
+            Aggregate::Count => return "count".into(),
+            Aggregate::Sum(_) => "sum",
+            Aggregate::Min(_) => "min",
+            Aggregate::Max(_) => "max",
+            Aggregate::Avg(_) => "avg",
+            Aggregate::DistinctCount(_) => "distinct_count",
+            Aggregate::First(_) => "first",
+            Aggregate::Last(_) => "last",
+        };
+        format!("{}({})", function, self.column().unwrap_or_default())
+    }
+
+    fn start(&self) -> State {
+        match self {
+            Aggregate::Count => State::Count(0),
+            Aggregate::Sum(_) => State::Sum(Sum::Integer(Integer::default())),
+            Aggregate::Min(_) | Aggregate::Max(_) => State::Extreme {
+                number: None,
+                text: None,
+                numeric: true,
+            },
+            Aggregate::Avg(_) => State::Avg { sum: 0.0, count: 0 },
+            Aggregate::DistinctCount(_) => State::Distinct(HashSet::new()),
+            Aggregate::First(_) | Aggregate::Last(_) => State::Value(None),
+        }
+    }
+}
+
+enum Sum {
+    Integer(Integer),
+    /// Once a value is not an integer
+    Decimal(f64),
+}
+
+/// What an aggregate keeps for a group
+enum State {
+    Count(u64),
+    Sum(Sum),
+    /// The best value as a number(with its text) and as text, and if all values were numbers
+    Extreme {
+        number: Option<(f64, String)>,
+        text: Option<String>,
+        numeric: bool,
+    },
+    Avg {
+        sum: f64,
+        count: u64,
+    },
+    Distinct(HashSet<u64>),
+    Value(Option<String>),
+}
+
+fn not_a_number(column: &str, value: &str, message: &str) -> CsvError {
+    let mut details = ErrorDetails::new(message);
+    details.column_name = Some(column.into());
+    details.excerpt = Some(value.into());
+    CsvError::InvalidRow(details.into())
+}
+
+impl State {
+    fn update(&mut self, aggregate: &Aggregate, value: &str) -> Result<(), CsvError> {
+        let column = aggregate.column().unwrap_or_default();
+        let trimmed = value.trim();
+        if trimmed.is_empty() && !matches!(self, State::Count(_) | State::Value(_)) {
+            return Ok(());
+        }
+
+        match self {
+            State::Count(count) => *count += 1,
+            State::Sum(sum) => {
+                if let Sum::Integer(integer) = sum {
+                    match trimmed.parse::<Integer>() {
+                        Ok(parsed) => {
+                            let current = std::mem::take(integer);
+                            *integer = add_integers(current, parsed)
+                                .ok_or_else(|| not_a_number(column, value, "The sum is too big"))?;
+                            return Ok(());
+                        }
+                        Err(_) => *sum = Sum::Decimal(integer.to_string().parse().unwrap_or(0.0)),
+                    }
+                }
+                if let Sum::Decimal(decimal) = sum {
+                    *decimal += trimmed
+                        .parse::<f64>()
+                        .map_err(|_| not_a_number(column, value, "Only numbers can be summed"))?;
+                }
+            }
+            State::Extreme {
+                number,
+                text,
+                numeric,
+            } => {
+                let wanted = match aggregate {
+                    Aggregate::Min(_) => Ordering::Less,
+                    _ => Ordering::Greater,
+                };
+                let better = |ordering: Ordering| ordering == wanted;
+                if text.as_deref().is_none_or(|best| better(value.cmp(best))) {
+                    *text = Some(value.into());
+                }
+                match trimmed.parse::<f64>() {
+                    Ok(parsed) if *numeric => {
+                        if number.as_ref().is_none_or(|(best, _)| {
+                            better(parsed.partial_cmp(best).unwrap_or(Ordering::Equal))
+                        }) {
+                            *number = Some((parsed, value.into()));
+                        }
+                    }
+                    _ => {
+                        *numeric = false;
+                        *number = None;
+                    }
+                }
+            }
+            State::Avg { sum, count } => {
+                *sum += trimmed
+                    .parse::<f64>()
+                    .map_err(|_| not_a_number(column, value, "Only numbers can be averaged"))?;
+                *count += 1;
+            }
+            State::Distinct(hashes) => {
+                let mut hasher = DefaultHasher::new();
+                value.hash(&mut hasher);
+                hashes.insert(hasher.finish());
+            }
+            State::Value(kept) => {
+                if kept.is_none() || matches!(aggregate, Aggregate::Last(_)) {
+                    *kept = Some(value.into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> String {
+        match self {
+            State::Count(count) => count.to_string(),
+            State::Sum(Sum::Integer(integer)) => integer.to_string(),
+            State::Sum(Sum::Decimal(decimal)) => decimal.to_string(),
+            State::Extreme {
+                number: Some((_, value)),
+                numeric: true,
+                ..
+            } => value,
+            State::Extreme { text, .. } => text.unwrap_or_default(),
+            State::Avg { count: 0, .. } => String::new(),
+            State::Avg { sum, count } => (sum / count as f64).to_string(),
+            State::Distinct(hashes) => hashes.len().to_string(),
+            State::Value(kept) => kept.unwrap_or_default(),
+        }
+    }
+}
+
+/// The aggregates to compute for every group, see `Csv::group_by`
+pub struct GroupBy<R: BufRead> {
+    csv: Csv<R>,
+    keys: Vec<String>,
+    aggregates: Vec<Aggregate>,
+}
+
+impl<R: BufRead> GroupBy<R> {
+    /// Adds an aggregate, its column must be one of the columns of the rows.
+    /// Its name must not be the name of a key or of another aggregate
+    pub fn aggregate(mut self, aggregate: Aggregate) -> Result<Self, CsvError> {
+        if let Some(column) = aggregate.column() {
+            if !self.csv.output_columns().iter().any(|name| name == column) {
+                return Err(CsvError::InvalidColumn(column.into()));
+            }
+        }
+        let name = aggregate.name();
+        if self.keys.contains(&name) || self.aggregates.iter().any(|a| a.name() == name) {
+            return Err(CsvError::InvalidHeader(format!(
+                "The column {:?} of the result is already taken",
+                name
+            )));
+        }
+        self.aggregates.push(aggregate);
+        Ok(self)
+    }
+
+    pub fn count(self) -> Result<Self, CsvError> {
+        self.aggregate(Aggregate::Count)
+    }
+
+    pub fn sum(self, column: &str) -> Result<Self, CsvError> {
+        self.aggregate(Aggregate::Sum(column.into()))
+    }
+
+    pub fn min(self, column: &str) -> Result<Self, CsvError> {
+        self.aggregate(Aggregate::Min(column.into()))
+    }
+
+    pub fn max(self, column: &str) -> Result<Self, CsvError> {
+        self.aggregate(Aggregate::Max(column.into()))
+    }
+
+    pub fn avg(self, column: &str) -> Result<Self, CsvError> {
+        self.aggregate(Aggregate::Avg(column.into()))
+    }
+
+    pub fn distinct_count(self, column: &str) -> Result<Self, CsvError> {
+        self.aggregate(Aggregate::DistinctCount(column.into()))
+    }
+
+    pub fn first(self, column: &str) -> Result<Self, CsvError> {
+        self.aggregate(Aggregate::First(column.into()))
+    }
+
+    pub fn last(self, column: &str) -> Result<Self, CsvError> {
+        self.aggregate(Aggregate::Last(column.into()))
+    }
+
+    /// Reads all the rows(through the pipeline) and computes the aggregates.
+    /// The columns of the result are the keys and then the names of the aggregates
+    pub fn run(self) -> Result<Grouped, CsvError> {
+        let GroupBy {
+            csv,
+            keys,
+            aggregates,
+        } = self;
+        let dialect = csv.dialect().clone();
+
+        let mut index = HashMap::<Vec<String>, usize>::new();
+        let mut groups = Vec::<(Vec<String>, Vec<State>)>::new();
+        for row in csv {
+            let row = row?;
+            let key: Vec<String> = keys.iter().map(|key| row[key].clone()).collect();
+            let group = match index.get(&key) {
+                Some(&group) => group,
+                None => {
+                    index.insert(key.clone(), groups.len());
+                    groups.push((key, aggregates.iter().map(Aggregate::start).collect()));
+                    groups.len() - 1
+                }
+            };
+
+            for (aggregate, state) in aggregates.iter().zip(groups[group].1.iter_mut()) {
+                let value = aggregate.column().map_or("", |column| row[column].as_str());
+                state.update(aggregate, value)?;
+            }
+        }
+
+        let mut columns = keys;
+        columns.extend(aggregates.iter().map(Aggregate::name));
+        let names: Arc<[String]> = columns.as_slice().into();
+        let rows: Vec<Row> = groups
+            .into_iter()
+            .map(|(mut values, states)| {
+                values.extend(states.into_iter().map(State::finish));
+                Row::with_columns(Arc::clone(&names), values)
+            })
+            .collect();
+
+        Ok(Grouped {
+            columns,
+            rows: rows.into_iter(),
+            dialect,
+        })
+    }
+}
+
+/// The result of a `GroupBy`, a row for every group
+pub struct Grouped {
+    pub columns: Vec<String>,
+    rows: std::vec::IntoIter<Row>,
+    dialect: Dialect,
+}
+
+impl Grouped {
+    /// Writes the header(if the dialect has one) and the groups in the dialect of the csv
+    /// which was grouped
+    pub fn write_to<W: Write>(self, writer: W) -> Result<(), CsvError> {
+        write_rows(writer, self.dialect, &self.columns, self.rows.map(Ok))
+    }
+}
+
+/// The groups, the same as the rows of a `Csv`
+impl Iterator for Grouped {
+    type Item = Result<Row, CsvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rows.next().map(Ok)
+    }
+}
+
+impl<R: BufRead> Csv<R> {
+    /// Groups the rows which come out of the pipeline by the values of the given columns,
+    /// add the aggregates to the result and `run` it
+    pub fn group_by(self, keys: &[&str]) -> Result<GroupBy<R>, CsvError> {
+        let columns = self.output_columns();
+        if let Some(missing) = keys.iter().find(|key| !columns.iter().any(|c| c == *key)) {
+            return Err(CsvError::InvalidColumn(missing.to_string()));
+        }
+        Ok(GroupBy {
+            csv: self,
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+            aggregates: Vec::new(),
+        })
+    }
+}
@@ -0,0 +1,445 @@
+//! Joining the rows of two `Csv`s on the values of some key columns.\
+//! A hash join reads the whole right side into memory and then goes through the left one,
+//! a merge join reads both sides together, so they must be sorted by the keys
+
+use crate::sort::{Keys, SortValue};
+use crate::{write_rows, Csv, CsvError, Dialect, ErrorDetails, Row, SortKey, SortType};
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, Write};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+    /// Only the rows with a match on the other side
+    Inner,
+    /// Every left row, with empty right values if it has no match
+    Left,
+    /// Every row of both sides, with empty values for the side without a match
+    FullOuter,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinStrategy {
+    Hash,
+    /// Both sides must be sorted by the keys, see `Join::sorted_by`
+    Merge,
+}
+
+/// How to join two `Csv`s, see `Csv::join`
+pub struct Join<L: BufRead, R: BufRead> {
+    left: Csv<L>,
+    right: Csv<R>,
+    keys: Vec<String>,
+    kind: JoinKind,
+    strategy: JoinStrategy,
+    suffixes: (String, String),
+    /// How the sides of a merge join are sorted, by the text of the keys if empty
+    order: Vec<SortKey>,
+}
+
+impl<L: BufRead, R: BufRead> Join<L, R> {
+    pub fn kind(mut self, kind: JoinKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn strategy(mut self, strategy: JoinStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Added to the names of the columns(other than the keys) which both sides have,
+    /// `_left` and `_right` by default
+    pub fn suffixes(mut self, left: &str, right: &str) -> Self {
+        self.suffixes = (left.into(), right.into());
+        self
+    }
+
+    /// How both sides of a merge join are sorted, the keys as given to `Csv::sort_by`
+    /// (e.g. `-age:number`), each of the join keys exactly once. Then the keys are compared
+    /// by their types, so "1.0" matches "1" as a number.\
+    /// By default the sides must be sorted by the text of the keys, in the order of the keys
+    pub fn sorted_by(mut self, keys: &[&str]) -> Self {
+        self.order = keys.iter().map(|spec| SortKey::parse(spec)).collect();
+        self
+    }
+
+    pub fn run(self) -> Result<Joined<L, R>, CsvError> {
+        let left_columns = self.left.output_columns();
+        let right_columns = self.right.output_columns();
+        let position = |columns: &[String], key: &str| {
+            columns
+                .iter()
+                .position(|column| column == key)
+                .ok_or_else(|| CsvError::InvalidColumn(key.into()))
+        };
+        let mut left_keys = Vec::with_capacity(self.keys.len());
+        let mut right_keys = Vec::with_capacity(self.keys.len());
+        for key in &self.keys {
+            left_keys.push(position(&left_columns, key)?);
+            right_keys.push(position(&right_columns, key)?);
+        }
+        let right_kept: Vec<usize> = (0..right_columns.len())
+            .filter(|i| !right_keys.contains(i))
+            .collect();
+
+        // the header: the left columns, then the right ones without the keys
+        let (left_suffix, right_suffix) = &self.suffixes;
+        let collides =
+            |name: &String, others: &[String]| !self.keys.contains(name) && others.contains(name);
+        let mut columns: Vec<String> = left_columns
+            .iter()
+            .map(|name| match collides(name, &right_columns) {
+                true => format!("{}{}", name, left_suffix),
+                false => name.clone(),
+            })
+            .collect();
+        columns.extend(right_kept.iter().map(|&i| {
+            let name = &right_columns[i];
+            match collides(name, &left_columns) {
+                true => format!("{}{}", name, right_suffix),
+                false => name.clone(),
+            }
+        }));
+        if let Some(duplicate) = columns
+            .iter()
+            .enumerate()
+            .find(|(i, name)| columns[..*i].contains(name))
+        {
+            return Err(CsvError::InvalidColumn(duplicate.1.clone()));
+        }
+
+        let dialect = self.left.dialect().clone();
+        let state = match self.strategy {
+            JoinStrategy::Hash => {
+                let mut table = HashMap::<Vec<String>, Vec<usize>>::new();
+                let mut right_rows = Vec::new();
+                for row in self.right {
+                    let row = row?;
+                    table
+                        .entry(key_of(&row, &right_keys))
+                        .or_default()
+                        .push(right_rows.len());
+                    right_rows.push(row);
+                }
+                State::Hash {
+                    left: Box::new(self.left),
+                    matched: vec![false; right_rows.len()],
+                    right_rows,
+                    table,
+                    unmatched: None,
+                }
+            }
+            JoinStrategy::Merge => {
+                let order = merge_order(&self.keys, self.order)?;
+                let side_keys = |columns: &[String]| -> Result<Keys, CsvError> {
+                    Ok(Keys {
+                        keys: order.clone(),
+                        positions: order
+                            .iter()
+                            .map(|key| position(columns, &key.column))
+                            .collect::<Result<_, _>>()?,
+                    })
+                };
+                State::Merge {
+                    left: Box::new(SortedSide::new(self.left, side_keys(&left_columns)?)),
+                    right: Box::new(SortedSide::new(self.right, side_keys(&right_columns)?)),
+                }
+            }
+        };
+
+        Ok(Joined {
+            layout: Layout {
+                names: columns.as_slice().into(),
+                left_keys,
+                right_keys,
+                right_kept,
+                left_width: left_columns.len(),
+            },
+            columns,
+            kind: self.kind,
+            state,
+            ready: VecDeque::new(),
+            dialect,
+        })
+    }
+}
+
+/// The keys of a merge join in the order in which the sides are sorted, each join key
+/// exactly once
+fn merge_order(keys: &[String], order: Vec<SortKey>) -> Result<Vec<SortKey>, CsvError> {
+    if order.is_empty() {
+        let by_text = |key: &String| SortKey {
+            column: key.clone(),
+            descending: false,
+            sort_type: SortType::String,
+        };
+        return Ok(keys.iter().map(by_text).collect());
+    }
+    for (i, key) in order.iter().enumerate() {
+        if !keys.contains(&key.column) || order[..i].iter().any(|k| k.column == key.column) {
+            return Err(CsvError::InvalidColumn(key.column.clone()));
+        }
+    }
+    match keys
+        .iter()
+        .find(|name| !order.iter().any(|key| key.column == **name))
+    {
+        Some(missing) => Err(CsvError::InvalidColumn(missing.clone())),
+        None => Ok(order),
+    }
+}
+
+fn key_of(row: &Row, keys: &[usize]) -> Vec<String> {
+    keys.iter().map(|&i| row[i].clone()).collect()
+}
+
+/// A side of a merge join, which checks that its rows are sorted
+struct SortedSide<B: BufRead> {
+    csv: Csv<B>,
+    keys: Keys,
+    next: Option<(Vec<SortValue>, Row)>,
+    /// The key of the last row which was read
+    last: Option<Vec<SortValue>>,
+    done: bool,
+}
+
+impl<B: BufRead> SortedSide<B> {
+    fn new(csv: Csv<B>, keys: Keys) -> Self {
+        SortedSide {
+            csv,
+            keys,
+            next: None,
+            last: None,
+            done: false,
+        }
+    }
+
+    /// The key of the next row, None at the end
+    fn peek(&mut self) -> Result<Option<&Vec<SortValue>>, CsvError> {
+        if self.next.is_none() && !self.done {
+            match self.csv.next().transpose()? {
+                Some(row) => {
+                    let key = self.keys.of(row.values())?;
+                    if self.last.as_ref().is_some_and(|last| *last > key) {
+                        let mut details = ErrorDetails::new(
+                            "The rows of a merge join must be sorted by the keys",
+                        );
+                        details.excerpt = Some(key_of(&row, &self.keys.positions).join(","));
+                        return Err(CsvError::InvalidRow(details.into()));
+                    }
+                    self.last = Some(key.clone());
+                    self.next = Some((key, row));
+                }
+                None => self.done = true,
+            }
+        }
+        Ok(self.next.as_ref().map(|(key, _)| key))
+    }
+
+    fn take(&mut self) -> Option<Row> {
+        self.next.take().map(|(_, row)| row)
+    }
+
+    /// All the next rows with the given key
+    fn take_group(&mut self, key: &[SortValue]) -> Result<Vec<Row>, CsvError> {
+        let mut group = Vec::new();
+        while self.peek()?.is_some_and(|next| next.as_slice() == key) {
+            group.extend(self.take());
+        }
+        Ok(group)
+    }
+}
+
+/// The sides are boxed, since a `Csv` is much bigger than the rest of the state
+enum State<L: BufRead, R: BufRead> {
+    Hash {
+        left: Box<Csv<L>>,
+        right_rows: Vec<Row>,
+        table: HashMap<Vec<String>, Vec<usize>>,
+        matched: Vec<bool>,
+        /// After the left side ends, the next right row to check for a full outer join
+        unmatched: Option<usize>,
+    },
+    Merge {
+        left: Box<SortedSide<L>>,
+        right: Box<SortedSide<R>>,
+    },
+}
+
+/// Where the values of a joined row come from
+struct Layout {
+    names: Arc<[String]>,
+    left_keys: Vec<usize>,
+    right_keys: Vec<usize>,
+    /// The right columns which are not keys
+    right_kept: Vec<usize>,
+    left_width: usize,
+}
+
+impl Layout {
+    /// A row of the result, the keys of a row without a left side come from the right one
+    fn combine(&self, left: Option<&Row>, right: Option<&Row>) -> Row {
+        let mut values = Vec::with_capacity(self.names.len());
+        match left {
+            Some(left) => values.extend(left.values().iter().cloned()),
+            None => {
+                values.resize(self.left_width, String::new());
+                if let Some(right) = right {
+                    for (&left_key, &right_key) in self.left_keys.iter().zip(&self.right_keys) {
+                        values[left_key] = right[right_key].clone();
+                    }
+                }
+            }
+        }
+        match right {
+            Some(right) => values.extend(self.right_kept.iter().map(|&i| right[i].clone())),
+            None => values.resize(self.names.len(), String::new()),
+        }
+        Row::with_columns(Arc::clone(&self.names), values)
+    }
+}
+
+/// The joined rows: the left columns and then the right ones without the keys
+pub struct Joined<L: BufRead, R: BufRead> {
+    pub columns: Vec<String>,
+    kind: JoinKind,
+    layout: Layout,
+    state: State<L, R>,
+    ready: VecDeque<Row>,
+    dialect: Dialect,
+}
+
+impl<L: BufRead, R: BufRead> Joined<L, R> {
+    /// Writes the header(if the dialect has one) and the joined rows in the dialect of the
+    /// left csv
+    pub fn write_to<W: Write>(mut self, writer: W) -> Result<(), CsvError> {
+        let columns = std::mem::take(&mut self.columns);
+        write_rows(writer, self.dialect.clone(), &columns, &mut self)
+    }
+
+    /// Puts the next joined rows in `ready`, false at the end
+    fn fill(&mut self) -> Result<bool, CsvError> {
+        let keep_left = self.kind != JoinKind::Inner;
+        let keep_right = self.kind == JoinKind::FullOuter;
+        let layout = &self.layout;
+        let ready = &mut self.ready;
+
+        match &mut self.state {
+            State::Hash {
+                left,
+                right_rows,
+                table,
+                matched,
+                unmatched: None,
+            } => match left.next().transpose()? {
+                Some(row) => {
+                    match table.get(&key_of(&row, &layout.left_keys)) {
+                        Some(indices) => {
+                            for &i in indices {
+                                matched[i] = true;
+                                ready.push_back(layout.combine(Some(&row), Some(&right_rows[i])));
+                            }
+                        }
+                        None if keep_left => ready.push_back(layout.combine(Some(&row), None)),
+                        None => {}
+                    }
+                    Ok(true)
+                }
+                None => {
+                    if let State::Hash { unmatched, .. } = &mut self.state {
+                        *unmatched = Some(0);
+                    }
+                    Ok(keep_right)
+                }
+            },
+            State::Hash {
+                right_rows,
+                matched,
+                unmatched: Some(next),
+                ..
+            } => {
+                let offset = match matched[*next..].iter().position(|&m| !m) {
+                    Some(offset) if keep_right => offset,
+                    _ => return Ok(false),
+                };
+                ready.push_back(layout.combine(None, Some(&right_rows[*next + offset])));
+                *next += offset + 1;
+                Ok(true)
+            }
+            State::Merge { left, right } => {
+                let left_key = left.peek()?.cloned();
+                let right_key = right.peek()?.cloned();
+                let ordering = match (&left_key, &right_key) {
+                    (None, None) => return Ok(false),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (Some(left_key), Some(right_key)) => left_key.cmp(right_key),
+                };
+                match ordering {
+                    Ordering::Less => {
+                        let row = left.take();
+                        if keep_left {
+                            ready.push_back(layout.combine(row.as_ref(), None));
+                        }
+                    }
+                    Ordering::Greater if keep_right => {
+                        let row = right.take();
+                        ready.push_back(layout.combine(None, row.as_ref()));
+                    }
+                    // without the unmatched right rows, the rest of the right side is not needed
+                    Ordering::Greater if left_key.is_none() => return Ok(false),
+                    Ordering::Greater => {
+                        right.take();
+                    }
+                    Ordering::Equal => {
+                        let key = left_key.unwrap_or_default();
+                        let lefts = left.take_group(&key)?;
+                        let rights = right.take_group(&key)?;
+                        for left_row in &lefts {
+                            for right_row in &rights {
+                                ready.push_back(layout.combine(Some(left_row), Some(right_row)));
+                            }
+                        }
+                    }
+                }
+                Ok(true)
+            }
+        }
+    }
+}
+
+impl<L: BufRead, R: BufRead> Iterator for Joined<L, R> {
+    type Item = Result<Row, CsvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.ready.pop_front() {
+                return Some(Ok(row));
+            }
+            match self.fill() {
+                Ok(true) => {}
+                Ok(false) => return self.ready.pop_front().map(Ok),
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+impl<L: BufRead> Csv<L> {
+    /// Joins the rows which come out of the pipelines of the two csvs, on the columns with
+    /// the given names(which both of them must have, see `rename_column`).
+    /// By default it is an inner hash join
+    pub fn join<R: BufRead>(self, right: Csv<R>, keys: &[&str]) -> Join<L, R> {
+        Join {
+            left: self,
+            right,
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+            kind: JoinKind::Inner,
+            strategy: JoinStrategy::Hash,
+            suffixes: ("_left".into(), "_right".into()),
+            order: Vec::new(),
+        }
+    }
+}
@@ -1,152 +1,244 @@
-use std::collections::HashMap;
 use std::io::BufRead;
 use std::io::Write;
-use std::io::{Error, ErrorKind};
+use std::sync::Arc;
+
+#[cfg(feature = "async")]
+mod async_csv;
+mod columns;
+#[cfg(feature = "serde")]
+mod de;
+mod dialect;
+mod encoding;
+mod error;
+mod filter;
+mod group;
+mod join;
+mod parallel;
+mod parser;
+mod pipeline;
+mod profile;
+mod reader;
+mod record;
+mod row;
+mod schema;
+#[cfg(feature = "serde")]
+mod ser;
+mod sniff;
+mod sort;
+mod writer;
+
+#[cfg(feature = "async")]
+pub use async_csv::AsyncCsv;
+#[cfg(feature = "serde")]
+pub use de::DeserializeRecords;
+pub use dialect::{CsvBuilder, Dialect, LineTerminator, QuoteStyle};
+pub use encoding::{Decoder, Encoding};
+use error::excerpt;
+pub use error::{CsvError, ErrorDetails, Position};
+pub use filter::{Filter, FilterError};
+pub use group::{Aggregate, GroupBy, Grouped};
+pub use join::{Join, JoinKind, JoinStrategy, Joined};
+pub use parallel::{Parallel, ParallelRows, DEFAULT_CHUNK_SIZE};
+pub use parser::{parse_record, parse_record_with, ParseMode};
+use parser::{parse_record_into, strip_line_end};
+use pipeline::Pipeline;
+pub use pipeline::{BoxError, StageError, StageKind};
+pub use profile::{ColumnProfile, LengthBucket, Profile, DEFAULT_TOP_K};
+use reader::RecordReader;
+pub use reader::DEFAULT_MAX_RECORD_SIZE;
+pub use record::{ByteRecord, Column, StringRecord};
+pub use row::Row;
+pub use schema::{ColumnSchema, ColumnType, Schema};
+pub use sniff::{Sniffed, SniffedReader, DEFAULT_SNIFF_SIZE};
+pub use sort::{Sort, SortKey, SortType, Sorted, DEFAULT_MEMORY_BUDGET};
+use writer::write_rows;
+pub use writer::CsvWriter;
 
-type Row = HashMap<String, String>;
 const DELIMITER: char = ',';
 const QUOTE: char = '"';
 
 pub struct Csv<R: BufRead> {
+    /// The columns of the rows, see `select_columns`, `rename_column`, `reorder` and
+    /// `drop_columns` for changing them
     pub columns: Vec<String>,
-    reader: R,
-    selection: Option<Box<dyn Fn(&Row) -> Result<bool, CsvError>>>,
-}
-
-#[derive(Debug)]
-pub enum CsvError {
-    IO(std::io::Error),
-    ParseError(String),
-    InvalidHeader(String),
-    InvalidRow(String),
-    InvalidColumn(String),
+    /// The columns of the input(the header)
+    header: Vec<String>,
+    /// The index in the input of the field for each of the columns
+    fields: Vec<usize>,
+    reader: RecordReader<R>,
+    /// The stages which the rows go through, see `filter_rows` and the others
+    pipeline: Pipeline,
+    /// Every row which is given out is checked against it, see `set_schema`
+    schema: Option<Schema>,
+    dialect: Dialect,
+    /// Without a header the first record has to be read to know the number of columns
+    pending: Option<StringRecord>,
+    /// The record which the iterator reads into, reused for every row
+    scratch: StringRecord,
+    /// The column names which the rows share
+    names: Arc<[String]>,
 }
 
 impl<R: BufRead> Csv<R> {
-    pub fn new(mut reader: R) -> Result<Self, CsvError> {
-        let mut headers = String::new();
-        let read_bytes = reader.read_line(&mut headers);
-        match read_bytes {
-            Ok(0) => Err(CsvError::InvalidHeader(
-                "The header row must not be empty".into(),
-            )),
-            Ok(_) => {
-                let mut cols = Vec::<String>::new();
-                let mut headers: String = headers.trim().into();
-                if !headers.contains(DELIMITER) {
-                    cols.push(headers.trim().into());
-                } else {
-                    // contains the delimiter, for sure
-                    while let Some((col_name, rest)) = take_and_skip(&headers, DELIMITER) {
-                        cols.push(col_name.trim().into());
-                        headers = rest.into();
-                    }
-                    cols.push(headers.trim().into()); // the last header
-                }
+    /// A new csv in the default dialect, see `Dialect::default`
+    pub fn new(reader: R) -> Result<Self, CsvError> {
+        Csv::with_dialect(reader, Dialect::default())
+    }
 
-                let mut distinct_cols = Vec::<String>::new();
-                for col in cols.clone() {
-                    if distinct_cols.contains(&col) {
-                        return Err(CsvError::InvalidHeader(
-                            "The names of the columns must be unique!".into(),
-                        ));
-                    }
-                    distinct_cols.push(col);
-                }
+    /// The default dialect in the given mode, see `Dialect::with_mode`
+    pub fn with_mode(reader: R, mode: ParseMode) -> Result<Self, CsvError> {
+        Csv::with_dialect(reader, Dialect::with_mode(mode))
+    }
 
-                Ok(Csv {
-                    columns: cols,
-                    reader,
-                    selection: None,
-                })
-            }
-            Err(error) => Err(CsvError::IO(error)),
-        }
+    pub fn with_dialect(reader: R, dialect: Dialect) -> Result<Self, CsvError> {
+        CsvBuilder::from_dialect(dialect).from_reader(reader)
+    }
+
+    fn build(
+        reader: R,
+        dialect: Dialect,
+        max_record_size: usize,
+        lossy: bool,
+    ) -> Result<Self, CsvError> {
+        let mut reader = RecordReader::new(reader);
+        reader.state.max_record_size = max_record_size;
+        reader.state.lossy = lossy;
+
+        let mut first = StringRecord::new();
+        let found = reader.read_record(&dialect, &mut first)?;
+        let (columns, pending) = split_header(first, found, &dialect)?;
+        let names = columns.as_slice().into();
+
+        Ok(Csv {
+            header: columns.clone(),
+            fields: (0..columns.len()).collect(),
+            columns,
+            reader,
+            pipeline: Pipeline::default(),
+            schema: None,
+            dialect,
+            pending,
+            scratch: StringRecord::new(),
+            names,
+        })
     }
 
     pub fn parse_line(&self, line: &str) -> Result<Row, CsvError> {
-        parse_row_with_cols(&self.columns, line)
+        let mut record = StringRecord::new();
+        parse_record_into(strip_line_end(line), &self.dialect, &mut record)
+            .and_then(|_| check_width(&self.header, &record, &self.dialect))
+            .map_err(|error| error.name_column(&self.header))?;
+        Ok(Row::with_columns(
+            self.columns.as_slice().into(),
+            self.project(&record),
+        ))
     }
 
-    pub fn apply_selection<F>(&mut self, callback: F)
-    where
-        F: Fn(&Row) -> Result<bool, CsvError> + 'static,
-    {
-        self.selection = Some(Box::new(callback));
+    /// The longest a single record may be(in bytes, with its line breaks).
+    /// A quoted field may span several lines, so without a limit a missing closing quote
+    /// would make the whole rest of the input a single record
+    pub fn set_max_record_size(&mut self, max_record_size: usize) {
+        self.reader.state.max_record_size = max_record_size;
     }
 
-    fn gen_header_str(&self) -> String {
-        let mut res = String::new();
-        for i in 0..(self.columns.len() - 1) {
-            res += &self.columns[i];
-            res += ", "
-        }
-        res += &self.columns[self.columns.len() - 1];
-        res + "\n"
+    /// In lossy mode what is not UTF-8 is replaced with U+FFFD, otherwise such a record is a
+    /// parse error. Input in another encoding should be read through a `Decoder` first
+    pub fn set_lossy(&mut self, lossy: bool) {
+        self.reader.state.lossy = lossy;
     }
 
-    pub fn write_to<W: Write>(self, mut writer: W) -> Result<(), CsvError> {
-        let header_str = self.gen_header_str();
-        let header_write_res = writer.write(header_str.as_bytes());
-        match header_write_res {
-            Ok(n) => {
-                if n != header_str.len() {
-                    return Err(CsvError::IO(Error::new(
-                        ErrorKind::Other,
-                        "Could not write all the bytes of the headers!",
-                    )));
-                }
+    /// Where the records(read so far) in which something was replaced in lossy mode start,
+    /// the header is record 1
+    pub fn replaced_records(&self) -> &[Position] {
+        &self.reader.state.replaced
+    }
+
+    pub fn dialect(&self) -> &Dialect {
+        &self.dialect
+    }
+
+    /// The same as the `columns` field
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// The column with the given name, to look up its value in the records
+    pub fn column(&self, name: &str) -> Option<Column> {
+        self.columns
+            .iter()
+            .position(|col| col == name)
+            .map(|i| Column(self.fields[i]))
+    }
+
+    /// Reads the next record into `record`, reusing its buffers, false at the end.\
+    /// Unlike the iterator, this does not run the pipeline, which works on rows,
+    /// and the record has all the fields of the input - use `column` to find them
+    pub fn read_record(&mut self, record: &mut StringRecord) -> Result<bool, CsvError> {
+        let found = match self.pending.take() {
+            Some(pending) => {
+                *record = pending;
+                true
             }
-            Err(error) => return Err(CsvError::IO(error)),
+            None => self
+                .reader
+                .read_record(&self.dialect, record)
+                .map_err(|error| error.name_column(&self.header))?,
+        };
+        if found {
+            check_record(&self.header, record, &self.dialect)?;
         }
+        Ok(found)
+    }
 
-        let Csv {
-            reader,
-            columns,
-            selection,
-        } = self;
+    /// The same as `read_record`, but the fields are bytes
+    pub fn read_byte_record(&mut self, record: &mut ByteRecord) -> Result<bool, CsvError> {
+        let mut string_record = record.take_buffers();
+        let res = self.read_record(&mut string_record);
+        *record = ByteRecord::from(string_record);
+        res
+    }
 
-        let lines: Vec<_> = reader.lines().collect();
+    /// The column names for the rows, made again only if `columns` was changed
+    fn shared_columns(&mut self) -> Arc<[String]> {
+        if *self.names != *self.columns {
+            self.names = self.columns.as_slice().into();
+        }
+        Arc::clone(&self.names)
+    }
 
-        for line in lines {
-            if line.is_err() {
-                return Err(CsvError::IO(Error::new(
-                    ErrorKind::Other,
-                    "Could not read line!",
-                )));
+    /// Reads the next record and matches its values to the columns, None at the end.
+    /// The position of the record is given with the row for the errors of the pipeline
+    fn next_row(&mut self) -> Result<Option<(Row, Option<Position>)>, CsvError> {
+        let mut record = std::mem::take(&mut self.scratch);
+        let res = self.read_record(&mut record);
+        let row = match res {
+            Ok(true) => {
+                let values = self.project(&record);
+                let row = Row::with_columns(self.shared_columns(), values);
+                Ok(Some((row, record.position())))
             }
-            let line = line.unwrap();
-            let parse_res = parse_row_with_cols(&columns, &line);
-            match parse_res {
-                Ok(parsed) => {
-                    let formatted_row = gen_row_str_from_cols(&columns, &parsed);
-                    let should_include = match &selection {
-                        Some(box_fn) => match box_fn(&parsed) {
-                            Ok(should) => should,
-                            Err(error) => return Err(error),
-                        },
-                        None => true,
-                    };
-                    if !should_include {
-                        continue;
-                    }
-                    let write_res = writer.write(formatted_row.as_bytes());
-                    match write_res {
-                        Ok(n) => {
-                            if n != formatted_row.len() {
-                                return Err(CsvError::IO(Error::new(
-                                    ErrorKind::Other,
-                                    "Could not write all bytes of row!",
-                                )));
-                            }
-                        }
-                        Err(error) => return Err(CsvError::IO(error)),
-                    }
-                }
-                Err(error) => return Err(error),
-            }
-        }
-        Ok(())
+            Ok(false) => Ok(None),
+            Err(error) => Err(error),
+        };
+        self.scratch = record;
+        row
+    }
+
+    /// Adds a filter stage to the pipeline, the same as `filter_rows` except that the errors
+    /// of `callback` are given out as they are, not in a `StageError`.
+    /// Every selection is kept, so a row has to pass all of them
+    pub fn apply_selection<F>(&mut self, callback: F)
+    where
+        F: Fn(&Row) -> Result<bool, CsvError> + Send + Sync + 'static,
+    {
+        self.pipeline.add_selection(Arc::new(callback));
+    }
+
+    /// Writes the header(if the dialect has one) and the rows which come out of the pipeline
+    /// in the dialect of the csv
+    pub fn write_to<W: Write>(mut self, writer: W) -> Result<(), CsvError> {
+        let columns = self.output_columns();
+        write_rows(writer, self.dialect.clone(), &columns, &mut self)
     }
 }
 
@@ -154,28 +246,25 @@ impl<R: BufRead> Iterator for Csv<R> {
     type Item = Result<Row, CsvError>;
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            let mut curr_row = String::new();
-            let read_res = self.reader.read_line(&mut curr_row);
-            match read_res {
-                Ok(0) => return None,
-                Ok(_) => match self.parse_line(&curr_row) {
-                    Ok(row) => {
-                        let should_return = match &self.selection {
-                            Some(select_fn) => match select_fn(&row) {
-                                Ok(res) => res,
-                                Err(error) => return Some(Err(error)),
-                            },
-                            None => true,
-                        };
-                        if should_return {
-                            return Some(Ok(row));
-                        } else {
-                            continue;
-                        }
+            if let Some((row, position)) = self.pipeline.ready.pop_front() {
+                if let Some(schema) = &self.schema {
+                    if let Err(error) = schema.validate(&row) {
+                        return Some(Err(match position {
+                            Some(position) => error.locate(position),
+                            None => error,
+                        }));
+                    }
+                }
+                return Some(Ok(row));
+            }
+            match self.next_row() {
+                Ok(None) => return None,
+                Ok(Some((row, position))) => {
+                    if let Err(error) = self.pipeline.run(row, position) {
+                        return Some(Err(error));
                     }
-                    Err(error) => return Some(Err(error)),
-                },
-                Err(error) => return Some(Err(CsvError::IO(error))),
+                }
+                Err(error) => return Some(Err(error)),
             }
         }
     }
@@ -186,10 +275,10 @@ pub fn skip_next(input: &str, target: char) -> Option<&str> {
         return None;
     }
 
-    if input.chars().nth(0).unwrap() == target {
+    if input.starts_with(target) {
         let mut ch_stream = input.chars();
         ch_stream.next();
-        Some(&ch_stream.as_str())
+        Some(ch_stream.as_str())
     } else {
         None
     }
@@ -211,13 +300,12 @@ pub fn take_until(input: &str, target: char) -> (&str, &str) {
     let mut left_end: usize = 0;
     input
         .chars()
-        .into_iter()
         .take(index)
         .for_each(|x| left_end += x.len_utf8());
     let left_side = &input[..left_end];
     let right_side = &input[left_end..];
 
-    (&left_side, &right_side)
+    (left_side, right_side)
 }
 
 pub fn take_and_skip(input: &str, target: char) -> Option<(&str, &str)> {
@@ -231,56 +319,77 @@ pub fn take_and_skip(input: &str, target: char) -> Option<(&str, &str)> {
     Some(split)
 }
 
-fn parse_row_with_cols(columns: &Vec<String>, line: &str) -> Result<Row, CsvError> {
-    let mut row: Row = Row::new();
-    let mut line = line.trim();
-
-    match skip_next(&line, QUOTE) {
-        Some(res) => {
-            line = res;
-            for col in columns {
-                if line.is_empty() {
-                    return Err(CsvError::ParseError(
-                        "There should be a value for every column!".into(),
-                    ));
-                }
-                let curr_value = take_and_skip(&line, QUOTE);
-                match curr_value {
-                    Some((col_val, rest)) => {
-                        row.insert(col.into(), col_val.into());
-                        line = rest;
-                    }
-                    None => {
-                        return Err(CsvError::ParseError(
-                            "Every opening quote should have a matching closed one!".into(),
-                        ));
-                    }
-                }
-                line = line.trim();
-                let rest = skip_next(&line, DELIMITER);
-                match rest {
-                    Some(rest) => line = rest,
-                    None => line = "",
-                }
-                line = skip_next(line.trim(), QUOTE).unwrap_or_else(|| &"");
-            }
-            if !line.is_empty() {
-                return Err(CsvError::ParseError("There should be no extra information in the row other than a value for each column!".into()));
-            }
-            Ok(row)
+/// The columns from the first record of the input(`found` is false if there is none) and,
+/// without a header, that record, which is the first one with values
+fn split_header(
+    first: StringRecord,
+    found: bool,
+    dialect: &Dialect,
+) -> Result<(Vec<String>, Option<StringRecord>), CsvError> {
+    if !dialect.has_header {
+        let columns = (0..first.len()).map(|i| i.to_string()).collect();
+        return Ok((columns, if found { Some(first) } else { None }));
+    }
+
+    if !found {
+        return Err(CsvError::InvalidHeader(
+            "The header row must not be empty".into(),
+        ));
+    }
+    let cols = first.to_vec();
+
+    let mut distinct_cols = Vec::<String>::new();
+    for col in cols.clone() {
+        if distinct_cols.contains(&col) {
+            return Err(CsvError::InvalidHeader(
+                "The names of the columns must be unique!".into(),
+            ));
         }
-        None => Err(CsvError::ParseError(
-            "Values need to be encased in quotes".into(),
-        )),
+        distinct_cols.push(col);
     }
+    Ok((cols, None))
 }
 
-fn gen_row_str_from_cols(columns: &Vec<String>, row: &Row) -> String {
-    let mut res = String::new();
-    for i in 0..(columns.len() - 1) {
-        res += &format!("\"{}\"", &row[&columns[i]]);
-        res += ", ";
+/// Checks a record which was read from the input, the error tells where it is
+fn check_record(
+    header: &[String],
+    record: &StringRecord,
+    dialect: &Dialect,
+) -> Result<(), CsvError> {
+    check_width(header, record, dialect).map_err(|error| {
+        let error = error.name_column(header);
+        match record.position() {
+            Some(position) => error.locate(position),
+            None => error,
+        }
+    })
+}
+
+/// Checks that there is a value for every column, the excerpt of the error is the record
+/// written back
+fn check_width(
+    columns: &[String],
+    record: &StringRecord,
+    dialect: &Dialect,
+) -> Result<(), CsvError> {
+    if record.len() == columns.len() {
+        return Ok(());
     }
-    res += &format!("\"{}\"", &row[&columns[columns.len() - 1]]);
-    res + "\n"
+    let message = if record.len() < columns.len() {
+        "There should be a value for every column!"
+    } else {
+        "There should be no extra information in the row other than a value for each column!"
+    };
+    // points at the first missing or extra value
+    let column = record.len().min(columns.len());
+    let delimiter = dialect.delimiter.to_string();
+    let values = record.to_vec();
+    let at = values[..column].join(&delimiter).len();
+    let text = values.join(&delimiter);
+    Err(CsvError::ParseError(
+        ErrorDetails::new(message)
+            .with_column(column)
+            .with_excerpt(excerpt(&text, at))
+            .into(),
+    ))
 }
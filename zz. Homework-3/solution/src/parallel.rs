@@ -0,0 +1,690 @@
+//! Reading the rows on several threads.\
+//! The input is cut into byte ranges of the same size(blocks) at fixed offsets, the calling
+//! thread only reads them. Where the records end in a block is found by a worker thread:
+//! where a record starts depends on the quotes before it, so the worker guesses that one
+//! starts after the first line break of the block and follows the states of the parser from
+//! there. The guess is checked once the block before it is scanned: the part of a record at
+//! the end of that block and the first line of this one have to be whole records, which is
+//! usually a single line to scan on the calling thread. If the guess was wrong(the line
+//! break was in a quoted field), the block is scanned again on the calling thread.\
+//! The whole records between the blocks are then parsed(and go through the pipeline) by the
+//! workers, and the rows are given out in the order of the input or, if that does not
+//! matter, as soon as a chunk is done
+
+use crate::parser::{ends_record, strip_line_end};
+use crate::pipeline::Pipeline;
+use crate::reader::{is_comment, strip_bom, too_long, RecordReader};
+use crate::{write_rows, Csv, CsvError, Dialect, ParseMode, Position, Row, Schema, StringRecord};
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{BufRead, Cursor, Read, Write};
+use std::num::NonZeroUsize;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// How many bytes of the input a block has and so about how many bytes of records every
+/// worker parses at once, 1 MiB
+pub const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+
+/// How many jobs every worker may have waiting, so the reading stays ahead of the workers
+/// without reading the whole input into memory
+const CHUNKS_PER_THREAD: usize = 2;
+
+/// The bytes of the input as they were read, cut at a fixed size and not where a record ends
+struct Block {
+    /// The number of the block, the first one is 0
+    index: usize,
+    /// Where the block starts in the input
+    offset: u64,
+    text: Vec<u8>,
+    /// A record starts right at the start of the first block, in the others it is a guess
+    first: bool,
+}
+
+/// A block after a worker found the ends of the records in it
+struct Scanned {
+    block: Block,
+    /// After the first line break, where the records of the block are guessed to start
+    start: usize,
+    /// Where the last whole record ends, the rest goes on in the next block
+    end: usize,
+    /// The records between `start` and `end`
+    records: usize,
+    /// If the record at `end` is longer than the limit
+    too_long: bool,
+}
+
+/// Whole records of the input
+struct Chunk {
+    /// Where the first record starts
+    start: Position,
+    /// The bytes of the records, which are checked to be UTF-8 by the worker
+    text: Vec<u8>,
+}
+
+/// The rows of a chunk and where its records with replacements start(see
+/// `Csv::replaced_records`), or the panic of the worker which parsed it
+type Batch = thread::Result<(Vec<Result<Row, CsvError>>, Vec<Position>)>;
+
+/// What the workers do, with the number of the chunk for the ones to parse
+enum Job {
+    Scan(Block),
+    Parse(usize, Chunk),
+}
+
+/// What comes back from the workers
+enum Done {
+    Scanned(thread::Result<Scanned>),
+    Parsed(usize, Batch),
+}
+
+/// Follows the states of the parser over whole lines to find where the records end, without
+/// keeping the fields. The records are counted the same way `RecordReader` counts them
+struct Scanner {
+    /// Where the next line starts
+    next: usize,
+    /// Where the record which is being read starts and if it has a quoted field which is
+    /// still open
+    record_start: usize,
+    open: bool,
+    /// The whole records before `record_start`
+    records: usize,
+    /// If the record at `record_start` is longer than the limit, the scan stops at it
+    too_long: bool,
+}
+
+impl Scanner {
+    /// A scan from the start of a record at `start`
+    fn at(start: usize) -> Self {
+        Scanner {
+            next: start,
+            record_start: start,
+            open: false,
+            records: 0,
+            too_long: false,
+        }
+    }
+
+    /// Follows the whole lines of `text` after the ones followed so far, `offset` is where
+    /// `text` starts in the input
+    fn scan(&mut self, text: &[u8], offset: u64, dialect: &Dialect, max_record_size: usize) {
+        while !self.too_long {
+            let line_end = match text[self.next..].iter().position(|&byte| byte == b'\n') {
+                Some(i) => self.next + i + 1,
+                // only the size of a part of a line matters yet
+                None => {
+                    self.too_long = text.len() - self.record_start > max_record_size;
+                    return;
+                }
+            };
+            if line_end - self.record_start > max_record_size {
+                self.too_long = true;
+                return;
+            }
+            // what is not UTF-8 does not matter here, the quotes and delimiters are ASCII
+            let bytes = strip_bom(&text[self.next..line_end], offset + self.next as u64);
+            let line = String::from_utf8_lossy(bytes);
+            let line = line.as_ref();
+            self.next = line_end;
+
+            if self.open {
+                self.open = !ends_record(strip_line_end(line), dialect, true);
+            } else if is_comment(line, dialect)
+                || (dialect.mode == ParseMode::Lenient && line.trim().is_empty())
+            {
+                self.record_start = self.next;
+                continue;
+            } else {
+                self.open = !ends_record(strip_line_end(line), dialect, false);
+            }
+            if !self.open {
+                self.records += 1;
+                self.record_start = self.next;
+            }
+        }
+    }
+}
+
+/// Finds the ends of the records of a block, from its first line break on(the start of the
+/// first block is known). Whether a record really starts there is checked by the `Chunker`
+fn scan_block(block: Block, dialect: &Dialect, max_record_size: usize) -> Scanned {
+    let start = match block.first {
+        true => 0,
+        false => block
+            .text
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .map_or(block.text.len(), |i| i + 1),
+    };
+    let mut scanner = Scanner::at(start);
+    scanner.scan(&block.text, block.offset, dialect, max_record_size);
+    Scanned {
+        start,
+        end: scanner.record_start,
+        records: scanner.records,
+        too_long: scanner.too_long,
+        block,
+    }
+}
+
+fn count_lines(text: &[u8]) -> usize {
+    text.iter().filter(|&&byte| byte == b'\n').count()
+}
+
+/// Reads the input in blocks and puts together the chunks of whole records from the scanned
+/// blocks, in the order of the input. A block is scanned from its first line break, which is
+/// where a record starts unless the line break is in a quoted field. That is checked with
+/// the end of the block before it: the part of a record at its end and the first line of
+/// the block have to be whole records. If they are not, the block is scanned again here
+struct Chunker<R: BufRead> {
+    inner: R,
+    dialect: Dialect,
+    max_record_size: usize,
+    chunk_size: usize,
+    /// The number of blocks and the bytes read so far
+    blocks: usize,
+    read: u64,
+    /// The blocks which were scanned before the ones before them
+    scanned: BTreeMap<usize, Scanned>,
+    next_block: usize,
+    /// The start of the input which is not in a chunk yet, which starts with a record
+    carry: Vec<u8>,
+    carry_offset: u64,
+    scanner: Scanner,
+    /// The numbers of records and physical lines before the carry
+    record: usize,
+    line: usize,
+    /// The chunks which are ready to be parsed, an error is the last one
+    ready: VecDeque<Result<Chunk, CsvError>>,
+    /// An error which comes after the last chunk
+    failed: Option<CsvError>,
+    /// No more blocks are read, because the input ended or failed
+    done: bool,
+    /// No more chunks come after the ones in `ready`
+    finished: bool,
+}
+
+impl<R: BufRead> Chunker<R> {
+    /// The next block of the input, which is shorter if there was an I/O error. After it
+    /// there are no more blocks
+    fn next_block(&mut self) -> Option<Block> {
+        if self.done || self.finished {
+            return None;
+        }
+        let mut text = Vec::with_capacity(self.chunk_size);
+        if let Err(error) = (&mut self.inner)
+            .take(self.chunk_size as u64)
+            .read_to_end(&mut text)
+        {
+            // the whole records before the error are still read
+            self.failed = Some(CsvError::IO(error));
+            self.done = true;
+        }
+        if text.is_empty() {
+            self.done = true;
+            self.finish();
+            return None;
+        }
+        let block = Block {
+            index: self.blocks,
+            offset: self.read,
+            first: self.blocks == 0,
+            text,
+        };
+        self.blocks += 1;
+        self.read += block.text.len() as u64;
+        Some(block)
+    }
+
+    /// Puts together the chunks from the scanned blocks which are next in the input
+    fn add(&mut self, scanned: Scanned) {
+        self.scanned.insert(scanned.block.index, scanned);
+        while let Some(scanned) = self.scanned.remove(&self.next_block) {
+            self.next_block += 1;
+            if !self.finished {
+                self.add_block(scanned);
+            }
+        }
+        self.finish();
+    }
+
+    fn add_block(&mut self, scanned: Scanned) {
+        let Scanned {
+            block,
+            start,
+            end,
+            records,
+            too_long,
+        } = scanned;
+        self.carry.extend_from_slice(&block.text[..start]);
+        self.scan_carry();
+        let guessed = !self.scanner.open && self.scanner.record_start == self.carry.len();
+        if self.scanner.too_long || !guessed {
+            // the first line break was in a quoted field, the block goes on the carry
+            self.carry.extend_from_slice(&block.text[start..]);
+            self.scan_carry();
+            self.send_carry();
+            return;
+        }
+
+        self.send_carry();
+        debug_assert_eq!(self.carry_offset, block.offset + start as u64);
+        let lines = count_lines(&block.text[start..end]);
+        self.send(block.text[start..end].to_vec(), records, lines);
+        self.carry = block.text[end..].to_vec();
+        self.carry_offset = block.offset + end as u64;
+        self.scanner = Scanner::at(0);
+        if too_long {
+            self.scanner.too_long = true;
+            self.send_carry();
+        }
+    }
+
+    fn scan_carry(&mut self) {
+        let (offset, max_record_size) = (self.carry_offset, self.max_record_size);
+        self.scanner
+            .scan(&self.carry, offset, &self.dialect, max_record_size);
+    }
+
+    /// Sends the whole records of the carry to be parsed, or an error for a record longer
+    /// than the limit and nothing after it
+    fn send_carry(&mut self) {
+        let end = self.scanner.record_start;
+        if end > 0 {
+            let rest = self.carry.split_off(end);
+            let text = std::mem::replace(&mut self.carry, rest);
+            let lines = count_lines(&text);
+            self.send(text, self.scanner.records, lines);
+            self.carry_offset += end as u64;
+            self.scanner.next -= end;
+            self.scanner.record_start = 0;
+            self.scanner.records = 0;
+        }
+        if self.scanner.too_long {
+            // as much of it as the reader would have read
+            let read = self.carry.len().min(self.max_record_size.saturating_add(1));
+            let record = String::from_utf8_lossy(&self.carry[..read]);
+            let error = too_long(self.max_record_size, &record, self.position());
+            self.ready.push_back(Err(error));
+            self.finished = true;
+        }
+    }
+
+    /// The records start where the carry does
+    fn send(&mut self, text: Vec<u8>, records: usize, lines: usize) {
+        if text.is_empty() {
+            return;
+        }
+        let start = self.position();
+        self.ready.push_back(Ok(Chunk { start, text }));
+        self.record += records;
+        self.line += lines;
+    }
+
+    /// Where the next record starts
+    fn position(&self) -> Position {
+        Position {
+            record: self.record + 1,
+            line: self.line + 1,
+            byte: self.carry_offset,
+        }
+    }
+
+    /// After the last block the rest is the last chunk(a quoted field which is still open is
+    /// an error for the worker to find), after an I/O error only its whole records are
+    fn finish(&mut self) {
+        if !self.done || self.finished || self.next_block < self.blocks {
+            return;
+        }
+        self.scan_carry();
+        self.send_carry();
+        if self.finished {
+            return;
+        }
+        match self.failed.take() {
+            Some(error) => self.ready.push_back(Err(error)),
+            None => {
+                let text = std::mem::take(&mut self.carry);
+                self.send(text, 0, 0);
+            }
+        }
+        self.finished = true;
+    }
+}
+
+/// What a worker needs to read the rows of a chunk the same way the csv would
+struct Template {
+    header: Vec<String>,
+    fields: Vec<usize>,
+    columns: Vec<String>,
+    /// The column names which the rows of every worker share
+    names: Arc<[String]>,
+    pipeline: Pipeline,
+    schema: Option<Schema>,
+    dialect: Dialect,
+    max_record_size: usize,
+    lossy: bool,
+}
+
+impl Template {
+    fn scan(&self, block: Block) -> Scanned {
+        scan_block(block, &self.dialect, self.max_record_size)
+    }
+
+    fn rows(&self, chunk: Chunk) -> (Vec<Result<Row, CsvError>>, Vec<Position>) {
+        let mut reader = RecordReader::resume(Cursor::new(chunk.text), chunk.start);
+        reader.state.max_record_size = self.max_record_size;
+        reader.state.lossy = self.lossy;
+        let mut csv = Csv {
+            columns: self.columns.clone(),
+            header: self.header.clone(),
+            fields: self.fields.clone(),
+            reader,
+            pipeline: self.pipeline.clone(),
+            schema: self.schema.clone(),
+            dialect: self.dialect.clone(),
+            pending: None,
+            scratch: StringRecord::new(),
+            names: Arc::clone(&self.names),
+        };
+        let rows = csv.by_ref().collect();
+        (rows, csv.reader.state.replaced)
+    }
+}
+
+fn work(template: Arc<Template>, jobs: Arc<Mutex<Receiver<Job>>>, done: Sender<Done>) {
+    loop {
+        // the lock is only held while waiting for a job, not while doing it
+        let job = match jobs.lock() {
+            Ok(jobs) => jobs.recv(),
+            Err(_) => return,
+        };
+        // a stage which panics must not leave the chunk missing, the panic is given back
+        let result = match job {
+            Ok(Job::Scan(block)) => Done::Scanned(panic::catch_unwind(AssertUnwindSafe(|| {
+                template.scan(block)
+            }))),
+            Ok(Job::Parse(index, chunk)) => Done::Parsed(
+                index,
+                panic::catch_unwind(AssertUnwindSafe(|| template.rows(chunk))),
+            ),
+            Err(_) => return,
+        };
+        if done.send(result).is_err() {
+            return;
+        }
+    }
+}
+
+/// How to read a `Csv` in parallel, see `Csv::parallel`
+pub struct Parallel<R: BufRead> {
+    csv: Csv<R>,
+    threads: usize,
+    ordered: bool,
+    chunk_size: usize,
+}
+
+impl<R: BufRead> Parallel<R> {
+    /// The number of worker threads, by default the number of cores
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// If false, the rows of a chunk are given out as soon as it is parsed, so the rows may
+    /// come in any order. The errors still come in the order of the input
+    pub fn ordered(mut self, ordered: bool) -> Self {
+        self.ordered = ordered;
+        self
+    }
+
+    /// How many bytes of the input every worker scans and parses at once, `DEFAULT_CHUNK_SIZE`
+    /// by default
+    pub fn chunk_size(mut self, bytes: usize) -> Self {
+        self.chunk_size = bytes.max(1);
+        self
+    }
+
+    /// Starts the workers
+    pub fn run(self) -> Result<ParallelRows<R>, CsvError> {
+        let Parallel {
+            mut csv,
+            threads,
+            ordered,
+            chunk_size,
+        } = self;
+
+        // the rows the csv already has: the first record(without a header) and the rows
+        // waiting in the pipeline(e.g. after `infer_schema`)
+        let mut ready = VecDeque::new();
+        if csv.pending.is_some() {
+            let first = csv.next_row().and_then(|first| match first {
+                Some((row, position)) => csv.pipeline.run(row, position),
+                None => Ok(()),
+            });
+            if let Err(error) = first {
+                ready.push_back(Err(error));
+            }
+        }
+        while !csv.pipeline.ready.is_empty() {
+            ready.extend(csv.next());
+        }
+
+        let Csv {
+            columns,
+            header,
+            fields,
+            mut reader,
+            pipeline,
+            schema,
+            dialect,
+            ..
+        } = csv;
+        let position = reader.state.next_position();
+        let max_record_size = reader.state.max_record_size;
+        let lossy = reader.state.lossy;
+        let replaced = std::mem::take(&mut reader.state.replaced);
+        let template = Arc::new(Template {
+            header,
+            fields,
+            columns: columns.clone(),
+            names: columns.as_slice().into(),
+            pipeline: pipeline.clone(),
+            schema,
+            dialect: dialect.clone(),
+            max_record_size,
+            lossy,
+        });
+
+        let (jobs, waiting_jobs) = mpsc::channel();
+        let waiting_jobs = Arc::new(Mutex::new(waiting_jobs));
+        let (done_jobs, done) = mpsc::channel();
+        let mut workers = Vec::with_capacity(threads);
+        for i in 0..threads {
+            let template = Arc::clone(&template);
+            let waiting_jobs = Arc::clone(&waiting_jobs);
+            let done_jobs = done_jobs.clone();
+            let worker = thread::Builder::new()
+                .name(format!("csv-worker-{}", i))
+                .spawn(move || work(template, waiting_jobs, done_jobs))?;
+            workers.push(worker);
+        }
+
+        Ok(ParallelRows {
+            columns: pipeline.output_columns(&columns),
+            chunker: Chunker {
+                inner: reader.into_inner(),
+                dialect: dialect.clone(),
+                max_record_size,
+                chunk_size,
+                blocks: 0,
+                read: position.byte,
+                scanned: BTreeMap::new(),
+                next_block: 0,
+                carry: Vec::new(),
+                carry_offset: position.byte,
+                scanner: Scanner::at(0),
+                record: position.record - 1,
+                line: position.line - 1,
+                ready: VecDeque::new(),
+                failed: None,
+                done: false,
+                finished: false,
+            },
+            jobs: Some(jobs),
+            done,
+            chunks: 0,
+            workers,
+            in_flight: 0,
+            max_in_flight: threads * CHUNKS_PER_THREAD,
+            ordered,
+            next_batch: 0,
+            waiting: BTreeMap::new(),
+            ready,
+            replaced,
+            dialect,
+        })
+    }
+}
+
+/// The rows of a `Csv` read in parallel, see `Csv::parallel`
+pub struct ParallelRows<R: BufRead> {
+    /// The columns of the rows, the same as the ones of the csv after its pipeline
+    pub columns: Vec<String>,
+    chunker: Chunker<R>,
+    /// None once the workers have to stop
+    jobs: Option<Sender<Job>>,
+    done: Receiver<Done>,
+    /// The number of chunks so far
+    chunks: usize,
+    workers: Vec<JoinHandle<()>>,
+    /// The jobs which were sent to the workers and have not come back yet
+    in_flight: usize,
+    max_in_flight: usize,
+    ordered: bool,
+    /// The number of the first chunk whose rows have not been given out yet(or, when not
+    /// ordered, whose errors have not)
+    next_batch: usize,
+    /// The chunks which came back before the ones before them
+    waiting: BTreeMap<usize, Vec<Result<Row, CsvError>>>,
+    ready: VecDeque<Result<Row, CsvError>>,
+    replaced: Vec<Position>,
+    dialect: Dialect,
+}
+
+impl<R: BufRead> ParallelRows<R> {
+    /// See `Csv::replaced_records`, the ones in the chunks which have been parsed so far.
+    /// They are in the order of the input only if there is a single thread
+    pub fn replaced_records(&self) -> &[Position] {
+        &self.replaced
+    }
+
+    /// Writes the header(if the dialect has one) and the rows in the dialect of the csv
+    pub fn write_to<W: Write>(mut self, writer: W) -> Result<(), CsvError> {
+        let columns = std::mem::take(&mut self.columns);
+        write_rows(writer, self.dialect.clone(), &columns, &mut self)
+    }
+
+    /// Sends the chunks which are ready to be parsed and the blocks to scan, until every
+    /// worker has enough jobs
+    fn send_jobs(&mut self) {
+        loop {
+            while let Some(chunk) = self.chunker.ready.pop_front() {
+                let index = self.chunks;
+                self.chunks += 1;
+                match chunk {
+                    Ok(chunk) => self.send(Job::Parse(index, chunk)),
+                    // the error is the last "chunk"
+                    Err(error) => self.receive(index, vec![Err(error)]),
+                }
+            }
+            if self.in_flight >= self.max_in_flight {
+                return;
+            }
+            match self.chunker.next_block() {
+                Some(block) => self.send(Job::Scan(block)),
+                // the end of the input may have made the last chunk
+                None if self.chunker.ready.is_empty() => return,
+                None => {}
+            }
+        }
+    }
+
+    fn send(&mut self, job: Job) {
+        if let Some(Ok(())) = self.jobs.as_ref().map(|jobs| jobs.send(job)) {
+            self.in_flight += 1;
+        }
+    }
+
+    /// Puts the rows of the chunk(and maybe the ones which waited for it) in `ready`
+    fn receive(&mut self, index: usize, mut rows: Vec<Result<Row, CsvError>>) {
+        if !self.ordered && rows.iter().all(Result::is_ok) {
+            self.ready.extend(rows.drain(..));
+        }
+        self.waiting.insert(index, rows);
+        while let Some(rows) = self.waiting.remove(&self.next_batch) {
+            self.ready.extend(rows);
+            self.next_batch += 1;
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ParallelRows<R> {
+    type Item = Result<Row, CsvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.ready.pop_front() {
+                return Some(row);
+            }
+            self.send_jobs();
+            if !self.ready.is_empty() {
+                continue;
+            }
+            if self.in_flight == 0 {
+                return None;
+            }
+
+            // every job comes back, the workers catch the panics of the stages
+            let done = self.done.recv().ok()?;
+            self.in_flight -= 1;
+            match done {
+                Done::Scanned(Ok(scanned)) => self.chunker.add(scanned),
+                Done::Parsed(index, Ok((rows, replaced))) => {
+                    self.replaced.extend(replaced);
+                    self.receive(index, rows);
+                }
+                Done::Scanned(Err(payload)) | Done::Parsed(_, Err(payload)) => {
+                    panic::resume_unwind(payload)
+                }
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Drop for ParallelRows<R> {
+    /// Stops the workers, after the jobs they are doing
+    fn drop(&mut self) {
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl<R: BufRead> Csv<R> {
+    /// Reads the rows(through the pipeline and the schema) on several threads. The stages
+    /// run on the worker threads, which is why they must be `Send + Sync`. The input does
+    /// not have to be a file which can seek, its blocks are read one after the other on the
+    /// calling thread.\
+    /// By default there is a thread for every core and the rows keep the order of the input
+    pub fn parallel(self) -> Parallel<R> {
+        Parallel {
+            csv: self,
+            threads: thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            ordered: true,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+}
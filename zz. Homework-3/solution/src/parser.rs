@@ -0,0 +1,393 @@
+//! A state machine which splits a single record into fields, following RFC 4180:
+//! - fields are separated by the delimiter and may or may not be quoted
+//! - a quote inside a quoted field is written twice: `"say ""hi"""`
+//! - a quoted field may contain delimiters and line breaks
+//! - empty fields are allowed: `a,,c` has three fields
+
+use crate::error::excerpt;
+use crate::{CsvError, Dialect, ErrorDetails, StringRecord};
+
+/// How forgiving the parser is with input which does not follow RFC 4180 exactly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Exactly RFC 4180 - a quote may only start a field
+    /// and after the closing quote there must be a delimiter or the end of the record
+    Strict,
+    /// A quote in the middle of an unquoted field is taken literally
+    /// and the blank lines between the records are skipped
+    #[default]
+    Lenient,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Nothing of the current field has been read yet
+    FieldStart,
+    Unquoted,
+    Quoted,
+    /// A quote was found inside a quoted field - either the field ends or the quote is escaped
+    QuoteInQuoted,
+    /// The quoted field has ended, only whitespace is allowed until the delimiter(trimming only)
+    AfterQuoted,
+    /// The escape character was found, the next one is taken literally
+    EscapedUnquoted,
+    EscapedQuoted,
+}
+
+/// Where the parsing of a record stopped at the end of a line inside a quoted field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Continued {
+    /// The bytes of the record which were parsed
+    at: usize,
+    state: State,
+}
+
+/// Removes a single trailing line terminator - LF or CRLF
+pub fn strip_line_end(line: &str) -> &str {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
+/// Splits one record(without its line terminator) into its fields,
+/// using the default dialect for the mode(trimming only in the lenient one)
+pub fn parse_record(input: &str, mode: ParseMode) -> Result<Vec<String>, CsvError> {
+    parse_record_with(input, &Dialect::with_mode(mode))
+}
+
+/// Splits one record(without its line terminator) into its fields
+pub fn parse_record_with(input: &str, dialect: &Dialect) -> Result<Vec<String>, CsvError> {
+    let mut record = StringRecord::new();
+    parse_record_into(input, dialect, &mut record)?;
+    Ok(record.to_vec())
+}
+
+/// The same as `parse_record_with`, but the fields are put in `record`
+pub(crate) fn parse_record_into(
+    input: &str,
+    dialect: &Dialect,
+    record: &mut StringRecord,
+) -> Result<(), CsvError> {
+    if parse_fields(input, dialect, record)? {
+        Ok(())
+    } else {
+        Err(CsvError::ParseError(
+            ErrorDetails::new("Every opening quote should have a matching closed one!")
+                .with_excerpt(excerpt(input, 0))
+                .into(),
+        ))
+    }
+}
+
+/// Where the states put the fields
+trait Fields {
+    fn push_char(&mut self, ch: char);
+    fn end_field(&mut self, trim_end: bool);
+    fn len(&self) -> usize;
+}
+
+impl Fields for StringRecord {
+    fn push_char(&mut self, ch: char) {
+        StringRecord::push_char(self, ch);
+    }
+
+    fn end_field(&mut self, trim_end: bool) {
+        StringRecord::end_field(self, trim_end);
+    }
+
+    fn len(&self) -> usize {
+        StringRecord::len(self)
+    }
+}
+
+/// Keeps nothing, only the states are followed
+impl Fields for () {
+    fn push_char(&mut self, _: char) {}
+
+    fn end_field(&mut self, _: bool) {}
+
+    fn len(&self) -> usize {
+        0
+    }
+}
+
+/// Puts the fields of the input in the(cleared) record.
+/// Returns false if the input ends inside a quoted field,
+/// which means that the record continues on the next line
+pub(crate) fn parse_fields(
+    input: &str,
+    dialect: &Dialect,
+    record: &mut StringRecord,
+) -> Result<bool, CsvError> {
+    record.clear();
+    follow_states(input, 0, dialect, record, &mut State::FieldStart)
+}
+
+/// The same as `parse_fields` for a record which is read a line at a time: `input` is the
+/// record so far and only what comes after the part which was parsed with the lines before
+/// is parsed. If the record continues on the next line, `continued` is where to go on from
+pub(crate) fn parse_more_fields(
+    input: &str,
+    dialect: &Dialect,
+    record: &mut StringRecord,
+    continued: &mut Option<Continued>,
+) -> Result<bool, CsvError> {
+    let (from, mut state) = match continued.take() {
+        Some(Continued { at, state }) => (at, state),
+        None => {
+            record.clear();
+            (0, State::FieldStart)
+        }
+    };
+    let complete = follow_states(input, from, dialect, record, &mut state)?;
+    if !complete {
+        *continued = Some(Continued {
+            at: input.len(),
+            state,
+        });
+    }
+    Ok(complete)
+}
+
+/// True if a record ends with this line(without its line terminator), also if it is broken.
+/// `continued` is for a line inside a quoted field which an earlier line opened.\
+/// The states are the same as for `parse_fields`, but the fields are not kept, so the ends
+/// of the records are found much faster than they are parsed
+pub(crate) fn ends_record(line: &str, dialect: &Dialect, continued: bool) -> bool {
+    // the line break of the earlier line was in the quoted field
+    let state = match continued {
+        true => State::Quoted,
+        false => State::FieldStart,
+    };
+    let ascii = dialect.delimiter.is_ascii()
+        && dialect.quote.is_ascii()
+        && dialect.escape.is_none_or(|escape| escape.is_ascii());
+    if ascii {
+        return skip_states(line, dialect, state);
+    }
+    // only a quote can open a field which goes on to the next line
+    let special = |ch: char| ch == dialect.quote || dialect.escape == Some(ch);
+    if !continued && !line.contains(special) {
+        return true;
+    }
+    let mut state = state;
+    !matches!(
+        follow_states(line, 0, dialect, &mut (), &mut state),
+        Ok(false)
+    )
+}
+
+/// The same as `ends_record` with `follow_states`, but the runs of bytes which cannot change
+/// the state(e.g. everything in a quoted field up to the next quote) are skipped at once.
+/// Only for a dialect with ASCII special characters, which are never a part of another
+/// UTF-8 character, so the other bytes can be skipped without decoding them
+fn skip_states(line: &str, dialect: &Dialect, mut state: State) -> bool {
+    let bytes = line.as_bytes();
+    let delimiter = dialect.delimiter as u8;
+    let quote = dialect.quote as u8;
+    let escape = dialect.escape.map(|escape| escape as u8);
+    let strict = dialect.mode == ParseMode::Strict;
+    let trim = dialect.trim;
+    // the length of the whitespace character at `at`(which starts a character), if it is one
+    let whitespace = |at: usize| {
+        let ch = match bytes[at] {
+            byte if byte.is_ascii() => char::from(byte),
+            _ => line[at..].chars().next()?,
+        };
+        (trim && ch.is_whitespace()).then(|| ch.len_utf8())
+    };
+    // the bytes which stop a run in an unquoted and in a quoted field
+    let mut unquoted_stops = [false; 256];
+    let mut quoted_stops = [false; 256];
+    unquoted_stops[usize::from(delimiter)] = true;
+    unquoted_stops[usize::from(quote)] = strict;
+    quoted_stops[usize::from(quote)] = true;
+    if let Some(escape) = escape {
+        unquoted_stops[usize::from(escape)] = true;
+        quoted_stops[usize::from(escape)] = true;
+    }
+
+    // only a quote can open a field which goes on to the next line
+    if state == State::FieldStart && !bytes.iter().any(|&b| quoted_stops[usize::from(b)]) {
+        return true;
+    }
+
+    let mut at = 0;
+    while at < bytes.len() {
+        let byte = bytes[at];
+        state = match state {
+            State::FieldStart => match byte {
+                _ if byte == delimiter => State::FieldStart,
+                _ if byte == quote => State::Quoted,
+                _ if escape == Some(byte) => State::EscapedUnquoted,
+                _ => match whitespace(at) {
+                    Some(len) => {
+                        at += len;
+                        continue;
+                    }
+                    None => State::Unquoted,
+                },
+            },
+            State::Unquoted => {
+                match bytes[at..]
+                    .iter()
+                    .position(|&b| unquoted_stops[usize::from(b)])
+                {
+                    Some(skipped) => {
+                        at += skipped;
+                        match bytes[at] {
+                            b if b == delimiter => State::FieldStart,
+                            b if escape == Some(b) => State::EscapedUnquoted,
+                            // a quote in an unquoted field breaks the record
+                            _ => return true,
+                        }
+                    }
+                    None => return true,
+                }
+            }
+            State::Quoted => {
+                match bytes[at..]
+                    .iter()
+                    .position(|&b| quoted_stops[usize::from(b)])
+                {
+                    Some(skipped) => {
+                        at += skipped;
+                        match bytes[at] {
+                            b if b == quote => State::QuoteInQuoted,
+                            _ => State::EscapedQuoted,
+                        }
+                    }
+                    None => return false,
+                }
+            }
+            // the escaped character is taken literally, the other bytes of a longer one are never
+            // special
+            State::EscapedUnquoted => State::Unquoted,
+            State::EscapedQuoted => State::Quoted,
+            State::QuoteInQuoted | State::AfterQuoted => match byte {
+                _ if byte == quote && state == State::QuoteInQuoted => State::Quoted,
+                _ if byte == delimiter => State::FieldStart,
+                _ => match whitespace(at) {
+                    Some(len) => {
+                        at += len;
+                        state = State::AfterQuoted;
+                        continue;
+                    }
+                    // anything else breaks the record
+                    None => return true,
+                },
+            },
+        };
+        at += 1;
+    }
+    !matches!(state, State::Quoted | State::EscapedQuoted)
+}
+
+/// Follows the states from `state` over the input after `from`, the excerpts of the errors
+/// are from the whole input
+fn follow_states<F: Fields>(
+    input: &str,
+    from: usize,
+    dialect: &Dialect,
+    record: &mut F,
+    state: &mut State,
+) -> Result<bool, CsvError> {
+    let lenient = dialect.mode == ParseMode::Lenient;
+    let trim = dialect.trim;
+    let delimiter = dialect.delimiter;
+    let quote = dialect.quote;
+    let is_escape = |ch: char| dialect.escape == Some(ch);
+
+    // the field which is being read is always the next one in the record
+    let error = |message: &str, at: usize, column: usize| {
+        CsvError::ParseError(
+            ErrorDetails::new(message)
+                .with_column(column)
+                .with_excerpt(excerpt(input, at))
+                .into(),
+        )
+    };
+
+    let finish = |record: &mut F, state: State| {
+        record.end_field(trim && state == State::Unquoted);
+    };
+
+    for (at, ch) in input[from..].char_indices() {
+        let at = from + at;
+        *state = match *state {
+            State::FieldStart => match ch {
+                _ if ch == delimiter => {
+                    finish(record, *state);
+                    State::FieldStart
+                }
+                _ if ch == quote => State::Quoted,
+                _ if is_escape(ch) => State::EscapedUnquoted,
+                _ if trim && ch.is_whitespace() => State::FieldStart,
+                _ => {
+                    record.push_char(ch);
+                    State::Unquoted
+                }
+            },
+            State::Unquoted => match ch {
+                _ if ch == delimiter => {
+                    finish(record, *state);
+                    State::FieldStart
+                }
+                _ if is_escape(ch) => State::EscapedUnquoted,
+                _ if ch == quote && !lenient => {
+                    return Err(error(
+                        "A quote may only appear in a quoted field!",
+                        at,
+                        record.len(),
+                    ))
+                }
+                _ => {
+                    record.push_char(ch);
+                    State::Unquoted
+                }
+            },
+            State::Quoted => match ch {
+                _ if is_escape(ch) => State::EscapedQuoted,
+                _ if ch == quote => State::QuoteInQuoted,
+                _ => {
+                    record.push_char(ch);
+                    State::Quoted
+                }
+            },
+            State::EscapedUnquoted => {
+                record.push_char(ch);
+                State::Unquoted
+            }
+            State::EscapedQuoted => {
+                record.push_char(ch);
+                State::Quoted
+            }
+            State::QuoteInQuoted | State::AfterQuoted => match ch {
+                _ if ch == quote && *state == State::QuoteInQuoted => {
+                    record.push_char(quote);
+                    State::Quoted
+                }
+                _ if ch == delimiter => {
+                    finish(record, *state);
+                    State::FieldStart
+                }
+                _ if trim && ch.is_whitespace() => State::AfterQuoted,
+                _ => {
+                    return Err(error(
+                        "There should be nothing between a closing quote and the delimiter!",
+                        at,
+                        record.len(),
+                    ))
+                }
+            },
+        };
+    }
+
+    match *state {
+        State::Quoted | State::EscapedQuoted => return Ok(false),
+        // a lone escape at the very end has nothing to escape
+        State::EscapedUnquoted => record.push_char(dialect.escape.unwrap()),
+        _ => {}
+    }
+    finish(record, *state);
+    Ok(true)
+}
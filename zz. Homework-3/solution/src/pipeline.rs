@@ -0,0 +1,269 @@
+//! The stages which the rows go through after they are read, in the order they were added.\
+//! A stage can drop rows(`filter_rows`), compute a column(`map_column`), turn a row into
+//! several(`flat_map_rows`) or just look at them(`inspect_rows`). The iterator,
+//! `write_to` and `deserialize` all give out the rows which come out of the last stage
+
+use crate::{Csv, CsvError, Position, Row};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::io::BufRead;
+use std::sync::Arc;
+
+/// Whatever error a stage returns, it is kept as the source of the `StageError`
+pub type BoxError = Box<dyn Error + Send + Sync>;
+
+/// The stages are shared by the threads which read the rows in parallel, see `Csv::parallel`
+type FilterFn = Arc<dyn Fn(&Row) -> Result<bool, BoxError> + Send + Sync>;
+pub(crate) type SelectFn = Arc<dyn Fn(&Row) -> Result<bool, CsvError> + Send + Sync>;
+type MapFn = Arc<dyn Fn(&Row) -> Result<String, BoxError> + Send + Sync>;
+type FlatMapFn = Arc<dyn Fn(Row) -> Result<Vec<Row>, BoxError> + Send + Sync>;
+type InspectFn = Arc<dyn Fn(&Row) + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageKind {
+    Filter,
+    Map,
+    FlatMap,
+    Inspect,
+}
+
+impl fmt::Display for StageKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            StageKind::Filter => "filter",
+            StageKind::Map => "map",
+            StageKind::FlatMap => "flat map",
+            StageKind::Inspect => "inspect",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A stage which failed on a row
+#[derive(Debug)]
+pub struct StageError {
+    /// The index of the stage, the first one added is 0
+    pub stage: usize,
+    pub kind: StageKind,
+    /// Where the record of the row starts, None for `parse_line`
+    pub position: Option<Position>,
+    /// The error which the stage returned, it can be downcast to its own type
+    pub source: BoxError,
+}
+
+impl fmt::Display for StageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "stage {}({}) failed", self.stage, self.kind)?;
+        if let Some(position) = &self.position {
+            write!(
+                f,
+                " on record {} starting at line {}",
+                position.record, position.line
+            )?;
+        }
+        write!(f, ": {}", self.source)
+    }
+}
+
+#[derive(Clone)]
+enum Stage {
+    Filter(FilterFn),
+    /// A filter from `Csv::apply_selection`, its errors are given out as they are
+    Select(SelectFn),
+    /// The column is added at the end if the rows do not have it yet
+    Map {
+        column: String,
+        compute: MapFn,
+        /// The columns of the rows after the stage, shared the same way as the input ones
+        columns: Option<Arc<[String]>>,
+    },
+    FlatMap(FlatMapFn),
+    Inspect(InspectFn),
+}
+
+impl Stage {
+    fn kind(&self) -> StageKind {
+        match self {
+            Stage::Filter(_) | Stage::Select(_) => StageKind::Filter,
+            Stage::Map { .. } => StageKind::Map,
+            Stage::FlatMap(_) => StageKind::FlatMap,
+            Stage::Inspect(_) => StageKind::Inspect,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Pipeline {
+    stages: Vec<Stage>,
+    /// The rows which came out of the last stage and have not been given out yet,
+    /// with the positions of their records
+    pub(crate) ready: VecDeque<(Row, Option<Position>)>,
+}
+
+impl Pipeline {
+    /// True if there are no stages and no rows waiting to be given out
+    #[cfg(feature = "serde")]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.stages.is_empty() && self.ready.is_empty()
+    }
+
+    pub(crate) fn add_selection(&mut self, select: SelectFn) {
+        self.stages.push(Stage::Select(select));
+    }
+
+    /// The columns of the rows which come out of the last stage
+    pub(crate) fn output_columns(&self, input: &[String]) -> Vec<String> {
+        let mut columns = input.to_vec();
+        for stage in &self.stages {
+            if let Stage::Map { column, .. } = stage {
+                if !columns.contains(column) {
+                    columns.push(column.clone());
+                }
+            }
+        }
+        columns
+    }
+
+    /// Puts the rows which come out of the last stage in `ready`
+    pub(crate) fn run(&mut self, row: Row, position: Option<Position>) -> Result<(), CsvError> {
+        self.run_from(0, row, position)
+    }
+
+    fn run_from(
+        &mut self,
+        start: usize,
+        mut row: Row,
+        position: Option<Position>,
+    ) -> Result<(), CsvError> {
+        for i in start..self.stages.len() {
+            let kind = self.stages[i].kind();
+            let failed = |source| {
+                CsvError::StageError(StageError {
+                    stage: i,
+                    kind,
+                    position,
+                    source,
+                })
+            };
+
+            match &mut self.stages[i] {
+                Stage::Filter(keep) => {
+                    if !keep(&row).map_err(failed)? {
+                        return Ok(());
+                    }
+                }
+                Stage::Select(select) => {
+                    if !select(&row)? {
+                        return Ok(());
+                    }
+                }
+                Stage::Map {
+                    column,
+                    compute,
+                    columns,
+                } => {
+                    let value = compute(&row).map_err(failed)?;
+                    match row.get_mut(column) {
+                        Some(old) => *old = value,
+                        None => {
+                            if !columns
+                                .as_ref()
+                                .is_some_and(|columns| extends(columns, &row, column))
+                            {
+                                *columns = Some(extended_columns(&row, column));
+                            }
+                            row.push(Arc::clone(columns.as_ref().unwrap()), value);
+                        }
+                    }
+                }
+                Stage::FlatMap(expand) => {
+                    let input = row.columns().to_vec();
+                    let rows = expand(row).map_err(failed)?;
+                    for row in rows {
+                        if row.columns() != input.as_slice() {
+                            return Err(failed(
+                                "The rows of a flat map must have the columns of its input".into(),
+                            ));
+                        }
+                        self.run_from(i + 1, row, position)?;
+                    }
+                    return Ok(());
+                }
+                Stage::Inspect(look) => look(&row),
+            }
+        }
+        self.ready.push_back((row, position));
+        Ok(())
+    }
+}
+
+/// True if `columns` are the columns of the row with `column` at the end
+fn extends(columns: &[String], row: &Row, column: &str) -> bool {
+    columns.len() == row.len() + 1
+        && columns[..row.len()] == *row.columns()
+        && columns[row.len()] == column
+}
+
+fn extended_columns(row: &Row, column: &str) -> Arc<[String]> {
+    let mut columns = row.columns().to_vec();
+    columns.push(column.into());
+    columns.into()
+}
+
+impl<R: BufRead> Csv<R> {
+    /// Keeps only the rows for which `keep` returns true
+    pub fn filter_rows<F, E>(&mut self, keep: F) -> &mut Self
+    where
+        F: Fn(&Row) -> Result<bool, E> + Send + Sync + 'static,
+        E: Into<BoxError>,
+    {
+        let keep = move |row: &Row| keep(row).map_err(Into::into);
+        self.pipeline.stages.push(Stage::Filter(Arc::new(keep)));
+        self
+    }
+
+    /// Sets the value of `column` to what `compute` returns. If the rows do not have such
+    /// a column, it is added after the others(and so written in the header by `write_to`)
+    pub fn map_column<F, E>(&mut self, column: &str, compute: F) -> &mut Self
+    where
+        F: Fn(&Row) -> Result<String, E> + Send + Sync + 'static,
+        E: Into<BoxError>,
+    {
+        let compute = move |row: &Row| compute(row).map_err(Into::into);
+        self.pipeline.stages.push(Stage::Map {
+            column: column.into(),
+            compute: Arc::new(compute),
+            columns: None,
+        });
+        self
+    }
+
+    /// Replaces every row with the rows `expand` returns(possibly none), which must have the
+    /// same columns as the row they came from
+    pub fn flat_map_rows<F, E>(&mut self, expand: F) -> &mut Self
+    where
+        F: Fn(Row) -> Result<Vec<Row>, E> + Send + Sync + 'static,
+        E: Into<BoxError>,
+    {
+        let expand = move |row: Row| expand(row).map_err(Into::into);
+        self.pipeline.stages.push(Stage::FlatMap(Arc::new(expand)));
+        self
+    }
+
+    /// Calls `look` with every row which gets to this stage(in parallel the rows of different
+    /// chunks are seen in any order)
+    pub fn inspect_rows<F>(&mut self, look: F) -> &mut Self
+    where
+        F: Fn(&Row) + Send + Sync + 'static,
+    {
+        self.pipeline.stages.push(Stage::Inspect(Arc::new(look)));
+        self
+    }
+
+    /// The columns of the rows which come out of the pipeline: `columns` and then the ones
+    /// added by `map_column`
+    pub fn output_columns(&self) -> Vec<String> {
+        self.pipeline.output_columns(&self.columns)
+    }
+}
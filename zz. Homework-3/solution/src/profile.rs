@@ -0,0 +1,392 @@
+//! An overview of every column, made in a single pass over the rows with bounded memory:
+//! the distinct values are estimated with a HyperLogLog and the most frequent ones are
+//! tracked with a fixed number of counters(the Space-Saving algorithm)
+
+use crate::{Csv, CsvError};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io::BufRead;
+
+/// How many of the most frequent values `Csv::profile` reports
+pub const DEFAULT_TOP_K: usize = 5;
+
+/// How many counters are kept for every value reported in the top-k, more counters make the
+/// counts more exact
+const COUNTERS_PER_TOP_VALUE: usize = 10;
+
+/// The HyperLogLog has 2^12 registers, which gives an error of about 1.6%
+const HLL_PRECISION: u32 = 12;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Profile {
+    pub rows: u64,
+    pub columns: Vec<ColumnProfile>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ColumnProfile {
+    pub name: String,
+    /// The number of values, the same as the number of rows
+    pub count: u64,
+    /// The number of empty values
+    pub empty: u64,
+    /// An estimate of the number of distinct values(other than the empty one)
+    pub distinct: u64,
+    /// Compared as numbers if all the values are numbers, as text otherwise
+    pub min: Option<String>,
+    pub max: Option<String>,
+    /// Only if all the values(other than the empty ones) are numbers
+    pub mean: Option<f64>,
+    /// The population standard deviation, only for numbers
+    pub stddev: Option<f64>,
+    /// The most frequent values with their counts, the most frequent first. With more
+    /// distinct values than counters the counts may be too high
+    pub top: Vec<(String, u64)>,
+    /// The number of values for every range of lengths(in characters)
+    pub lengths: Vec<LengthBucket>,
+}
+
+/// The values with `min..=max` characters, the ranges are 0, 1, 2-3, 4-7, 8-15 and so on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LengthBucket {
+    pub min: usize,
+    pub max: usize,
+    pub count: u64,
+}
+
+/// Estimates the number of distinct values, with a fixed amount of memory
+struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    fn new() -> Self {
+        HyperLogLog {
+            registers: vec![0; 1 << HLL_PRECISION],
+        }
+    }
+
+    fn insert(&mut self, value: &str) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let index = (hash >> (64 - HLL_PRECISION)) as usize;
+        // the bit after the rest of the hash stops the count if the rest is all zeros
+        let rest = (hash << HLL_PRECISION) | (1 << (HLL_PRECISION - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|&rank| 2f64.powi(-(rank as i32)))
+            .sum();
+        let raw = alpha * m * m / sum;
+
+        // few values leave registers empty, counting them is more exact then
+        let zeros = self.registers.iter().filter(|&&rank| rank == 0).count();
+        let estimate = if raw <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            raw
+        };
+        estimate.round() as u64
+    }
+}
+
+/// Counts the most frequent values with a fixed number of counters. A new value takes the
+/// place of the least frequent one and gets its count
+struct SpaceSaving {
+    counters: HashMap<String, u64>,
+    capacity: usize,
+}
+
+impl SpaceSaving {
+    fn insert(&mut self, value: &str) {
+        if let Some(count) = self.counters.get_mut(value) {
+            *count += 1;
+            return;
+        }
+        if self.counters.len() < self.capacity {
+            self.counters.insert(value.into(), 1);
+            return;
+        }
+        let smallest = self
+            .counters
+            .iter()
+            .min_by(|left, right| left.1.cmp(right.1).then_with(|| right.0.cmp(left.0)))
+            .map(|(value, &count)| (value.clone(), count));
+        if let Some((evicted, count)) = smallest {
+            self.counters.remove(&evicted);
+            self.counters.insert(value.into(), count + 1);
+        }
+    }
+
+    /// The `k` most frequent values, the ones with equal counts in the order of the values
+    fn top(&self, k: usize) -> Vec<(String, u64)> {
+        let mut top: Vec<(String, u64)> = self
+            .counters
+            .iter()
+            .map(|(value, &count)| (value.clone(), count))
+            .collect();
+        top.sort_by(|left, right| right.1.cmp(&left.1).then_with(|| left.0.cmp(&right.0)));
+        top.truncate(k);
+        top
+    }
+}
+
+/// What is kept for a column while the rows are read
+struct ColumnStats {
+    count: u64,
+    empty: u64,
+    distinct: HyperLogLog,
+    text_min: Option<String>,
+    text_max: Option<String>,
+    /// None once a value is not a number
+    numbers: Option<Numbers>,
+    top: SpaceSaving,
+    /// The count for every power of two of the length
+    lengths: Vec<u64>,
+}
+
+/// The running mean and variance(Welford's algorithm) and the extremes, with their text
+#[derive(Default)]
+struct Numbers {
+    count: u64,
+    mean: f64,
+    m2: f64,
+    min: Option<(f64, String)>,
+    max: Option<(f64, String)>,
+}
+
+impl Numbers {
+    fn insert(&mut self, number: f64, text: &str) {
+        self.count += 1;
+        let delta = number - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (number - self.mean);
+
+        if self.min.as_ref().is_none_or(|(min, _)| number < *min) {
+            self.min = Some((number, text.into()));
+        }
+        if self.max.as_ref().is_none_or(|(max, _)| number > *max) {
+            self.max = Some((number, text.into()));
+        }
+    }
+}
+
+/// The index of the bucket for a length: 0, 1, 2-3, 4-7, ...
+fn length_bucket(length: usize) -> usize {
+    match length {
+        0 => 0,
+        _ => (usize::BITS - length.leading_zeros()) as usize,
+    }
+}
+
+impl ColumnStats {
+    fn new(top_k: usize) -> Self {
+        ColumnStats {
+            count: 0,
+            empty: 0,
+            distinct: HyperLogLog::new(),
+            text_min: None,
+            text_max: None,
+            numbers: Some(Numbers::default()),
+            top: SpaceSaving {
+                counters: HashMap::new(),
+                capacity: top_k * COUNTERS_PER_TOP_VALUE,
+            },
+            lengths: Vec::new(),
+        }
+    }
+
+    fn insert(&mut self, value: &str) {
+        self.count += 1;
+        let bucket = length_bucket(value.chars().count());
+        if self.lengths.len() <= bucket {
+            self.lengths.resize(bucket + 1, 0);
+        }
+        self.lengths[bucket] += 1;
+
+        if value.trim().is_empty() {
+            self.empty += 1;
+            return;
+        }
+        self.distinct.insert(value);
+        self.top.insert(value);
+        if self.text_min.as_deref().is_none_or(|min| value < min) {
+            self.text_min = Some(value.into());
+        }
+        if self.text_max.as_deref().is_none_or(|max| value > max) {
+            self.text_max = Some(value.into());
+        }
+        if let Some(numbers) = &mut self.numbers {
+            match value.trim().parse::<f64>() {
+                Ok(number) => numbers.insert(number, value),
+                Err(_) => self.numbers = None,
+            }
+        }
+    }
+
+    fn finish(self, name: String, top_k: usize) -> ColumnProfile {
+        let lengths = self
+            .lengths
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .map(|(bucket, &count)| LengthBucket {
+                min: if bucket == 0 { 0 } else { 1 << (bucket - 1) },
+                max: if bucket == 0 { 0 } else { (1 << bucket) - 1 },
+                count,
+            })
+            .collect();
+
+        let numbers = self.numbers.filter(|numbers| numbers.count > 0);
+        let (min, max, mean, stddev) = match numbers {
+            Some(numbers) => (
+                numbers.min.map(|(_, text)| text),
+                numbers.max.map(|(_, text)| text),
+                Some(numbers.mean),
+                Some((numbers.m2 / numbers.count as f64).sqrt()),
+            ),
+            None => (self.text_min, self.text_max, None, None),
+        };
+
+        ColumnProfile {
+            name,
+            count: self.count,
+            empty: self.empty,
+            distinct: self.distinct.estimate(),
+            min,
+            max,
+            mean,
+            stddev,
+            top: self.top.top(top_k),
+            lengths,
+        }
+    }
+}
+
+impl Profile {
+    pub fn column(&self, name: &str) -> Option<&ColumnProfile> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        // there are only strings, numbers and lists in a profile
+        serde_json::to_string_pretty(self).expect("A profile can always be written as JSON")
+    }
+}
+
+fn format_number(number: Option<f64>) -> String {
+    match number {
+        Some(number) if number.fract() == 0.0 && number.abs() < 1e15 => format!("{}", number),
+        Some(number) => format!("{:.3}", number),
+        None => String::new(),
+    }
+}
+
+/// A table with a line for every column
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header = [
+            "column", "count", "empty", "distinct", "min", "max", "mean", "stddev", "top",
+            "lengths",
+        ];
+        let mut lines: Vec<Vec<String>> =
+            vec![header.iter().map(|title| title.to_string()).collect()];
+        for column in &self.columns {
+            let top: Vec<String> = column
+                .top
+                .iter()
+                .map(|(value, count)| format!("{} ({})", value, count))
+                .collect();
+            let lengths: Vec<String> = column
+                .lengths
+                .iter()
+                .map(|bucket| match bucket.min == bucket.max {
+                    true => format!("{}:{}", bucket.min, bucket.count),
+                    false => format!("{}-{}:{}", bucket.min, bucket.max, bucket.count),
+                })
+                .collect();
+            lines.push(vec![
+                column.name.clone(),
+                column.count.to_string(),
+                column.empty.to_string(),
+                format!("~{}", column.distinct),
+                column.min.clone().unwrap_or_default(),
+                column.max.clone().unwrap_or_default(),
+                format_number(column.mean),
+                format_number(column.stddev),
+                top.join(", "),
+                lengths.join(" "),
+            ]);
+        }
+
+        let widths: Vec<usize> = (0..header.len())
+            .map(|i| {
+                lines
+                    .iter()
+                    .map(|line| line[i].chars().count())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        writeln!(f, "{} rows", self.rows)?;
+        for (n, line) in lines.iter().enumerate() {
+            let cells: Vec<String> = line
+                .iter()
+                .zip(&widths)
+                .map(|(cell, &width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            writeln!(f, "{}", cells.join(" | ").trim_end())?;
+            if n == 0 {
+                let rule: Vec<String> = widths.iter().map(|&width| "-".repeat(width)).collect();
+                writeln!(f, "{}", rule.join("-+-"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Csv<R> {
+    /// Goes through all the rows(which come out of the pipeline) and describes every column,
+    /// with the `DEFAULT_TOP_K` most frequent values
+    pub fn profile(self) -> Result<Profile, CsvError> {
+        self.profile_top(DEFAULT_TOP_K)
+    }
+
+    /// The same as `profile`, with the `top_k` most frequent values
+    pub fn profile_top(self, top_k: usize) -> Result<Profile, CsvError> {
+        let names = self.output_columns();
+        let mut stats: Vec<ColumnStats> = names.iter().map(|_| ColumnStats::new(top_k)).collect();
+        let mut rows = 0;
+        for row in self {
+            let row = row?;
+            rows += 1;
+            for (column, value) in stats.iter_mut().zip(row.values()) {
+                column.insert(value);
+            }
+        }
+
+        Ok(Profile {
+            rows,
+            columns: names
+                .into_iter()
+                .zip(stats)
+                .map(|(name, column)| column.finish(name, top_k))
+                .collect(),
+        })
+    }
+}
@@ -0,0 +1,280 @@
+//! Reads whole records - a quoted field may contain line breaks,
+//! so a single record can span several physical lines.\
+//! The lines are read as bytes and have to be UTF-8(a byte order mark at the start is
+//! skipped), in lossy mode what is not is replaced with U+FFFD
+
+use crate::error::excerpt;
+use crate::parser::{parse_more_fields, strip_line_end, Continued, ParseMode};
+use crate::{CsvError, Dialect, ErrorDetails, Position, StringRecord};
+use std::io::{BufRead, Read};
+
+/// The default limit for the size of a single record, 1 MiB
+pub const DEFAULT_MAX_RECORD_SIZE: usize = 1 << 20;
+
+pub(crate) struct RecordReader<R: BufRead> {
+    inner: R,
+    pub state: RecordState,
+}
+
+/// The UTF-8 byte order mark, which some programs put at the start of the file
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Putting the lines together into records, whichever way the lines are read
+pub(crate) struct RecordState {
+    /// The number of records read so far, including the broken ones
+    record: usize,
+    /// The number of physical lines read so far
+    line: usize,
+    /// The number of bytes read so far
+    offset: u64,
+    /// The number of bytes of the current record
+    size: usize,
+    /// If something in the current record was replaced with U+FFFD
+    replacing: bool,
+    /// The bytes of the line which was just read, before they are checked to be UTF-8
+    pub bytes: Vec<u8>,
+    /// The lines of the current record, kept between the records so it is allocated only once
+    pub text: String,
+    /// Where the parsing stopped, if the current record goes on to the next line
+    continued: Option<Continued>,
+    pub max_record_size: usize,
+    /// Replace what is not UTF-8 instead of failing the record
+    pub lossy: bool,
+    /// Where the records with replacements start
+    pub replaced: Vec<Position>,
+}
+
+/// What comes after a line, see `RecordState::add_line`
+pub(crate) enum Step {
+    /// The record is complete(or the input ended, if false)
+    Done(bool),
+    /// The line was a comment or a blank line, a new record starts on the next one
+    Skipped,
+    /// The quoted field goes on to the next line
+    Continues,
+}
+
+/// Only the first line of a record can be a comment
+pub(crate) fn is_comment(line: &str, dialect: &Dialect) -> bool {
+    let line = if dialect.trim {
+        line.trim_start()
+    } else {
+        line
+    };
+    dialect
+        .comment_prefixes
+        .iter()
+        .any(|prefix| line.starts_with(prefix.as_str()))
+}
+
+/// The bytes of a line without the byte order mark, if the line is at the start of the input
+pub(crate) fn strip_bom(bytes: &[u8], offset: u64) -> &[u8] {
+    match bytes.strip_prefix(BOM) {
+        Some(rest) if offset == 0 => rest,
+        _ => bytes,
+    }
+}
+
+/// The error for a record(which starts at `position`) which is not UTF-8
+fn not_utf8(text: &str, bytes: &[u8], position: Position) -> CsvError {
+    let valid = match std::str::from_utf8(bytes) {
+        Err(error) => error.valid_up_to(),
+        Ok(_) => bytes.len(),
+    };
+    let at = text.len() + valid;
+    let text = format!("{}{}", text, String::from_utf8_lossy(bytes));
+    CsvError::ParseError(
+        ErrorDetails::new(
+            "The record is not valid UTF-8, read it through a `Decoder` or in lossy mode!",
+        )
+        .with_excerpt(excerpt(&text, at))
+        .into(),
+    )
+    .locate(position)
+}
+
+/// The error for a record(which starts at `position`) longer than the limit
+pub(crate) fn too_long(max_record_size: usize, text: &str, position: Position) -> CsvError {
+    CsvError::ParseError(
+        ErrorDetails::new(format!(
+            "The record is longer than the limit of {} bytes!",
+            max_record_size
+        ))
+        .with_excerpt(excerpt(text, 0))
+        .into(),
+    )
+    .locate(position)
+}
+
+impl RecordState {
+    /// Starts reading the record at `position`
+    pub fn new(position: Position) -> Self {
+        RecordState {
+            record: position.record.saturating_sub(1),
+            line: position.line.saturating_sub(1),
+            offset: position.byte,
+            size: 0,
+            replacing: false,
+            bytes: Vec::new(),
+            text: String::new(),
+            continued: None,
+            max_record_size: DEFAULT_MAX_RECORD_SIZE,
+            lossy: false,
+            replaced: Vec::new(),
+        }
+    }
+
+    /// Where the next record starts
+    pub fn next_position(&self) -> Position {
+        Position {
+            record: self.record + 1,
+            line: self.line + 1,
+            byte: self.offset,
+        }
+    }
+
+    /// The most bytes which the next line may take, so a line is never read past the limit,
+    /// even if there is no line break for a long time
+    pub fn limit(&self) -> u64 {
+        ((self.max_record_size - self.size) as u64).saturating_add(1)
+    }
+
+    /// Starts the record which is read next and returns where it starts
+    pub fn start_record(&mut self) -> Position {
+        self.text.clear();
+        self.continued = None;
+        self.size = 0;
+        self.replacing = false;
+        self.next_position()
+    }
+
+    /// Continues the record which starts at `position` with the line which was just read into
+    /// `bytes`(none at the end of the input), and puts its fields in `record` once it is
+    /// complete.\
+    /// Comment lines are skipped and in lenient mode so are the blank lines between the records
+    pub fn add_line(
+        &mut self,
+        position: Position,
+        dialect: &Dialect,
+        record: &mut StringRecord,
+    ) -> Result<Step, CsvError> {
+        let step = self.follow_line(position, dialect, record);
+        if self.replacing && matches!(step, Ok(Step::Done(true))) {
+            self.replaced.push(position);
+        }
+        step
+    }
+
+    fn follow_line(
+        &mut self,
+        position: Position,
+        dialect: &Dialect,
+        record: &mut StringRecord,
+    ) -> Result<Step, CsvError> {
+        let read = self.bytes.len();
+        let bytes = strip_bom(&self.bytes, self.offset);
+        self.offset += read as u64;
+        self.size += read;
+        if read > 0 {
+            self.line += 1;
+        }
+        // before the check for UTF-8, since the limit may cut the line inside a character
+        if self.size > self.max_record_size {
+            self.text.push_str(&String::from_utf8_lossy(bytes));
+            self.record += 1;
+            return Err(too_long(self.max_record_size, &self.text, position));
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(line) => self.text.push_str(line),
+            Err(_) if self.lossy => {
+                self.text.push_str(&String::from_utf8_lossy(bytes));
+                self.replacing = true;
+            }
+            Err(_) => {
+                self.record += 1;
+                return Err(not_utf8(&self.text, bytes, position));
+            }
+        }
+        if self.text.is_empty() {
+            record.clear();
+            return Ok(Step::Done(false));
+        }
+
+        if self.line == position.line && is_comment(&self.text, dialect) {
+            return Ok(Step::Skipped);
+        }
+
+        // only the new line is parsed, a record of many lines is not parsed again for each
+        let line = strip_line_end(&self.text);
+        let complete = match parse_more_fields(line, dialect, record, &mut self.continued) {
+            Ok(complete) => complete,
+            Err(error) => {
+                self.record += 1;
+                return Err(error.locate(position));
+            }
+        };
+        if complete {
+            if dialect.mode == ParseMode::Lenient && self.text.trim().is_empty() {
+                return Ok(Step::Skipped);
+            }
+            self.record += 1;
+            record.set_position(position);
+            return Ok(Step::Done(true));
+        }
+        if read == 0 {
+            self.record += 1;
+            return Err(CsvError::ParseError(
+                ErrorDetails::new("Every opening quote should have a matching closed one!")
+                    .with_excerpt(excerpt(&self.text, 0))
+                    .into(),
+            )
+            .locate(position));
+        }
+        // the quote is still open - the record continues on the next line
+        Ok(Step::Continues)
+    }
+}
+
+impl<R: BufRead> RecordReader<R> {
+    pub fn new(inner: R) -> Self {
+        RecordReader::resume(inner, Position::default())
+    }
+
+    /// A reader for a part of the input, which starts where the record at `position` does.
+    /// The positions of its records are the ones they have in the whole input
+    pub fn resume(inner: R, position: Position) -> Self {
+        RecordReader {
+            inner,
+            state: RecordState::new(position),
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Reads lines until the record is complete and puts its fields in `record`,
+    /// false at the end of the input
+    pub fn read_record(
+        &mut self,
+        dialect: &Dialect,
+        record: &mut StringRecord,
+    ) -> Result<bool, CsvError> {
+        loop {
+            let position = self.state.start_record();
+
+            loop {
+                self.state.bytes.clear();
+                (&mut self.inner)
+                    .take(self.state.limit())
+                    .read_until(b'\n', &mut self.state.bytes)
+                    .map_err(CsvError::IO)?;
+                match self.state.add_line(position, dialect, record)? {
+                    Step::Done(found) => return Ok(found),
+                    Step::Skipped => break,
+                    Step::Continues => {}
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! Records which keep all their fields in a single buffer.
+//! Reading with `Csv::read_record` into the same record over and over
+//! does not allocate once the buffers are big enough for the longest record
+
+use crate::Position;
+use std::ops::Index;
+
+/// A column found by name once with `Csv::column`, so that the lookups in every record
+/// are just indexing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Column(pub(crate) usize);
+
+impl Column {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+/// The fields of a record, one after the other in a single `String`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StringRecord {
+    buffer: String,
+    /// Where each field ends in the buffer
+    ends: Vec<usize>,
+    position: Option<Position>,
+}
+
+/// The same as `StringRecord`, but the fields are bytes - for when the values are only compared
+/// or parsed as numbers. The input is still required to be UTF-8
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ByteRecord {
+    buffer: Vec<u8>,
+    ends: Vec<usize>,
+    position: Option<Position>,
+}
+
+/// The range of the field with the given index in the buffer
+fn bounds(ends: &[usize], i: usize) -> Option<(usize, usize)> {
+    let end = *ends.get(i)?;
+    let start = if i == 0 { 0 } else { ends[i - 1] };
+    Some((start, end))
+}
+
+impl StringRecord {
+    pub fn new() -> Self {
+        StringRecord::default()
+    }
+
+    /// A record which can hold `fields` fields of `bytes` bytes in total without allocating
+    pub fn with_capacity(bytes: usize, fields: usize) -> Self {
+        StringRecord {
+            buffer: String::with_capacity(bytes),
+            ends: Vec::with_capacity(fields),
+            position: None,
+        }
+    }
+
+    /// The number of fields
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    pub fn get(&self, i: usize) -> Option<&str> {
+        bounds(&self.ends, i).map(|(start, end)| &self.buffer[start..end])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> + '_ {
+        (0..self.len()).map(move |i| &self[i])
+    }
+
+    /// Where the record starts in the input, None if it was not read from one
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    /// Removes the fields, but keeps the buffers
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.ends.clear();
+        self.position = None;
+    }
+
+    pub fn push_field(&mut self, field: &str) {
+        self.buffer.push_str(field);
+        self.ends.push(self.buffer.len());
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.buffer.as_bytes()
+    }
+
+    pub(crate) fn set_position(&mut self, position: Position) {
+        self.position = Some(position);
+    }
+
+    /// Adds a character to the field which is being read
+    pub(crate) fn push_char(&mut self, ch: char) {
+        self.buffer.push(ch);
+    }
+
+    /// Finishes the field which is being read, optionally without the whitespace at its end
+    pub(crate) fn end_field(&mut self, trim_end: bool) {
+        if trim_end {
+            let start = self.ends.last().copied().unwrap_or(0);
+            let len = self.buffer[start..].trim_end().len();
+            self.buffer.truncate(start + len);
+        }
+        self.ends.push(self.buffer.len());
+    }
+
+    pub(crate) fn to_vec(&self) -> Vec<String> {
+        self.iter().map(String::from).collect()
+    }
+}
+
+impl ByteRecord {
+    pub fn new() -> Self {
+        ByteRecord::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    pub fn get(&self, i: usize) -> Option<&[u8]> {
+        bounds(&self.ends, i).map(|(start, end)| &self.buffer[start..end])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        (0..self.len()).map(move |i| &self[i])
+    }
+
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.ends.clear();
+        self.position = None;
+    }
+
+    pub fn push_field(&mut self, field: &[u8]) {
+        self.buffer.extend_from_slice(field);
+        self.ends.push(self.buffer.len());
+    }
+
+    /// Empties the record and lends its buffers to a `StringRecord`,
+    /// they are given back with `ByteRecord::from`
+    pub(crate) fn take_buffers(&mut self) -> StringRecord {
+        let mut buffer = std::mem::take(&mut self.buffer);
+        buffer.clear();
+        let mut ends = std::mem::take(&mut self.ends);
+        ends.clear();
+        StringRecord {
+            // an empty buffer is always valid UTF-8
+            buffer: String::from_utf8(buffer).unwrap_or_default(),
+            ends,
+            position: None,
+        }
+    }
+}
+
+impl From<StringRecord> for ByteRecord {
+    fn from(record: StringRecord) -> Self {
+        ByteRecord {
+            buffer: record.buffer.into_bytes(),
+            ends: record.ends,
+            position: record.position,
+        }
+    }
+}
+
+impl Index<usize> for StringRecord {
+    type Output = str;
+
+    fn index(&self, i: usize) -> &str {
+        match self.get(i) {
+            Some(field) => field,
+            None => panic!(
+                "The record has {} fields, there is no field {}",
+                self.len(),
+                i
+            ),
+        }
+    }
+}
+
+impl Index<Column> for StringRecord {
+    type Output = str;
+
+    fn index(&self, column: Column) -> &str {
+        &self[column.0]
+    }
+}
+
+impl Index<usize> for ByteRecord {
+    type Output = [u8];
+
+    fn index(&self, i: usize) -> &[u8] {
+        match self.get(i) {
+            Some(field) => field,
+            None => panic!(
+                "The record has {} fields, there is no field {}",
+                self.len(),
+                i
+            ),
+        }
+    }
+}
+
+impl Index<Column> for ByteRecord {
+    type Output = [u8];
+
+    fn index(&self, column: Column) -> &[u8] {
+        &self[column.0]
+    }
+}
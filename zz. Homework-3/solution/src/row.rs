@@ -0,0 +1,119 @@
+//! A row of values in the order of the header, which can still be looked up by column name
+
+use std::iter::FromIterator;
+use std::ops::Index;
+use std::sync::Arc;
+
+/// The values of a record, matched to the columns.\
+/// The column names are shared between all the rows of a `Csv`, so they are not copied for
+/// every row. Looking up a value by name goes through the names, by position it does not
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Row {
+    columns: Arc<[String]>,
+    values: Vec<String>,
+}
+
+impl Row {
+    /// There must be a value for every column
+    pub(crate) fn with_columns(columns: Arc<[String]>, values: Vec<String>) -> Self {
+        debug_assert_eq!(columns.len(), values.len());
+        Row { columns, values }
+    }
+
+    /// Adds a value at the end, `columns` are the columns of the row with the new one
+    pub(crate) fn push(&mut self, columns: Arc<[String]>, value: String) {
+        self.values.push(value);
+        debug_assert_eq!(columns.len(), self.values.len());
+        self.columns = columns;
+    }
+
+    /// The value in the column with the given name
+    pub fn get(&self, column: &str) -> Option<&String> {
+        self.position(column).map(|i| &self.values[i])
+    }
+
+    pub fn get_mut(&mut self, column: &str) -> Option<&mut String> {
+        let i = self.position(column)?;
+        Some(&mut self.values[i])
+    }
+
+    /// The index of the column with the given name
+    pub fn position(&self, column: &str) -> Option<usize> {
+        self.columns.iter().position(|name| name == column)
+    }
+
+    pub fn contains_column(&self, column: &str) -> bool {
+        self.position(column).is_some()
+    }
+
+    /// The number of columns
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    pub fn values(&self) -> &[String] {
+        &self.values
+    }
+
+    pub fn into_values(self) -> Vec<String> {
+        self.values
+    }
+
+    /// The (column, value) pairs in the order of the columns
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.columns
+            .iter()
+            .zip(self.values.iter())
+            .map(|(column, value)| (column.as_str(), value.as_str()))
+    }
+}
+
+impl Index<usize> for Row {
+    type Output = String;
+
+    fn index(&self, i: usize) -> &String {
+        &self.values[i]
+    }
+}
+
+impl Index<&str> for Row {
+    type Output = String;
+
+    fn index(&self, column: &str) -> &String {
+        match self.get(column) {
+            Some(value) => value,
+            None => panic!("There is no column {:?} in the row", column),
+        }
+    }
+}
+
+/// So that a column name which is a `String` works too, the same as with a `HashMap`
+impl Index<&String> for Row {
+    type Output = String;
+
+    fn index(&self, column: &String) -> &String {
+        &self[column.as_str()]
+    }
+}
+
+/// Builds a row from (column, value) pairs, the columns are in the order of the pairs
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for Row {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(pairs: I) -> Self {
+        let (columns, values): (Vec<String>, Vec<String>) = pairs
+            .into_iter()
+            .map(|(column, value)| (column.into(), value.into()))
+            .unzip();
+        Row {
+            columns: columns.into(),
+            values,
+        }
+    }
+}
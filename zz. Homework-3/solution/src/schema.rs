@@ -0,0 +1,230 @@
+//! The types of the columns: inferred from a sample of the rows and then checked for every
+//! row. With the `serde` feature a schema can be written to and read from JSON
+
+use crate::sort::parse_date;
+use crate::{Csv, CsvError, ErrorDetails, Position, Row};
+use std::cmp::Ordering;
+use std::io::BufRead;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum ColumnType {
+    /// Fits in an `i64`
+    Integer,
+    Float,
+    /// `true` or `false`, in any case
+    Bool,
+    /// `YYYY-MM-DD`, optionally with a time, the same as the dates of `sort_by`
+    Date,
+    String,
+}
+
+impl ColumnType {
+    /// The most specific types first, a column gets the first one which all its values have
+    const INFERRED: [ColumnType; 4] = [
+        ColumnType::Integer,
+        ColumnType::Float,
+        ColumnType::Bool,
+        ColumnType::Date,
+    ];
+
+    /// True if the value(which is not empty) is of this type
+    pub fn accepts(self, value: &str) -> bool {
+        let value = value.trim();
+        match self {
+            ColumnType::Integer => value.parse::<i64>().is_ok(),
+            ColumnType::Float => value.parse::<f64>().is_ok(),
+            ColumnType::Bool => {
+                value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false")
+            }
+            ColumnType::Date => parse_date(value).is_some(),
+            ColumnType::String => true,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ColumnType::Integer => "an integer",
+            ColumnType::Float => "a float",
+            ColumnType::Bool => "a bool",
+            ColumnType::Date => "a date",
+            ColumnType::String => "a string",
+        }
+    }
+
+    /// Compares two values of this type, the way min and max are found
+    fn compare(self, left: &str, right: &str) -> Ordering {
+        let (left, right) = (left.trim(), right.trim());
+        match self {
+            ColumnType::Integer => left
+                .parse::<i64>()
+                .unwrap_or_default()
+                .cmp(&right.parse().unwrap_or_default()),
+            ColumnType::Float => left
+                .parse::<f64>()
+                .unwrap_or_default()
+                .total_cmp(&right.parse().unwrap_or_default()),
+            ColumnType::Bool => left.to_lowercase().cmp(&right.to_lowercase()),
+            ColumnType::Date => parse_date(left).cmp(&parse_date(right)),
+            ColumnType::String => left.cmp(right),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ColumnSchema {
+    pub name: String,
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
+    pub column_type: ColumnType,
+    /// If the values may be empty
+    pub nullable: bool,
+    /// The smallest and the biggest value in the sample, only informative - they are not
+    /// checked, since the sample does not have to have all the values
+    pub min: Option<String>,
+    pub max: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Schema {
+    pub columns: Vec<ColumnSchema>,
+}
+
+impl Schema {
+    /// The most specific schema which all the rows fit, they must have the given columns
+    pub fn infer(columns: &[String], rows: &[Row]) -> Schema {
+        let columns = columns
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let values: Vec<&str> = rows
+                    .iter()
+                    .map(|row| row[i].as_str())
+                    .filter(|value| !value.trim().is_empty())
+                    .collect();
+                let column_type = ColumnType::INFERRED
+                    .iter()
+                    .copied()
+                    .find(|column_type| {
+                        !values.is_empty() && values.iter().all(|value| column_type.accepts(value))
+                    })
+                    .unwrap_or(ColumnType::String);
+                let by_type = |left: &&&str, right: &&&str| column_type.compare(left, right);
+
+                ColumnSchema {
+                    name: name.clone(),
+                    column_type,
+                    // nothing is known about a column without values
+                    nullable: values.len() < rows.len() || values.is_empty(),
+                    min: values.iter().min_by(by_type).map(|value| value.to_string()),
+                    max: values.iter().max_by(by_type).map(|value| value.to_string()),
+                }
+            })
+            .collect();
+        Schema { columns }
+    }
+
+    pub fn column(&self, name: &str) -> Option<&ColumnSchema> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    /// Checks the values of the row for the columns of the schema, other columns can be
+    /// anything
+    pub fn validate(&self, row: &Row) -> Result<(), CsvError> {
+        for column in &self.columns {
+            let i = row
+                .position(&column.name)
+                .ok_or_else(|| CsvError::InvalidColumn(column.name.clone()))?;
+            let value = &row[i];
+
+            let message = if value.trim().is_empty() {
+                if column.nullable {
+                    continue;
+                }
+                format!(
+                    "Expected {}, but the value is empty",
+                    column.column_type.name()
+                )
+            } else if column.column_type.accepts(value) {
+                continue;
+            } else {
+                format!("Expected {}, got {:?}", column.column_type.name(), value)
+            };
+
+            let mut details = ErrorDetails::new(message).with_column(i);
+            details.column_name = Some(column.name.clone());
+            details.excerpt = Some(value.clone());
+            return Err(CsvError::InvalidRow(details.into()));
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, CsvError> {
+        serde_json::to_string_pretty(self)
+            .map_err(|error| CsvError::InvalidSchema(error.to_string()))
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Schema, CsvError> {
+        serde_json::from_str(json).map_err(|error| CsvError::InvalidSchema(error.to_string()))
+    }
+}
+
+impl<R: BufRead> Csv<R> {
+    /// Infers the schema of the first `sample_rows` rows(which come out of the pipeline).
+    /// The rows are kept and still given out by the iterator, but not by `read_record`,
+    /// also when reading them fails
+    pub fn infer_schema(&mut self, sample_rows: usize) -> Result<Schema, CsvError> {
+        let mut sample = Vec::with_capacity(sample_rows);
+        let res = self.take_sample(&mut sample, sample_rows);
+        let rows: Vec<Row> = sample.iter().map(|(row, _)| row.clone()).collect();
+        for ready in sample.into_iter().rev() {
+            self.pipeline.ready.push_front(ready);
+        }
+        res?;
+        Ok(Schema::infer(&self.output_columns(), &rows))
+    }
+
+    /// Moves the next rows out of the pipeline into `sample`, until it has `sample_rows`
+    fn take_sample(
+        &mut self,
+        sample: &mut Vec<(Row, Option<Position>)>,
+        sample_rows: usize,
+    ) -> Result<(), CsvError> {
+        while sample.len() < sample_rows {
+            match self.pipeline.ready.pop_front() {
+                Some(ready) => sample.push(ready),
+                None => match self.next_row()? {
+                    Some((row, position)) => self.pipeline.run(row, position)?,
+                    None => break,
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// Every row which the iterator gives out from now on is checked against the schema,
+    /// its columns must be columns of the rows
+    pub fn set_schema(&mut self, schema: Schema) -> Result<(), CsvError> {
+        let columns = self.output_columns();
+        if let Some(missing) = schema
+            .columns
+            .iter()
+            .find(|column| !columns.contains(&column.name))
+        {
+            return Err(CsvError::InvalidColumn(missing.name.clone()));
+        }
+        self.schema = Some(schema);
+        Ok(())
+    }
+
+    pub fn schema(&self) -> Option<&Schema> {
+        self.schema.as_ref()
+    }
+}
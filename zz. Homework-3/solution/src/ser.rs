@@ -0,0 +1,425 @@
+//! Writes any `T: Serialize` as a record(behind the `serde` feature).\
+//! The fields of a struct(or the keys of a map) become the header, a tuple or a sequence
+//! is written as it is. Every field has to be a single value: numbers, booleans, strings,
+//! unit enum variants and `Option`s, which are written as empty values when None
+
+use crate::{CsvError, CsvWriter, ErrorDetails};
+use serde::ser::{self, Impossible, Serialize};
+use std::fmt;
+use std::io::Write;
+
+impl<W: Write> CsvWriter<W> {
+    /// Writes the value as a record. If nothing has been written yet, the dialect has a header
+    /// and the value has field names, they are written as the header first
+    pub fn serialize<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), CsvError> {
+        let mut fields = RecordFields::default();
+        value.serialize(&mut fields).map_err(|error| {
+            let mut details = ErrorDetails::new(error.message);
+            if let Some(column) = error.column {
+                details.column = Some(column);
+                details.column_name = fields.names.get(column).cloned();
+            }
+            CsvError::InvalidRow(details.into())
+        })?;
+
+        if self.at_start && self.dialect().has_header && !fields.names.is_empty() {
+            self.write_header(&fields.names)?;
+        }
+        self.write_record(&fields.values)
+    }
+}
+
+/// A value which cannot be written, in the field with the given index
+#[derive(Debug)]
+struct SerError {
+    message: String,
+    column: Option<usize>,
+}
+
+impl fmt::Display for SerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for SerError {}
+
+impl ser::Error for SerError {
+    fn custom<M: fmt::Display>(message: M) -> Self {
+        SerError {
+            message: message.to_string(),
+            column: None,
+        }
+    }
+}
+
+fn not_a_single_value() -> SerError {
+    ser::Error::custom("A field must be a single value, not a sequence, a map or a struct")
+}
+
+/// The names(if the value has them) and the values of the record
+#[derive(Default)]
+struct RecordFields {
+    names: Vec<String>,
+    values: Vec<String>,
+}
+
+impl RecordFields {
+    fn push_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        let column = self.values.len();
+        let value = value.serialize(FieldSerializer).map_err(|mut error| {
+            error.column = Some(column);
+            error
+        })?;
+        self.values.push(value);
+        Ok(())
+    }
+}
+
+macro_rules! serialize_single {
+    ($($method:ident: $type:ty,)*) => {
+        $(
+            fn $method(self, value: $type) -> Result<(), SerError> {
+                self.push_value(&value)
+            }
+        )*
+    };
+}
+
+/// The whole record - a single value is a record with one field
+impl ser::Serializer for &mut RecordFields {
+    type Ok = ();
+    type Error = SerError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Impossible<(), SerError>;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), SerError>;
+
+    serialize_single! {
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+        serialize_f32: f32,
+        serialize_f64: f64,
+        serialize_char: char,
+        serialize_str: &str,
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<(), SerError> {
+        let value = ser::Serializer::serialize_bytes(FieldSerializer, value)?;
+        self.values.push(value);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), SerError> {
+        self.push_value(&())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), SerError> {
+        self.push_value(&())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), SerError> {
+        self.push_value(&())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), SerError> {
+        self.push_value(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self, SerError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, SerError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, SerError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerError> {
+        Err(ser::Error::custom(
+            "Enum variants with fields cannot be written",
+        ))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self, SerError> {
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, SerError> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerError> {
+        Err(ser::Error::custom(
+            "Enum variants with fields cannot be written",
+        ))
+    }
+}
+
+impl ser::SerializeSeq for &mut RecordFields {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.push_value(value)
+    }
+
+    fn end(self) -> Result<(), SerError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut RecordFields {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.push_value(value)
+    }
+
+    fn end(self) -> Result<(), SerError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut RecordFields {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.push_value(value)
+    }
+
+    fn end(self) -> Result<(), SerError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for &mut RecordFields {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerError> {
+        self.names.push(key.serialize(FieldSerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.push_value(value)
+    }
+
+    fn end(self) -> Result<(), SerError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut RecordFields {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerError> {
+        self.names.push(key.into());
+        self.push_value(value)
+    }
+
+    fn end(self) -> Result<(), SerError> {
+        Ok(())
+    }
+}
+
+/// A single field, written as text
+struct FieldSerializer;
+
+macro_rules! serialize_display {
+    ($($method:ident: $type:ty,)*) => {
+        $(
+            fn $method(self, value: $type) -> Result<String, SerError> {
+                Ok(value.to_string())
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for FieldSerializer {
+    type Ok = String;
+    type Error = SerError;
+    type SerializeSeq = Impossible<String, SerError>;
+    type SerializeTuple = Impossible<String, SerError>;
+    type SerializeTupleStruct = Impossible<String, SerError>;
+    type SerializeTupleVariant = Impossible<String, SerError>;
+    type SerializeMap = Impossible<String, SerError>;
+    type SerializeStruct = Impossible<String, SerError>;
+    type SerializeStructVariant = Impossible<String, SerError>;
+
+    serialize_display! {
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+        serialize_f32: f32,
+        serialize_f64: f64,
+        serialize_char: char,
+        serialize_str: &str,
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<String, SerError> {
+        String::from_utf8(value.to_vec())
+            .map_err(|_| ser::Error::custom("Only UTF-8 bytes can be written"))
+    }
+
+    fn serialize_none(self) -> Result<String, SerError> {
+        Ok(String::new())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<String, SerError> {
+        Ok(String::new())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, SerError> {
+        Ok(String::new())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<String, SerError> {
+        Ok(variant.into())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, SerError> {
+        Err(not_a_single_value())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerError> {
+        Err(not_a_single_value())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerError> {
+        Err(not_a_single_value())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerError> {
+        Err(not_a_single_value())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerError> {
+        Err(not_a_single_value())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerError> {
+        Err(not_a_single_value())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, SerError> {
+        Err(not_a_single_value())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerError> {
+        Err(not_a_single_value())
+    }
+}
@@ -0,0 +1,200 @@
+//! Guesses the dialect of an unknown file from its beginning, similar to Python's `csv.Sniffer`
+//!
+//! Every candidate delimiter/quote pair is used to parse the sample and the one which gives
+//! the same number of fields in the most records wins. The header is detected by comparing
+//! the first record with the values below it - a name in a column of numbers is a good sign.
+
+use crate::dialect::{Dialect, LineTerminator, QuoteStyle};
+use crate::reader::RecordReader;
+use crate::{Csv, CsvError, StringRecord};
+use std::collections::HashMap;
+use std::io::{BufRead, Chain, Cursor, Read};
+
+/// The default amount of input which is inspected, 64 KiB
+pub const DEFAULT_SNIFF_SIZE: usize = 64 * 1024;
+
+const DELIMITERS: [char; 5] = [',', ';', '\t', '|', ':'];
+const QUOTES: [char; 2] = ['"', '\''];
+
+/// The whole input - the inspected sample followed by the rest
+pub type SniffedReader<R> = Chain<Cursor<Vec<u8>>, R>;
+
+/// The result of `Csv::sniff` - the guess and the input, which still starts from the beginning
+pub struct Sniffed<R: BufRead> {
+    pub dialect: Dialect,
+    /// How sure the guess is, from 0 to 1
+    pub confidence: f64,
+    reader: SniffedReader<R>,
+}
+
+impl<R: BufRead> Sniffed<R> {
+    /// The whole input, including the inspected sample
+    pub fn into_reader(self) -> SniffedReader<R> {
+        self.reader
+    }
+
+    /// Reads the input with the guessed dialect
+    pub fn into_csv(self) -> Result<Csv<SniffedReader<R>>, CsvError> {
+        Csv::with_dialect(self.reader, self.dialect)
+    }
+}
+
+/// Parses the sample and returns the records, stops at the first bad one
+fn sample_records(sample: &str, dialect: &Dialect) -> Vec<Vec<String>> {
+    let mut reader = RecordReader::new(Cursor::new(sample.as_bytes()));
+    let mut record = StringRecord::new();
+    let mut records = Vec::new();
+    while let Ok(true) = reader.read_record(dialect, &mut record) {
+        records.push(record.to_vec());
+    }
+    records
+}
+
+/// The share of the records which have the most common number of fields,
+/// only counts if that number is above 1
+fn consistency(records: &[Vec<String>]) -> f64 {
+    let mut widths = HashMap::<usize, usize>::new();
+    for record in records {
+        *widths.entry(record.len()).or_insert(0) += 1;
+    }
+    match widths
+        .into_iter()
+        .max_by_key(|&(width, count)| (count, width))
+    {
+        Some((width, count)) if width > 1 => count as f64 / records.len() as f64,
+        _ => 0.0,
+    }
+}
+
+/// How many fields in the sample start with the quote right after a delimiter or a line break,
+/// and how many fields there are overall
+fn quoted_fields(sample: &str, delimiter: char, quote: char) -> (usize, usize) {
+    let mut quoted = 0;
+    let mut fields = 0;
+    let mut field_start = true;
+    let mut in_quotes = false;
+    for ch in sample.chars() {
+        if field_start && !ch.is_whitespace() {
+            fields += 1;
+            if ch == quote {
+                quoted += 1;
+            }
+            field_start = false;
+        }
+        if ch == quote {
+            // a doubled quote toggles twice, which is just right
+            in_quotes = !in_quotes;
+        } else if !in_quotes && (ch == delimiter || ch == '\n') {
+            field_start = true;
+        }
+    }
+    (quoted, fields)
+}
+
+fn line_terminator(sample: &str) -> LineTerminator {
+    let crlf = sample.matches("\r\n").count();
+    let lf = sample.matches('\n').count() - crlf;
+    if crlf > lf {
+        LineTerminator::CrLf
+    } else {
+        LineTerminator::Lf
+    }
+}
+
+/// Every column votes: if all the values below the first record are numbers,
+/// or all have the same length, the first record is a header if it does not fit in
+fn has_header(records: &[Vec<String>]) -> bool {
+    let (first, rest) = match records.split_first() {
+        Some((first, rest)) if !rest.is_empty() => (first, rest),
+        _ => return true,
+    };
+    let mut votes = 0;
+    for (i, name) in first.iter().enumerate() {
+        let values: Vec<&String> = rest.iter().filter_map(|record| record.get(i)).collect();
+        if values.is_empty() {
+            continue;
+        }
+        if values.iter().all(|value| value.parse::<f64>().is_ok()) {
+            votes += if name.parse::<f64>().is_ok() { -1 } else { 1 };
+        } else if values.iter().all(|value| value.len() == values[0].len()) {
+            votes += if name.len() == values[0].len() { -1 } else { 1 };
+        }
+    }
+    votes >= 0
+}
+
+/// Finds the dialect which explains the sample best
+fn sniff_sample(sample: &str) -> (Dialect, f64) {
+    let mut best: Option<(Dialect, f64, Vec<Vec<String>>)> = None;
+    for &delimiter in DELIMITERS.iter() {
+        if !sample.contains(delimiter) {
+            continue;
+        }
+        for &quote in QUOTES.iter() {
+            let dialect = Dialect {
+                delimiter,
+                quote,
+                ..Dialect::default()
+            };
+            let records = sample_records(sample, &dialect);
+            let score = consistency(&records);
+            // the order of the candidates breaks the ties
+            if best
+                .as_ref()
+                .is_none_or(|(_, best_score, _)| score > *best_score)
+            {
+                best = Some((dialect, score, records));
+            }
+        }
+    }
+
+    let (mut dialect, score, records) = match best {
+        Some(best) => best,
+        None => (
+            Dialect::default(),
+            0.0,
+            sample_records(sample, &Dialect::default()),
+        ),
+    };
+
+    let (quoted, fields) = quoted_fields(sample, dialect.delimiter, dialect.quote);
+    dialect.quote_style = if fields > 0 && quoted == fields {
+        QuoteStyle::Always
+    } else {
+        QuoteStyle::Necessary
+    };
+    dialect.line_terminator = line_terminator(sample);
+    dialect.has_header = has_header(&records);
+
+    // a couple of records prove much less than a whole page of them
+    let sample_weight = (records.len() as f64 / 10.0).min(1.0);
+    (dialect, score * (0.5 + 0.5 * sample_weight))
+}
+
+impl<R: BufRead> Csv<R> {
+    /// Inspects up to `sample_size` bytes from the beginning of the input and guesses the
+    /// delimiter, the quoting, if there is a header and the line terminator.\
+    /// The inspected bytes are not lost - `Sniffed::into_csv` reads the whole input
+    pub fn sniff(mut reader: R, sample_size: usize) -> Result<Sniffed<R>, CsvError> {
+        let mut buffer = Vec::new();
+        (&mut reader)
+            .take(sample_size as u64)
+            .read_to_end(&mut buffer)
+            .map_err(CsvError::IO)?;
+
+        // the last line is probably cut in the middle, unless the whole input fit
+        let mut sample = String::from_utf8_lossy(&buffer).into_owned();
+        if buffer.len() == sample_size {
+            if let Some(end) = sample.rfind('\n') {
+                sample.truncate(end + 1);
+            }
+        }
+
+        let (dialect, confidence) = sniff_sample(&sample);
+        Ok(Sniffed {
+            dialect,
+            confidence,
+            reader: Cursor::new(buffer).chain(reader),
+        })
+    }
+}
@@ -0,0 +1,570 @@
+//! Sorting the rows, also when they do not fit in memory.\
+//! The rows are collected until they reach the memory budget, then sorted and written to a
+//! temporary file(a run). At the end the runs are merged with a heap, which holds only the
+//! next row of every run. Every run keeps its file open, so at most `MAX_RUNS_PER_MERGE` runs
+//! are merged at once: as soon as there are that many runs of the same length, they are merged
+//! into one longer run, and at the end the last runs are merged until few enough are left.
+//! The sort is stable: rows with equal keys keep their input order
+
+use crate::filter::parse_number;
+use crate::{
+    write_rows, Csv, CsvBuilder, CsvError, CsvWriter, Dialect, ErrorDetails, ParseMode, QuoteStyle,
+    Row,
+};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::Arc;
+
+/// How much memory the rows may take before they are written to a run, 64 MiB
+pub const DEFAULT_MEMORY_BUDGET: usize = 64 * 1024 * 1024;
+
+/// How the values of a key column are compared
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortType {
+    /// Numbers(decimal literals, "NaN" and "inf" are text) as numbers and the rest as text,
+    /// after all the numbers
+    Auto,
+    String,
+    /// Any number `f64` can read
+    Number,
+    /// `YYYY-MM-DD`, optionally followed by a time `HH:MM` or `HH:MM:SS`(after a space or a `T`)
+    Date,
+}
+
+/// A column to sort by, see `SortKey::parse` for the text form
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    pub column: String,
+    pub descending: bool,
+    pub sort_type: SortType,
+}
+
+impl SortKey {
+    /// `column`, `-column` for descending order and an optional `:string`, `:number` or
+    /// `:date` at the end for the type, e.g. `-age:number`. Without one it is `SortType::Auto`
+    pub fn parse(spec: &str) -> SortKey {
+        let (descending, spec) = match spec.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, spec),
+        };
+        let (column, sort_type) = match spec.rsplit_once(':') {
+            Some((column, "string")) => (column, SortType::String),
+            Some((column, "number")) => (column, SortType::Number),
+            Some((column, "date")) => (column, SortType::Date),
+            _ => (spec, SortType::Auto),
+        };
+        SortKey {
+            column: column.into(),
+            descending,
+            sort_type,
+        }
+    }
+
+    /// The value to compare, empty values come before all others
+    fn value(&self, value: &str) -> Result<SortValue, CsvError> {
+        let trimmed = value.trim();
+        let key = if trimmed.is_empty() {
+            KeyValue::Empty
+        } else {
+            match self.sort_type {
+                SortType::Auto => match parse_number(trimmed) {
+                    Some(number) => KeyValue::Number(number),
+                    None => KeyValue::Text(value.into()),
+                },
+                SortType::String => KeyValue::Text(value.into()),
+                SortType::Number => match trimmed.parse::<f64>() {
+                    Ok(number) => KeyValue::Number(number),
+                    Err(_) => return Err(self.invalid(value, "a number")),
+                },
+                SortType::Date => match parse_date(trimmed) {
+                    Some(date) => KeyValue::Date(date),
+                    None => return Err(self.invalid(value, "a date(YYYY-MM-DD)")),
+                },
+            }
+        };
+        Ok(SortValue {
+            key,
+            descending: self.descending,
+        })
+    }
+
+    fn invalid(&self, value: &str, expected: &str) -> CsvError {
+        let mut details = ErrorDetails::new(format!("Cannot sort {:?} as {}", value, expected));
+        details.column_name = Some(self.column.clone());
+        details.excerpt = Some(value.into());
+        CsvError::InvalidRow(details.into())
+    }
+}
+
+/// The date and time as (year, month, day, hours, minutes, seconds)
+pub(crate) fn parse_date(text: &str) -> Option<[u32; 6]> {
+    let (date, time) = match text.find([' ', 'T']) {
+        Some(i) => (&text[..i], text[i + 1..].trim()),
+        None => (text, ""),
+    };
+    let mut res = [0; 6];
+
+    let parts: Vec<&str> = date.split('-').collect();
+    if parts.len() != 3 || parts[0].len() != 4 || parts[1].len() != 2 || parts[2].len() != 2 {
+        return None;
+    }
+    for (i, part) in parts.iter().enumerate() {
+        res[i] = part.parse().ok()?;
+    }
+    if !(1..=12).contains(&res[1]) || !(1..=31).contains(&res[2]) {
+        return None;
+    }
+
+    if !time.is_empty() {
+        let parts: Vec<&str> = time.split(':').collect();
+        if parts.len() < 2 || parts.len() > 3 || parts.iter().any(|part| part.len() != 2) {
+            return None;
+        }
+        for (i, part) in parts.iter().enumerate() {
+            res[3 + i] = part.parse().ok()?;
+        }
+        if res[3] > 23 || res[4] > 59 || res[5] > 60 {
+            return None;
+        }
+    }
+    Some(res)
+}
+
+#[derive(Debug, Clone)]
+enum KeyValue {
+    Empty,
+    Text(String),
+    Number(f64),
+    Date([u32; 6]),
+}
+
+impl KeyValue {
+    fn cmp(&self, other: &KeyValue) -> Ordering {
+        match (self, other) {
+            (KeyValue::Empty, KeyValue::Empty) => Ordering::Equal,
+            (KeyValue::Empty, _) => Ordering::Less,
+            (_, KeyValue::Empty) => Ordering::Greater,
+            (KeyValue::Text(left), KeyValue::Text(right)) => left.cmp(right),
+            (KeyValue::Number(left), KeyValue::Number(right)) => left.total_cmp(right),
+            (KeyValue::Date(left), KeyValue::Date(right)) => left.cmp(right),
+            // only with `SortType::Auto`
+            (KeyValue::Number(_), KeyValue::Text(_)) => Ordering::Less,
+            (KeyValue::Text(_), KeyValue::Number(_)) => Ordering::Greater,
+            // the other values of a column all have the type of its key
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+/// A value of a key column, ordered in the direction of the key
+#[derive(Debug, Clone)]
+pub(crate) struct SortValue {
+    key: KeyValue,
+    descending: bool,
+}
+
+impl PartialEq for SortValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SortValue {}
+
+impl PartialOrd for SortValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SortValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let ordering = self.key.cmp(&other.key);
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+/// The key columns and where they are in the rows
+pub(crate) struct Keys {
+    pub keys: Vec<SortKey>,
+    pub positions: Vec<usize>,
+}
+
+impl Keys {
+    pub fn of(&self, values: &[String]) -> Result<Vec<SortValue>, CsvError> {
+        self.keys
+            .iter()
+            .zip(&self.positions)
+            .map(|(key, &i)| key.value(&values[i]))
+            .collect()
+    }
+}
+
+/// The next row of a run in the heap. The smallest keys come out first, equal keys in the
+/// order of the runs, so that the rows stay in input order
+struct HeapEntry {
+    key: Vec<SortValue>,
+    run: usize,
+    values: Vec<String>,
+}
+
+impl PartialEq for HeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for HeapEntry {}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Reversed, since `BinaryHeap` gives out the biggest entry first
+impl Ord for HeapEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .key
+            .cmp(&self.key)
+            .then_with(|| other.run.cmp(&self.run))
+    }
+}
+
+/// A sorted run in a temporary file, which is removed when the run is dropped
+struct Run {
+    path: PathBuf,
+    reader: Option<Csv<BufReader<File>>>,
+    /// How many times its rows were merged, runs of the same level are about as long
+    level: usize,
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        self.reader = None;
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// The dialect of the runs: every value is quoted and nothing is trimmed, so that the values
+/// are read back exactly
+fn run_dialect() -> Dialect {
+    Dialect {
+        has_header: false,
+        quote_style: QuoteStyle::Always,
+        ..Dialect::with_mode(ParseMode::Strict)
+    }
+}
+
+/// Makes the names of the runs unique among all the sorts of the process, a name which is
+/// taken(e.g. by another process) is skipped
+static RUN_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// How many runs are merged at once. The open files are at most this many for every level of
+/// runs, see `Run::level`
+const MAX_RUNS_PER_MERGE: usize = 16;
+
+/// How to sort a `Csv`, see `Csv::sort_by`
+pub struct Sort<R: BufRead> {
+    csv: Csv<R>,
+    keys: Keys,
+    memory_budget: usize,
+    temp_dir: PathBuf,
+}
+
+impl<R: BufRead> Sort<R> {
+    /// About how many bytes the rows may take in memory, see `DEFAULT_MEMORY_BUDGET`
+    pub fn memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = bytes;
+        self
+    }
+
+    /// Where the runs are written, `std::env::temp_dir()` by default
+    pub fn temp_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.temp_dir = dir.into();
+        self
+    }
+
+    /// Reads all the rows(through the pipeline) and sorts them
+    pub fn run(self) -> Result<Sorted, CsvError> {
+        let Sort {
+            csv,
+            keys,
+            memory_budget,
+            temp_dir,
+        } = self;
+        let columns = csv.output_columns();
+        let dialect = csv.dialect().clone();
+
+        let mut runs = Vec::new();
+        let mut rows = Vec::new();
+        let mut used = 0;
+        for row in csv {
+            let values = row?.into_values();
+            let key = keys.of(&values)?;
+            used += size_of_row(&values) + size_of_key(&key);
+            rows.push((key, values));
+            if used > memory_budget {
+                runs.push(write_run(&mut rows, &temp_dir)?);
+                used = 0;
+                while runs.len() >= MAX_RUNS_PER_MERGE {
+                    let last = &runs[runs.len() - MAX_RUNS_PER_MERGE..];
+                    if last.iter().any(|run| run.level != last[0].level) {
+                        break;
+                    }
+                    merge_last_runs(&mut runs, &keys, &temp_dir)?;
+                }
+            }
+        }
+        // one more is left for the rows in memory
+        while runs.len() >= MAX_RUNS_PER_MERGE {
+            merge_last_runs(&mut runs, &keys, &temp_dir)?;
+        }
+        // sorting by the keys only is stable
+        rows.sort_by(|(left, _), (right, _)| left.cmp(right));
+
+        let names: Arc<[String]> = columns.as_slice().into();
+        let source = if runs.is_empty() {
+            Source::Memory(rows.into_iter())
+        } else {
+            let merge = Merge::new(runs, rows.into_iter(), &keys)?;
+            Source::Merge { merge, keys }
+        };
+
+        Ok(Sorted {
+            columns,
+            names,
+            source,
+            dialect,
+        })
+    }
+}
+
+/// About how many bytes the values take
+fn size_of_row(values: &[String]) -> usize {
+    values
+        .iter()
+        .map(|value| value.capacity() + std::mem::size_of::<String>())
+        .sum::<usize>()
+        + std::mem::size_of::<Vec<String>>()
+}
+
+/// About how many bytes the key of a row takes, the text values are copies
+fn size_of_key(key: &[SortValue]) -> usize {
+    key.iter()
+        .map(|value| match &value.key {
+            KeyValue::Text(text) => text.capacity(),
+            _ => 0,
+        })
+        .sum::<usize>()
+        + std::mem::size_of_val(key)
+        + std::mem::size_of::<Vec<SortValue>>()
+}
+
+/// Sorts the rows, writes them to a new run and empties `rows`
+fn write_run(
+    rows: &mut Vec<(Vec<SortValue>, Vec<String>)>,
+    temp_dir: &std::path::Path,
+) -> Result<Run, CsvError> {
+    rows.sort_by(|(left, _), (right, _)| left.cmp(right));
+    new_run(temp_dir, 0, |writer| {
+        for (_, values) in rows.drain(..) {
+            writer.write_record(&values)?;
+        }
+        Ok(())
+    })
+}
+
+/// Merges the last `MAX_RUNS_PER_MERGE` runs into one, which takes their place. They are
+/// next to each other, so the sort stays stable
+fn merge_last_runs(
+    runs: &mut Vec<Run>,
+    keys: &Keys,
+    temp_dir: &std::path::Path,
+) -> Result<(), CsvError> {
+    let last = runs.split_off(runs.len() - MAX_RUNS_PER_MERGE);
+    let level = last.iter().map(|run| run.level).max().unwrap_or(0) + 1;
+    let mut merge = Merge::new(last, Vec::new().into_iter(), keys)?;
+    let run = new_run(temp_dir, level, |writer| {
+        while let Some(values) = merge.next(keys)? {
+            writer.write_record(&values)?;
+        }
+        Ok(())
+    })?;
+    runs.push(run);
+    Ok(())
+}
+
+/// Writes a new run with `write` and opens it for reading
+fn new_run<F>(temp_dir: &std::path::Path, level: usize, write: F) -> Result<Run, CsvError>
+where
+    F: FnOnce(&mut CsvWriter<BufWriter<&File>>) -> Result<(), CsvError>,
+{
+    // only a new file, never one which is already there(or a link to another file)
+    let (path, mut file) = loop {
+        let path = temp_dir.join(format!(
+            "csv-sort-{}-{}.csv",
+            std::process::id(),
+            RUN_COUNTER.fetch_add(1, AtomicOrdering::Relaxed)
+        ));
+        let mut options = OpenOptions::new();
+        match options.read(true).write(true).create_new(true).open(&path) {
+            Ok(file) => break (path, file),
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(error) => return Err(error.into()),
+        }
+    };
+    let mut run = Run {
+        path,
+        reader: None,
+        level,
+    };
+    let mut writer = CsvWriter::with_dialect(BufWriter::new(&file), run_dialect())?;
+    write(&mut writer)?;
+    writer.into_inner()?;
+
+    // read back through the same file, not whatever has the path now
+    file.seek(SeekFrom::Start(0))?;
+    // no limit already for the first record, which is read here
+    let reader = CsvBuilder::from_dialect(run_dialect())
+        .max_record_size(usize::MAX)
+        .from_reader(BufReader::new(file))?;
+    run.reader = Some(reader);
+    Ok(run)
+}
+
+fn next_in_run(run: &mut Run) -> Result<Option<Vec<String>>, CsvError> {
+    match run.reader.as_mut().and_then(Iterator::next) {
+        Some(row) => Ok(Some(row?.into_values())),
+        None => Ok(None),
+    }
+}
+
+/// The runs and the sorted rows in memory, merged with a heap
+struct Merge {
+    heap: BinaryHeap<HeapEntry>,
+    runs: Vec<Run>,
+    /// The rest of the rows which were not written to a run
+    memory: std::vec::IntoIter<(Vec<SortValue>, Vec<String>)>,
+}
+
+impl Merge {
+    fn new(
+        mut runs: Vec<Run>,
+        mut memory: std::vec::IntoIter<(Vec<SortValue>, Vec<String>)>,
+        keys: &Keys,
+    ) -> Result<Merge, CsvError> {
+        let mut heap = BinaryHeap::with_capacity(runs.len() + 1);
+        for (i, run) in runs.iter_mut().enumerate() {
+            if let Some(values) = next_in_run(run)? {
+                let key = keys.of(&values)?;
+                heap.push(HeapEntry {
+                    key,
+                    run: i,
+                    values,
+                });
+            }
+        }
+        // the rows still in memory are the last run
+        if let Some((key, values)) = memory.next() {
+            let run = runs.len();
+            heap.push(HeapEntry { key, run, values });
+        }
+        Ok(Merge { heap, runs, memory })
+    }
+
+    fn next(&mut self, keys: &Keys) -> Result<Option<Vec<String>>, CsvError> {
+        let HeapEntry { run, values, .. } = match self.heap.pop() {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        // the next row of the same run takes its place
+        let next = if run < self.runs.len() {
+            match next_in_run(&mut self.runs[run])? {
+                Some(next) => Some((keys.of(&next)?, next)),
+                None => None,
+            }
+        } else {
+            self.memory.next()
+        };
+        if let Some((key, values)) = next {
+            self.heap.push(HeapEntry { key, run, values });
+        }
+        Ok(Some(values))
+    }
+}
+
+enum Source {
+    Memory(std::vec::IntoIter<(Vec<SortValue>, Vec<String>)>),
+    Merge { merge: Merge, keys: Keys },
+}
+
+/// The sorted rows
+pub struct Sorted {
+    pub columns: Vec<String>,
+    names: Arc<[String]>,
+    source: Source,
+    dialect: Dialect,
+}
+
+impl Sorted {
+    /// Writes the header(if the dialect has one) and the sorted rows in the dialect of the csv
+    /// which was sorted
+    pub fn write_to<W: Write>(mut self, writer: W) -> Result<(), CsvError> {
+        let columns = std::mem::take(&mut self.columns);
+        write_rows(writer, self.dialect.clone(), &columns, &mut self)
+    }
+
+    fn next_values(&mut self) -> Result<Option<Vec<String>>, CsvError> {
+        match &mut self.source {
+            Source::Memory(rows) => Ok(rows.next().map(|(_, values)| values)),
+            Source::Merge { merge, keys } => merge.next(keys),
+        }
+    }
+}
+
+impl Iterator for Sorted {
+    type Item = Result<Row, CsvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_values() {
+            Ok(Some(values)) => Some(Ok(Row::with_columns(Arc::clone(&self.names), values))),
+            Ok(None) => None,
+            Err(error) => Some(Err(error)),
+        }
+    }
+}
+
+impl<R: BufRead> Csv<R> {
+    /// Sorts the rows which come out of the pipeline by the given keys(see `SortKey::parse`),
+    /// the first key is the most important one
+    pub fn sort_by(self, keys: &[&str]) -> Result<Sort<R>, CsvError> {
+        let keys: Vec<SortKey> = keys.iter().map(|spec| SortKey::parse(spec)).collect();
+        let columns = self.output_columns();
+        let positions = keys
+            .iter()
+            .map(|key| {
+                columns
+                    .iter()
+                    .position(|column| *column == key.column)
+                    .ok_or_else(|| CsvError::InvalidColumn(key.column.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Sort {
+            csv: self,
+            keys: Keys { keys, positions },
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+            temp_dir: std::env::temp_dir(),
+        })
+    }
+}
@@ -0,0 +1,166 @@
+//! Writes records in a dialect, independently of any `Csv` which is read
+
+use crate::{CsvError, Dialect, QuoteStyle, Row};
+use std::io::Write;
+
+/// Writes a header and records to `W`, everything goes through `write_all`.\
+/// With `CsvWriter::new` only the values which need quotes are quoted,
+/// `QuoteStyle::Always` in the dialect quotes every value(the way `Csv::write_to` does)
+pub struct CsvWriter<W: Write> {
+    inner: W,
+    dialect: Dialect,
+    /// The text of the current record, reused for every record
+    line: String,
+    /// Nothing has been written yet, so a header can still be written
+    pub(crate) at_start: bool,
+}
+
+impl<W: Write> CsvWriter<W> {
+    /// The default dialect, but the values are only quoted if they have to be
+    pub fn new(inner: W) -> Self {
+        CsvWriter {
+            inner,
+            dialect: Dialect {
+                quote_style: QuoteStyle::Necessary,
+                ..Dialect::default()
+            },
+            line: String::new(),
+            at_start: true,
+        }
+    }
+
+    pub fn with_dialect(inner: W, dialect: Dialect) -> Result<Self, CsvError> {
+        dialect.validate()?;
+        Ok(CsvWriter {
+            inner,
+            dialect,
+            line: String::new(),
+            at_start: true,
+        })
+    }
+
+    pub fn dialect(&self) -> &Dialect {
+        &self.dialect
+    }
+
+    /// The column names are only quoted if they have to be, whatever the quote style
+    pub fn write_header<I, S>(&mut self, columns: I) -> Result<(), CsvError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.write_fields(columns, false)
+    }
+
+    pub fn write_record<I, S>(&mut self, values: I) -> Result<(), CsvError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let quote_all = self.dialect.quote_style == QuoteStyle::Always;
+        self.write_fields(values, quote_all)
+    }
+
+    /// Writes the values of the row in the order of its columns
+    pub fn write_row(&mut self, row: &Row) -> Result<(), CsvError> {
+        self.write_record(row.values())
+    }
+
+    pub fn flush(&mut self) -> Result<(), CsvError> {
+        self.inner.flush().map_err(CsvError::IO)
+    }
+
+    /// The writer, what is written directly to it goes between the records
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Flushes and gives back the writer
+    pub fn into_inner(mut self) -> Result<W, CsvError> {
+        self.flush()?;
+        Ok(self.inner)
+    }
+
+    fn write_fields<I, S>(&mut self, fields: I, quote_all: bool) -> Result<(), CsvError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.line.clear();
+        for (i, field) in fields.into_iter().enumerate() {
+            if i > 0 {
+                self.line.push(self.dialect.delimiter);
+                // a space after the delimiter is ignored when reading back a trimmed dialect
+                if self.dialect.trim {
+                    self.line.push(' ');
+                }
+            }
+            let field = field.as_ref();
+            if quote_all || needs_quotes(field, &self.dialect) {
+                push_quoted(&mut self.line, field, &self.dialect);
+            } else {
+                self.line.push_str(field);
+            }
+        }
+        self.line.push_str(self.dialect.line_terminator.as_str());
+
+        self.at_start = false;
+        self.inner
+            .write_all(self.line.as_bytes())
+            .map_err(CsvError::IO)
+    }
+}
+
+/// Writes the header(if the dialect has one) and the rows, for everything which gives out
+/// rows the way `Csv` does
+pub(crate) fn write_rows<W, I>(
+    writer: W,
+    dialect: Dialect,
+    columns: &[String],
+    rows: I,
+) -> Result<(), CsvError>
+where
+    W: Write,
+    I: IntoIterator<Item = Result<Row, CsvError>>,
+{
+    let mut writer = CsvWriter::with_dialect(writer, dialect)?;
+    if writer.dialect().has_header {
+        writer.write_header(columns)?;
+    }
+    for row in rows {
+        writer.write_row(&row?)?;
+    }
+    writer.flush()
+}
+
+/// A value has to be quoted if it would be read back differently otherwise
+fn needs_quotes(value: &str, dialect: &Dialect) -> bool {
+    value.is_empty()
+        || value.contains(|ch| {
+            ch == dialect.delimiter
+                || ch == dialect.quote
+                || ch == '\n'
+                || ch == '\r'
+                || Some(ch) == dialect.escape
+        })
+        || (dialect.trim && value.trim() != value)
+        || dialect
+            .comment_prefixes
+            .iter()
+            .any(|prefix| value.starts_with(prefix.as_str()))
+}
+
+/// Quotes the value, the quotes inside it are escaped
+/// (doubled, unless the dialect has an escape character)
+fn push_quoted(res: &mut String, value: &str, dialect: &Dialect) {
+    res.push(dialect.quote);
+    for ch in value.chars() {
+        match dialect.escape {
+            Some(escape) if ch == dialect.quote || ch == escape => res.push(escape),
+            None if ch == dialect.quote => res.push(dialect.quote),
+            _ => {}
+        }
+        res.push(ch);
+    }
+    res.push(dialect.quote);
+}
@@ -0,0 +1,175 @@
+#![cfg(feature = "async")]
+
+use futures::executor::block_on;
+use futures::io::{AsyncRead, BufReader, Cursor};
+use futures::stream::{StreamExt, TryStreamExt};
+use solution::*;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+const DATA: &str = "name,note
+Ada,\"first
+programmer\"
+# not a comment inside the records
+
+Alan,\"says \"\"hi\"\"\"
+broken
+Grace,\r
+";
+
+/// Gives out a byte at a time and is not ready before every byte
+struct Trickle {
+    data: Vec<u8>,
+    at: usize,
+    ready: bool,
+}
+
+impl AsyncRead for Trickle {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if !self.ready {
+            self.ready = true;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        self.ready = false;
+        match self.data.get(self.at) {
+            Some(&byte) if !buf.is_empty() => {
+                buf[0] = byte;
+                self.at += 1;
+                Poll::Ready(Ok(1))
+            }
+            _ => Poll::Ready(Ok(0)),
+        }
+    }
+}
+
+fn trickle(data: &str) -> BufReader<Trickle> {
+    BufReader::new(Trickle {
+        data: data.as_bytes().to_vec(),
+        at: 0,
+        ready: false,
+    })
+}
+
+/// The rows as text, the errors as their messages
+fn results(rows: Vec<Result<Row, CsvError>>) -> Vec<String> {
+    rows.into_iter()
+        .map(|row| match row {
+            Ok(row) => row.values().join("|"),
+            Err(error) => error.to_string(),
+        })
+        .collect()
+}
+
+#[test]
+fn stream_test() {
+    let expected = results(Csv::new(DATA.as_bytes()).unwrap().collect());
+    assert_eq!(expected.len(), 5);
+    assert!(expected[3].starts_with("Parse error"), "{}", expected[3]);
+
+    let csv = block_on(AsyncCsv::new(Cursor::new(DATA))).unwrap();
+    assert_eq!(csv.columns(), &["name", "note"]);
+    assert_eq!(results(block_on(csv.collect())), expected);
+
+    // a byte at a time, with a pending poll before each of them
+    let csv = block_on(AsyncCsv::new(trickle(DATA))).unwrap();
+    assert_eq!(results(block_on(csv.collect())), expected);
+}
+
+#[test]
+fn dialect_test() {
+    let data = "# comment\n1;x\n2;\"y;z\"\n";
+    let read = CsvBuilder::new()
+        .delimiter(';')
+        .comment("#")
+        .has_header(false)
+        .from_async_reader(trickle(data));
+    let csv = block_on(read).unwrap();
+    assert_eq!(csv.columns(), &["0", "1"]);
+    let rows: Vec<Row> = block_on(csv.try_collect()).unwrap();
+    assert_eq!(rows[0]["1"], "x");
+    assert_eq!(rows[1]["1"], "y;z");
+
+    let invalid = Dialect {
+        quote: ';',
+        ..Dialect::semicolon()
+    };
+    assert!(matches!(
+        block_on(AsyncCsv::with_dialect(Cursor::new(data), invalid)),
+        Err(CsvError::InvalidDialect(_))
+    ));
+}
+
+#[test]
+fn header_test() {
+    assert!(matches!(
+        block_on(AsyncCsv::new(Cursor::new(""))),
+        Err(CsvError::InvalidHeader(_))
+    ));
+    assert!(matches!(
+        block_on(AsyncCsv::new(Cursor::new("a,a\n"))),
+        Err(CsvError::InvalidHeader(_))
+    ));
+}
+
+#[test]
+fn limit_test() {
+    let data = format!("a\n{}\nshort\n", "x".repeat(100));
+    let mut csv = block_on(AsyncCsv::new(trickle(&data))).unwrap();
+    csv.set_max_record_size(50);
+    let first = block_on(csv.next()).unwrap().unwrap_err().to_string();
+    assert!(
+        first.contains("longer than the limit of 50 bytes"),
+        "{}",
+        first
+    );
+    assert!(first.contains("record 2 starting at line 2"), "{}", first);
+}
+
+#[test]
+fn invalid_utf8_test() {
+    let data: &[u8] = b"a\n\xff\nok\n";
+    let mut csv = block_on(AsyncCsv::new(Cursor::new(data))).unwrap();
+    let error = block_on(csv.next()).unwrap().unwrap_err();
+    assert!(matches!(error, CsvError::ParseError(_)));
+    assert!(error.to_string().contains("not valid UTF-8"), "{}", error);
+    assert_eq!(block_on(csv.next()).unwrap().unwrap()["a"], "ok");
+    assert!(block_on(csv.next()).is_none());
+
+    let read = CsvBuilder::new()
+        .lossy(true)
+        .from_async_reader(Cursor::new(data));
+    let mut csv = block_on(read).unwrap();
+    let rows: Vec<Row> = block_on(csv.by_ref().try_collect()).unwrap();
+    assert_eq!(rows[0]["a"], "\u{FFFD}");
+    assert_eq!(csv.replaced_records().len(), 1);
+    assert_eq!(csv.replaced_records()[0].record, 2);
+}
+
+#[test]
+fn write_test() {
+    let data = "a,b\n1,\"x, y\"\n2,z\n";
+    let mut expected = Vec::new();
+    Csv::new(data.as_bytes())
+        .unwrap()
+        .write_to(&mut expected)
+        .unwrap();
+
+    let csv = block_on(AsyncCsv::new(trickle(data))).unwrap();
+    let mut output = Cursor::new(Vec::new());
+    block_on(csv.write_to(&mut output)).unwrap();
+    assert_eq!(output.into_inner(), expected);
+}
+
+#[test]
+fn write_error_test() {
+    let csv = block_on(AsyncCsv::new(Cursor::new("a,b\n1\n"))).unwrap();
+    let mut output = Cursor::new(Vec::new());
+    let error = block_on(csv.write_to(&mut output)).unwrap_err();
+    assert!(matches!(error, CsvError::ParseError(_)));
+}
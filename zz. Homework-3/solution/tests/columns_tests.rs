@@ -0,0 +1,115 @@
+use solution::*;
+use std::io::BufReader;
+
+const DATA: &str = "name,age,birth date,city
+Ada,36,1815-12-10,London
+Bob,20,2000-01-01,Sofia
+";
+
+fn csv() -> Csv<BufReader<&'static [u8]>> {
+    Csv::new(BufReader::new(DATA.as_bytes())).unwrap()
+}
+
+fn values(csv: Csv<BufReader<&[u8]>>) -> Vec<Vec<String>> {
+    csv.map(|row| row.unwrap().into_values()).collect()
+}
+
+fn written(csv: Csv<BufReader<&[u8]>>) -> String {
+    let mut output = Vec::new();
+    csv.write_to(&mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn select_columns_test() {
+    let mut csv = csv();
+    csv.select_columns(&["city", "name"]).unwrap();
+    assert_eq!(csv.columns(), &["city", "name"]);
+
+    let rows: Vec<Row> = csv.map(Result::unwrap).collect();
+    assert_eq!(
+        rows[0].iter().collect::<Vec<_>>(),
+        &[("city", "London"), ("name", "Ada")]
+    );
+    assert_eq!(rows[1]["name"], "Bob");
+    assert!(!rows[1].contains_column("age"));
+}
+
+#[test]
+fn rename_column_test() {
+    let mut csv = csv();
+    csv.rename_column("birth date", "dob").unwrap();
+    assert_eq!(csv.columns(), &["name", "age", "dob", "city"]);
+    assert_eq!(csv.next().unwrap().unwrap()["dob"], "1815-12-10");
+
+    assert!(matches!(
+        csv.rename_column("height", "h"),
+        Err(CsvError::InvalidColumn(name)) if name == "height"
+    ));
+    assert!(matches!(
+        csv.rename_column("dob", "age"),
+        Err(CsvError::InvalidColumn(name)) if name == "age"
+    ));
+    csv.rename_column("dob", "dob").unwrap();
+}
+
+#[test]
+fn reorder_test() {
+    let mut csv = csv();
+    assert!(matches!(
+        csv.reorder(&["age", "name", "city"]),
+        Err(CsvError::InvalidColumn(name)) if name == "birth date"
+    ));
+    assert!(matches!(
+        csv.reorder(&["age", "name", "city", "name"]),
+        Err(CsvError::InvalidColumn(name)) if name == "name"
+    ));
+    // a failed operation changes nothing
+    assert_eq!(csv.columns(), &["name", "age", "birth date", "city"]);
+
+    csv.reorder(&["city", "birth date", "age", "name"]).unwrap();
+    assert_eq!(
+        values(csv),
+        &[
+            ["London", "1815-12-10", "36", "Ada"],
+            ["Sofia", "2000-01-01", "20", "Bob"],
+        ]
+    );
+}
+
+#[test]
+fn drop_columns_test() {
+    let mut csv = csv();
+    assert!(matches!(
+        csv.drop_columns(&["age", "height"]),
+        Err(CsvError::InvalidColumn(name)) if name == "height"
+    ));
+    csv.drop_columns(&["age", "birth date"]).unwrap();
+    assert_eq!(values(csv), &[["Ada", "London"], ["Bob", "Sofia"]]);
+}
+
+#[test]
+fn combined_with_write_to_test() {
+    let mut csv = csv();
+    csv.drop_columns(&["city"]).unwrap();
+    csv.rename_column("birth date", "dob").unwrap();
+    csv.reorder(&["dob", "name", "age"]).unwrap();
+    csv.select_columns(&["name", "dob"]).unwrap();
+    csv.apply_selection(|row| Ok(row["dob"].as_str() > "1900"));
+
+    assert_eq!(written(csv), "name, dob\n\"Bob\", \"2000-01-01\"\n");
+}
+
+#[test]
+fn records_and_column_handles_test() {
+    let mut csv = csv();
+    csv.select_columns(&["city", "age"]).unwrap();
+    let age = csv.column("age").unwrap();
+    assert!(csv.column("name").is_none());
+
+    let mut record = StringRecord::new();
+    assert!(csv.read_record(&mut record).unwrap());
+    // the record still has every field of the input
+    assert_eq!(record.len(), 4);
+    assert_eq!(&record[age], "36");
+}
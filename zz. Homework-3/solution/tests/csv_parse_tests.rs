@@ -47,7 +47,6 @@ fn parse_too_long_row_test() {
 }
 
 #[test]
-#[should_panic]
 fn parse_no_quotes_row_test() {
     let data = r#"
     name, age, birth date
@@ -0,0 +1,167 @@
+use solution::*;
+use std::io::BufReader;
+
+fn write(csv: Csv<BufReader<&[u8]>>) -> String {
+    let mut output = Vec::new();
+    csv.write_to(&mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn tsv_test() {
+    let data = "name\tage\tnotes\nAda Lovelace\t36\t has, commas \n".as_bytes();
+    let mut csv = Csv::with_dialect(BufReader::new(data), Dialect::tsv()).unwrap();
+    assert_eq!(csv.columns(), &["name", "age", "notes"]);
+
+    let row = csv.next().unwrap().unwrap();
+    assert_eq!(row["name"], "Ada Lovelace");
+    assert_eq!(row["notes"], " has, commas ");
+}
+
+#[test]
+fn semicolon_with_builder_test() {
+    let data = "name; price\n\"Café; Bar\"; 3,50\n".as_bytes();
+    let csv = CsvBuilder::new()
+        .delimiter(';')
+        .from_reader(BufReader::new(data))
+        .unwrap();
+    assert_eq!(csv.dialect(), &Dialect::semicolon());
+
+    let rows: Vec<_> = csv.map(Result::unwrap).collect();
+    assert_eq!(rows[0]["name"], "Café; Bar");
+    assert_eq!(rows[0]["price"], "3,50");
+}
+
+#[test]
+fn pipe_and_custom_quote_test() {
+    let data = "a|b\n'x|y'|'it''s'\n".as_bytes();
+    let rows: Vec<_> = CsvBuilder::new()
+        .delimiter('|')
+        .quote('\'')
+        .from_reader(BufReader::new(data))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(rows[0]["a"], "x|y");
+    assert_eq!(rows[0]["b"], "it's");
+}
+
+#[test]
+fn escape_test() {
+    let data = r#"a,b
+"say \"hi\"",back\\slash\,comma
+"#
+    .as_bytes();
+    let csv = CsvBuilder::new()
+        .escape(Some('\\'))
+        .from_reader(BufReader::new(data))
+        .unwrap();
+    let rows: Vec<_> = csv.map(Result::unwrap).collect();
+    assert_eq!(rows[0]["a"], r#"say "hi""#);
+    assert_eq!(rows[0]["b"], r#"back\slash,comma"#);
+
+    let csv = CsvBuilder::new()
+        .escape(Some('\\'))
+        .from_reader(BufReader::new(data))
+        .unwrap();
+    assert_eq!(
+        write(csv),
+        "a, b\n\"say \\\"hi\\\"\", \"back\\\\slash,comma\"\n"
+    );
+}
+
+#[test]
+fn comments_test() {
+    let data = "# exported on 2020-12-01\nname,age\n# a comment between rows\nAda,36\n  // another one\nBob,20\n"
+        .as_bytes();
+    let csv = CsvBuilder::new()
+        .comment("#")
+        .comment("//")
+        .from_reader(BufReader::new(data))
+        .unwrap();
+    assert_eq!(csv.columns(), &["name", "age"]);
+
+    let names: Vec<_> = csv.map(|row| row.unwrap()["name"].clone()).collect();
+    assert_eq!(names, &["Ada", "Bob"]);
+}
+
+#[test]
+fn no_header_test() {
+    let data = "Ada,36\nBob,20\n".as_bytes();
+    let csv = CsvBuilder::new()
+        .has_header(false)
+        .from_reader(BufReader::new(data))
+        .unwrap();
+    assert_eq!(csv.columns(), &["0", "1"]);
+
+    let rows: Vec<_> = csv.map(Result::unwrap).collect();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["0"], "Ada");
+    assert_eq!(rows[1]["1"], "20");
+
+    let csv = CsvBuilder::new()
+        .has_header(false)
+        .from_reader(BufReader::new(data))
+        .unwrap();
+    assert_eq!(write(csv), "\"Ada\", \"36\"\n\"Bob\", \"20\"\n");
+
+    let empty = CsvBuilder::new()
+        .has_header(false)
+        .from_reader(BufReader::new("".as_bytes()))
+        .unwrap();
+    assert_eq!(empty.count(), 0);
+}
+
+#[test]
+fn trimming_test() {
+    let data = " name , age \n  Ada  , 36 \n".as_bytes();
+    let csv = CsvBuilder::new()
+        .trim(false)
+        .from_reader(BufReader::new(data))
+        .unwrap();
+    assert_eq!(csv.columns(), &[" name ", " age "]);
+    let row = csv.map(Result::unwrap).next().unwrap();
+    assert_eq!(row[" name "], "  Ada  ");
+
+    let csv = Csv::new(BufReader::new(data)).unwrap();
+    assert_eq!(csv.columns(), &["name", "age"]);
+}
+
+#[test]
+fn write_follows_dialect_test() {
+    let data = "name\tnote\nAda\tsays \"hi\"\n".as_bytes();
+    let csv = Csv::with_dialect(BufReader::new(data), Dialect::tsv()).unwrap();
+    assert_eq!(write(csv), "name\tnote\n\"Ada\"\t\"says \"\"hi\"\"\"\n");
+}
+
+#[test]
+fn header_is_quoted_when_needed_test() {
+    let data = "\"last, first\",age\n\"Lovelace, Ada\",36\n".as_bytes();
+    let csv = Csv::new(BufReader::new(data)).unwrap();
+    assert_eq!(
+        write(csv),
+        "\"last, first\", age\n\"Lovelace, Ada\", \"36\"\n"
+    );
+}
+
+#[test]
+fn invalid_dialect_test() {
+    let data = "a,b\n".as_bytes();
+    let same = CsvBuilder::new()
+        .quote(',')
+        .from_reader(BufReader::new(data));
+    assert!(matches!(same.err(), Some(CsvError::InvalidDialect(_))));
+
+    let escape_is_quote = CsvBuilder::new()
+        .escape(Some('"'))
+        .from_reader(BufReader::new(data));
+    assert!(matches!(
+        escape_is_quote.err(),
+        Some(CsvError::InvalidDialect(_))
+    ));
+
+    let newline = CsvBuilder::new()
+        .delimiter('\n')
+        .from_reader(BufReader::new(data));
+    assert!(matches!(newline.err(), Some(CsvError::InvalidDialect(_))));
+}
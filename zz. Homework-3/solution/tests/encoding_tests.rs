@@ -0,0 +1,241 @@
+use solution::*;
+use std::io::{self, Read};
+
+/// Gives out a byte at a time, so the characters are cut between the reads
+struct OneByte<'a>(&'a [u8]);
+
+impl Read for OneByte<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match (self.0.split_first(), buf.first_mut()) {
+            (Some((&byte, rest)), Some(first)) => {
+                *first = byte;
+                self.0 = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+/// Only 'А'..='я' and ASCII, which is enough for the tests
+fn windows_1251(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|ch| match ch {
+            'А'..='я' => (ch as u32 - 0x0410 + 0xC0) as u8,
+            _ => ch as u8,
+        })
+        .collect()
+}
+
+fn utf16(text: &str, little_endian: bool) -> Vec<u8> {
+    text.encode_utf16()
+        .flat_map(|unit| {
+            if little_endian {
+                unit.to_le_bytes()
+            } else {
+                unit.to_be_bytes()
+            }
+        })
+        .collect()
+}
+
+fn values<R: io::BufRead>(csv: Csv<R>) -> Vec<String> {
+    csv.map(|row| match row {
+        Ok(row) => row.values().join("|"),
+        Err(error) => error.to_string(),
+    })
+    .collect()
+}
+
+#[test]
+fn bom_test() {
+    let csv = Csv::new(&b"\xEF\xBB\xBFname,age\nIvan,30\n"[..]).unwrap();
+    assert_eq!(csv.columns(), &["name", "age"]);
+    assert_eq!(values(csv), &["Ivan|30"]);
+
+    // only at the start of the input
+    let csv = Csv::new(&b"name\n\xEF\xBB\xBFIvan\n"[..]).unwrap();
+    assert_eq!(values(csv), &["\u{FEFF}Ivan"]);
+}
+
+#[test]
+fn invalid_utf8_test() {
+    let error = Csv::new(&b"na\xE9me,age\n"[..]).err().unwrap();
+    assert!(matches!(error, CsvError::ParseError(_)));
+    let details = error.details().unwrap();
+    assert_eq!(details.position.unwrap().record, 1);
+    assert_eq!(details.excerpt.as_deref(), Some("na\u{FFFD}me,age\n"));
+
+    let data = b"name,age\nIvan,30\nJos\xE9,40\nMaria,50\n";
+    let rows = values(Csv::new(&data[..]).unwrap());
+    assert_eq!(rows.len(), 3);
+    assert!(rows[1].starts_with("Parse error: The record is not valid UTF-8"));
+    assert!(
+        rows[1].contains("record 3 starting at line 3"),
+        "{}",
+        rows[1]
+    );
+    assert_eq!(rows[2], "Maria|50");
+}
+
+#[test]
+fn lossy_test() {
+    let data = b"name,note\nIvan,\"a\nb\xFF\"\nJos\xE9,ok\nMaria,\xC0\xC1\n";
+    let mut csv = CsvBuilder::new()
+        .lossy(true)
+        .from_reader(&data[..])
+        .unwrap();
+    let rows: Vec<String> = csv
+        .by_ref()
+        .map(|row| row.unwrap().values().join("|"))
+        .collect();
+    assert_eq!(
+        rows,
+        &[
+            "Ivan|a\nb\u{FFFD}",
+            "Jos\u{FFFD}|ok",
+            "Maria|\u{FFFD}\u{FFFD}"
+        ]
+    );
+    let records: Vec<usize> = csv.replaced_records().iter().map(|p| p.record).collect();
+    assert_eq!(records, &[2, 3, 4]);
+    assert_eq!(csv.replaced_records()[1].line, 4);
+
+    let mut csv = Csv::new(&b"a\n1\n"[..]).unwrap();
+    csv.set_lossy(true);
+    assert_eq!(csv.by_ref().count(), 1);
+    assert!(csv.replaced_records().is_empty());
+}
+
+#[test]
+fn windows_1251_test() {
+    let data = windows_1251("Име,Град\nИван,София\nМария,Пловдив\n");
+    let csv = Csv::new(Decoder::with_encoding(&data[..], Encoding::Windows1251)).unwrap();
+    assert_eq!(csv.columns(), &["Име", "Град"]);
+    assert_eq!(values(csv), &["Иван|София", "Мария|Пловдив"]);
+
+    // the ones outside 'А'..='я'
+    let data = b"a\n\xA8\xB8\xB9\x96\x88\n";
+    let csv = Csv::new(Decoder::with_encoding(&data[..], Encoding::Windows1251)).unwrap();
+    assert_eq!(values(csv), &["Ёё№–€"]);
+}
+
+#[test]
+fn undecodable_test() {
+    // 0x98 is not a character in Windows-1251
+    let data = b"a\nx\x98\ny\n";
+    let csv = Csv::new(Decoder::with_encoding(&data[..], Encoding::Windows1251)).unwrap();
+    let rows = values(csv);
+    assert!(rows[0].contains("not valid UTF-8"), "{}", rows[0]);
+    assert_eq!(rows[1], "y");
+
+    let decoder = Decoder::with_encoding(&data[..], Encoding::Windows1251);
+    let mut csv = CsvBuilder::new().lossy(true).from_reader(decoder).unwrap();
+    let rows: Vec<Row> = csv.by_ref().map(Result::unwrap).collect();
+    assert_eq!(rows[0]["a"], "x\u{FFFD}");
+    assert_eq!(csv.replaced_records().len(), 1);
+}
+
+#[test]
+fn latin1_test() {
+    let csv = Csv::new(Decoder::with_encoding(
+        &b"word\ncaf\xE9\n"[..],
+        Encoding::Latin1,
+    ))
+    .unwrap();
+    assert_eq!(values(csv), &["café"]);
+}
+
+#[test]
+fn utf16_test() {
+    let text = "name,emoji\nИван,\"🙂\"\n";
+    for &little_endian in &[true, false] {
+        let with_bom = utf16(&format!("\u{FEFF}{}", text), little_endian);
+        let without_bom = utf16(text, little_endian);
+        for data in &[with_bom, without_bom] {
+            let mut decoder = Decoder::new(OneByte(data));
+            assert_eq!(decoder.encoding(), None);
+            let mut decoded = String::new();
+            decoder.read_to_string(&mut decoded).unwrap();
+            assert_eq!(decoded, text);
+            let expected = if little_endian {
+                Encoding::Utf16Le
+            } else {
+                Encoding::Utf16Be
+            };
+            assert_eq!(decoder.encoding(), Some(expected));
+
+            let csv = Csv::new(Decoder::new(&data[..])).unwrap();
+            assert_eq!(values(csv), &["Иван|🙂"]);
+        }
+    }
+}
+
+#[test]
+fn invalid_utf16_test() {
+    let mut data = utf16("a\nx", true);
+    // a lone low surrogate, a line break and half a code unit
+    data.extend_from_slice(&[0x00, 0xDC, b'\n', 0x00, b'y']);
+    let decoder = Decoder::with_encoding(&data[..], Encoding::Utf16Le);
+    let mut csv = CsvBuilder::new().lossy(true).from_reader(decoder).unwrap();
+    let rows: Vec<Row> = csv.by_ref().map(Result::unwrap).collect();
+    assert_eq!(rows[0]["a"], "x\u{FFFD}");
+    assert_eq!(rows[1]["a"], "\u{FFFD}");
+    assert_eq!(csv.replaced_records().len(), 2);
+}
+
+#[test]
+fn detect_test() {
+    assert_eq!(Encoding::detect(b"\xEF\xBB\xBFa,b"), Encoding::Utf8);
+    assert_eq!(Encoding::detect(b"\xFF\xFEa\x00"), Encoding::Utf16Le);
+    assert_eq!(Encoding::detect(b"\xFE\xFF\x00a"), Encoding::Utf16Be);
+    assert_eq!(Encoding::detect(b"a\x00,\x00b\x00"), Encoding::Utf16Le);
+    assert_eq!(Encoding::detect(b"\x00a\x00,\x00b"), Encoding::Utf16Be);
+    assert_eq!(Encoding::detect(b"a,b\n"), Encoding::Utf8);
+    assert_eq!(Encoding::detect(b"\xC8\xEC\xE5"), Encoding::Utf8);
+    assert_eq!(Encoding::detect(b""), Encoding::Utf8);
+
+    let mut decoder = Decoder::new(&b"a,b\n"[..]);
+    io::copy(&mut decoder, &mut io::sink()).unwrap();
+    assert_eq!(decoder.encoding(), Some(Encoding::Utf8));
+}
+
+#[test]
+fn parallel_test() {
+    let mut data = b"\xEF\xBB\xBFid,name\n".to_vec();
+    for i in 0..200 {
+        data.extend_from_slice(format!("{},", i).as_bytes());
+        data.extend_from_slice(if i % 30 == 0 { b"bad\xFF\n" } else { b"good\n" });
+    }
+    let read = || {
+        CsvBuilder::new()
+            .lossy(true)
+            .from_reader(&data[..])
+            .unwrap()
+    };
+    let mut csv = read();
+    let expected: Vec<Row> = csv.by_ref().map(Result::unwrap).collect();
+    let expected_replaced = csv.replaced_records().to_vec();
+    assert_eq!(expected_replaced.len(), 7);
+
+    let mut rows = read().parallel().threads(1).chunk_size(64).run().unwrap();
+    assert_eq!(rows.columns, &["id", "name"]);
+    let parallel: Vec<Row> = rows.by_ref().map(Result::unwrap).collect();
+    assert_eq!(parallel, expected);
+    assert_eq!(rows.replaced_records(), &expected_replaced[..]);
+
+    // strict, the records are errors in the same places
+    let strict = || Csv::new(&data[..]).unwrap();
+    let expected = values(strict());
+    let rows: Vec<String> = strict()
+        .parallel()
+        .chunk_size(64)
+        .run()
+        .unwrap()
+        .map(|row| match row {
+            Ok(row) => row.values().join("|"),
+            Err(error) => error.to_string(),
+        })
+        .collect();
+    assert_eq!(rows, expected);
+}
@@ -0,0 +1,143 @@
+use solution::*;
+use std::error::Error;
+use std::io::{self, BufRead, BufReader, Read};
+
+fn first_error(data: &str, mode: ParseMode) -> ErrorDetails {
+    let csv = Csv::with_mode(BufReader::new(data.as_bytes()), mode).unwrap();
+    for row in csv {
+        match row {
+            Err(CsvError::ParseError(details)) => return *details,
+            Err(error) => panic!("expected a parse error, got {:?}", error),
+            Ok(_) => {}
+        }
+    }
+    panic!("expected a parse error");
+}
+
+#[test]
+fn stray_quote_test() {
+    let details = first_error(
+        "name,height,age\nAda,170,36\n\nBob,5'10\",20\n",
+        ParseMode::Strict,
+    );
+    // in strict mode the blank line is a record with a single empty value
+    assert_eq!(
+        details.position,
+        Some(Position {
+            record: 3,
+            line: 3,
+            byte: 27,
+        })
+    );
+    assert_eq!(details.column, Some(1));
+
+    let details = first_error(
+        "name,height,age\nAda,170,36\nBob,5'10\",20\n",
+        ParseMode::Strict,
+    );
+    assert_eq!(details.position.unwrap().record, 3);
+    assert_eq!(details.position.unwrap().line, 3);
+    assert_eq!(details.column, Some(1));
+    assert_eq!(details.column_name.as_deref(), Some("height"));
+    assert_eq!(details.excerpt.as_deref(), Some("Bob,5'10\",20"));
+}
+
+#[test]
+fn missing_and_extra_values_test() {
+    let details = first_error("a,b,c\n1,2,3\n# not a comment\n", ParseMode::Lenient);
+    assert_eq!(details.position.unwrap().record, 3);
+    assert_eq!(details.column, Some(1));
+    assert_eq!(details.column_name.as_deref(), Some("b"));
+
+    let details = first_error("a,b\n1,2,3,4\n", ParseMode::Lenient);
+    assert_eq!(details.column, Some(2));
+    assert_eq!(details.column_name, None);
+    assert_eq!(details.excerpt.as_deref(), Some("1,2,3,4"));
+}
+
+#[test]
+fn comments_are_not_records_test() {
+    let data = "# exported today\na,b\n# first\n1,2\n3\n".as_bytes();
+    let mut csv = CsvBuilder::new()
+        .comment("#")
+        .from_reader(BufReader::new(data))
+        .unwrap();
+    assert!(csv.next().unwrap().is_ok());
+    match csv.next() {
+        Some(Err(CsvError::ParseError(details))) => {
+            assert_eq!(
+                details.position,
+                Some(Position {
+                    record: 3,
+                    line: 5,
+                    byte: 33,
+                })
+            );
+        }
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn long_excerpt_is_cut_test() {
+    let data = format!("a,b\n\"{}\"x{},2\n", "y".repeat(50), "z".repeat(50));
+    let details = first_error(&data, ParseMode::Lenient);
+    assert_eq!(
+        details.excerpt.unwrap(),
+        format!("{}\"x{}", "y".repeat(19), "z".repeat(19))
+    );
+}
+
+#[test]
+fn parse_line_has_no_position_test() {
+    let csv = Csv::new(BufReader::new("name,age\n".as_bytes())).unwrap();
+    match csv.parse_line("Ada") {
+        Err(CsvError::ParseError(details)) => {
+            assert_eq!(details.position, None);
+            assert_eq!(details.column_name.as_deref(), Some("age"));
+        }
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn display_test() {
+    let details = first_error("name,age\nAda,36\nBob,\"20\" years\n", ParseMode::Lenient);
+    assert_eq!(
+        CsvError::ParseError(details.into()).to_string(),
+        "Parse error: There should be nothing between a closing quote and the delimiter! \
+         (record 3 starting at line 3, byte offset 16, column 1 \"age\") near \"Bob,\\\"20\\\" years\""
+    );
+    assert_eq!(
+        CsvError::ParseError("Bad".into()).to_string(),
+        "Parse error: Bad"
+    );
+}
+
+struct ErroringReader;
+
+impl Read for ErroringReader {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::PermissionDenied, "no access"))
+    }
+}
+
+impl BufRead for ErroringReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Err(io::Error::new(io::ErrorKind::PermissionDenied, "no access"))
+    }
+
+    fn consume(&mut self, _amt: usize) {}
+}
+
+#[test]
+fn io_error_source_test() {
+    let error = Csv::new(ErroringReader).err().unwrap();
+    assert_eq!(error.to_string(), "I/O error: no access");
+
+    let source = error.source().unwrap();
+    let io_error = source.downcast_ref::<io::Error>().unwrap();
+    assert_eq!(io_error.kind(), io::ErrorKind::PermissionDenied);
+
+    assert!(CsvError::InvalidColumn("x".into()).source().is_none());
+}
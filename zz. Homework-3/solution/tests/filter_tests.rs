@@ -0,0 +1,140 @@
+use solution::*;
+use std::io::BufReader;
+
+const DATA: &str = "name,age,birth date,city
+Ada Lovelace,36,1815-12-10,London
+Alan Turing,41,1912-06-23,
+Gen Z. Person,20,2000-01-01,Sofia
+";
+
+fn names(expression: &str) -> Vec<String> {
+    let mut csv = Csv::new(BufReader::new(DATA.as_bytes())).unwrap();
+    csv.apply_filter(expression).unwrap();
+    csv.map(|row| row.unwrap()["name"].clone()).collect()
+}
+
+fn compile_error(expression: &str) -> FilterError {
+    match Filter::compile(expression) {
+        Err(error) => error,
+        Ok(filter) => panic!("{:?} should not compile", filter.expression()),
+    }
+}
+
+#[test]
+fn comparison_test() {
+    assert_eq!(names("age > 30"), &["Ada Lovelace", "Alan Turing"]);
+    assert_eq!(names("age <= 36"), &["Ada Lovelace", "Gen Z. Person"]);
+    assert_eq!(
+        names("`birth date` >= \"1900-01-01\""),
+        &["Alan Turing", "Gen Z. Person"]
+    );
+    assert_eq!(names("name == \"Alan Turing\""), &["Alan Turing"]);
+    // compared as numbers, not as text("100" < "36")
+    assert_eq!(names("age < 100").len(), 3);
+}
+
+#[test]
+fn non_finite_test() {
+    let filtered = |expression: &str| {
+        let data = "name,age\nNaN,1\nNan,2\ninf,3\nInfinity,4\n";
+        let mut csv = Csv::new(BufReader::new(data.as_bytes())).unwrap();
+        csv.apply_filter(expression).unwrap();
+        csv.map(|row| row.unwrap()["age"].clone())
+            .collect::<Vec<String>>()
+    };
+    // "NaN" and "inf" are text, not numbers which are in no order with the others
+    assert_eq!(filtered("name == \"Nan\""), &["2"]);
+    assert_eq!(filtered("name != \"Nan\""), &["1", "3", "4"]);
+    assert_eq!(filtered("name in [\"Nan\", \"inf\"]"), &["2", "3"]);
+    assert_eq!(
+        filtered("name == \"NaN\" || name == \"Infinity\""),
+        &["1", "4"]
+    );
+    assert_eq!(filtered("name > \"N\""), &["1", "2", "3"]);
+
+    let mut csv = Csv::new(BufReader::new("name,age\nfive,inf\n".as_bytes())).unwrap();
+    csv.apply_filter("age > 5").unwrap();
+    assert!(matches!(csv.next(), Some(Err(CsvError::InvalidRow(_)))));
+    assert!(Filter::compile("age > -inf").is_err());
+}
+
+#[test]
+fn regex_test() {
+    assert_eq!(names("age > 30 && name ~ \"^Ada\""), &["Ada Lovelace"]);
+    assert_eq!(names("name !~ \"^A\""), &["Gen Z. Person"]);
+}
+
+#[test]
+fn in_and_null_test() {
+    assert_eq!(names("city in [\"Sofia\", \"Paris\"]"), &["Gen Z. Person"]);
+    assert_eq!(names("age in [20, 41]"), &["Alan Turing", "Gen Z. Person"]);
+    assert_eq!(names("city is null"), &["Alan Turing"]);
+    assert_eq!(names("city is not null").len(), 2);
+    // an empty value is not equal or unequal to anything
+    assert_eq!(names("city != \"Sofia\""), &["Ada Lovelace"]);
+}
+
+#[test]
+fn boolean_logic_test() {
+    assert_eq!(
+        names("city == \"Sofia\" || age > 40 && name ~ \"Turing\""),
+        &["Alan Turing", "Gen Z. Person"]
+    );
+    assert_eq!(
+        names("(city == \"Sofia\" || age > 40) && !(name ~ \"Turing\")"),
+        &["Gen Z. Person"]
+    );
+}
+
+#[test]
+fn compile_error_test() {
+    let error = compile_error("age > 30 && name ~ ");
+    assert_eq!(error.offset, 19);
+    assert_eq!(
+        error.to_string(),
+        "Expected a regular expression string at character 20\n    \
+         age > 30 && name ~ \n                       ^"
+    );
+
+    assert_eq!(compile_error("age >> 30").offset, 5);
+    assert_eq!(compile_error("name ~ \"(\"").offset, 7);
+    assert_eq!(compile_error("age 30").offset, 4);
+    assert_eq!(compile_error("(age > 30").offset, 9);
+    assert_eq!(compile_error("city in [\"Sofia\" \"Paris\"]").offset, 17);
+    assert_eq!(compile_error("name == \"Ada").offset, 8);
+    assert_eq!(compile_error("age > 30 $").offset, 9);
+    assert_eq!(compile_error("city is empty").offset, 8);
+}
+
+#[test]
+fn unknown_column_test() {
+    let mut csv = Csv::new(BufReader::new(DATA.as_bytes())).unwrap();
+    match csv.apply_filter("age > 30 && country == \"UK\"") {
+        Err(CsvError::InvalidFilter(error)) => {
+            assert_eq!(error.offset, 12);
+            assert!(error.message.contains("country"), "{}", error);
+        }
+        other => panic!("expected an invalid filter, got {:?}", other.err()),
+    }
+
+    // a column added by the pipeline can be used after it
+    csv.map_column("country", |row| {
+        Ok::<_, CsvError>(if row["city"] == "Sofia" { "BG" } else { "UK" }.to_string())
+    })
+    .apply_filter("country == \"BG\"")
+    .unwrap();
+    assert_eq!(csv.count(), 1);
+}
+
+#[test]
+fn runtime_error_test() {
+    let mut csv = Csv::new(BufReader::new(DATA.as_bytes())).unwrap();
+    csv.apply_filter("name > 10").unwrap();
+    match csv.next() {
+        Some(Err(CsvError::InvalidRow(details))) => {
+            assert_eq!(details.column_name.as_deref(), Some("name"));
+            assert_eq!(details.excerpt.as_deref(), Some("Ada Lovelace"));
+        }
+        other => panic!("expected an invalid row, got {:?}", other),
+    }
+}
@@ -0,0 +1,201 @@
+use solution::*;
+use std::io::BufReader;
+
+const DATA: &str = "country,city,population,area
+BG,Sofia,1236000,492
+UK,London,8982000,1572
+BG,Plovdiv,346893,102
+UK,Leeds,,551.7
+BG,Varna,336000,154
+";
+
+fn csv(data: &'static str) -> Csv<BufReader<&'static [u8]>> {
+    Csv::new(BufReader::new(data.as_bytes())).unwrap()
+}
+
+fn values(grouped: Grouped) -> Vec<Vec<String>> {
+    grouped.map(|row| row.unwrap().into_values()).collect()
+}
+
+#[test]
+fn count_sum_test() {
+    let grouped = csv(DATA)
+        .group_by(&["country"])
+        .unwrap()
+        .count()
+        .unwrap()
+        .sum("population")
+        .unwrap()
+        .sum("area")
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(
+        grouped.columns,
+        &["country", "count", "sum(population)", "sum(area)"]
+    );
+    assert_eq!(
+        values(grouped),
+        &[
+            ["BG", "3", "1918893", "748"],
+            ["UK", "2", "8982000", "2123.7"],
+        ]
+    );
+}
+
+#[test]
+fn min_max_avg_test() {
+    let grouped = csv(DATA)
+        .group_by(&["country"])
+        .unwrap()
+        .min("population")
+        .unwrap()
+        .max("population")
+        .unwrap()
+        .min("city")
+        .unwrap()
+        .avg("area")
+        .unwrap()
+        .run()
+        .unwrap();
+    let rows = values(grouped);
+    // as numbers "336000" < "346893" < "1236000", as text "1236000" would be the smallest
+    assert_eq!(
+        rows[0],
+        &["BG", "336000", "1236000", "Plovdiv", "249.33333333333334"]
+    );
+    // the empty population of Leeds is skipped
+    assert_eq!(rows[1], &["UK", "8982000", "8982000", "Leeds", "1061.85"]);
+}
+
+#[test]
+fn distinct_first_last_test() {
+    let data = "team,player,score\na,x,1\nb,y,2\na,z,3\na,x,4\n";
+    let grouped = csv(data)
+        .group_by(&["team"])
+        .unwrap()
+        .distinct_count("player")
+        .unwrap()
+        .first("score")
+        .unwrap()
+        .last("score")
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(
+        values(grouped),
+        &[["a", "2", "1", "4"], ["b", "1", "2", "2"]]
+    );
+}
+
+#[test]
+fn several_keys_and_pipeline_test() {
+    let data = "a,b,n\n1,x,5\n1,y,6\n1,x,7\n2,x,8\n";
+    let mut csv = csv(data);
+    csv.apply_filter("n > 5").unwrap();
+    let grouped = csv
+        .group_by(&["a", "b"])
+        .unwrap()
+        .aggregate(Aggregate::Sum("n".into()))
+        .unwrap()
+        .run()
+        .unwrap();
+    assert_eq!(
+        values(grouped),
+        &[["1", "y", "6"], ["1", "x", "7"], ["2", "x", "8"]]
+    );
+}
+
+#[test]
+fn write_to_test() {
+    let grouped = csv(DATA)
+        .group_by(&["country"])
+        .unwrap()
+        .count()
+        .unwrap()
+        .run()
+        .unwrap();
+    let mut output = Vec::new();
+    grouped.write_to(&mut output).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "country, count\n\"BG\", \"3\"\n\"UK\", \"2\"\n"
+    );
+}
+
+#[test]
+fn errors_test() {
+    assert!(matches!(
+        csv(DATA).group_by(&["continent"]).err(),
+        Some(CsvError::InvalidColumn(column)) if column == "continent"
+    ));
+    assert!(matches!(
+        csv(DATA).group_by(&["country"]).unwrap().sum("size").err(),
+        Some(CsvError::InvalidColumn(column)) if column == "size"
+    ));
+
+    let sum_of_names = csv(DATA)
+        .group_by(&["country"])
+        .unwrap()
+        .sum("city")
+        .unwrap();
+    match sum_of_names.run() {
+        Err(CsvError::InvalidRow(details)) => {
+            assert_eq!(details.column_name.as_deref(), Some("city"));
+            assert_eq!(details.excerpt.as_deref(), Some("Sofia"));
+        }
+        other => panic!("expected an invalid row, got {:?}", other.err()),
+    }
+
+    // the columns of the result must be unique
+    let data = "count,n\n1,2\n";
+    assert!(matches!(
+        csv(data).group_by(&["count"]).unwrap().count().err(),
+        Some(CsvError::InvalidHeader(_))
+    ));
+    assert!(matches!(
+        csv(data)
+            .group_by(&["count"])
+            .unwrap()
+            .sum("n")
+            .unwrap()
+            .sum("n")
+            .err(),
+        Some(CsvError::InvalidHeader(_))
+    ));
+}
+
+#[test]
+fn whitespace_test() {
+    let data = "k,n\na,1\na, \na,2\n";
+    let grouped = CsvBuilder::new()
+        .trim(false)
+        .from_reader(BufReader::new(data.as_bytes()))
+        .unwrap()
+        .group_by(&["k"])
+        .unwrap()
+        .sum("n")
+        .unwrap()
+        .min("n")
+        .unwrap()
+        .avg("n")
+        .unwrap()
+        .run()
+        .unwrap();
+    // the same with and without the `bigint` feature
+    assert_eq!(values(grouped), &[["a", "3", "1", "1.5"]]);
+}
+
+#[test]
+fn big_sum_test() {
+    let data = "k,n\na,170141183460469231731687303715884105727\na,1\n";
+    let result = csv(data).group_by(&["k"]).unwrap().sum("n").unwrap().run();
+    if cfg!(feature = "bigint") {
+        assert_eq!(
+            values(result.unwrap()),
+            &[["a", "170141183460469231731687303715884105728"]]
+        );
+    } else {
+        assert!(matches!(result.err(), Some(CsvError::InvalidRow(_))));
+    }
+}
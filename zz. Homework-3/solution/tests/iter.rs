@@ -24,7 +24,7 @@ fn iter_test() {
             .ok_or_else(|| CsvError::InvalidColumn(String::from("age")))?;
         let age = age
             .parse::<u32>()
-            .map_err(|_| CsvError::ParseError(String::from(age)))?;
+            .map_err(|_| CsvError::ParseError(String::from(age).into()))?;
 
         Ok(age > 30)
     });
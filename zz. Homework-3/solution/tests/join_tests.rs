@@ -0,0 +1,240 @@
+use solution::*;
+use std::io::BufReader;
+
+const ORDERS: &str = "order,customer,total,note
+1,ada,10,
+2,bob,20,gift
+3,ada,5,
+4,eve,7,
+";
+
+const CUSTOMERS: &str = "customer,name,note
+ada,Ada Lovelace,vip
+bob,Bob Smith,
+carl,Carl Gauss,
+";
+
+fn csv(data: &'static str) -> Csv<BufReader<&'static [u8]>> {
+    Csv::new(BufReader::new(data.as_bytes())).unwrap()
+}
+
+fn values<L: std::io::BufRead, R: std::io::BufRead>(joined: Joined<L, R>) -> Vec<Vec<String>> {
+    joined.map(|row| row.unwrap().into_values()).collect()
+}
+
+fn join(kind: JoinKind, strategy: JoinStrategy) -> Vec<Vec<String>> {
+    // the merge join needs both sides sorted by the customer
+    let orders = match strategy {
+        JoinStrategy::Hash => ORDERS,
+        JoinStrategy::Merge => {
+            "order,customer,total,note\n1,ada,10,\n3,ada,5,\n2,bob,20,gift\n4,eve,7,\n"
+        }
+    };
+    let mut rows = values(
+        csv(orders)
+            .join(csv(CUSTOMERS), &["customer"])
+            .kind(kind)
+            .strategy(strategy)
+            .run()
+            .unwrap(),
+    );
+    rows.sort();
+    rows
+}
+
+fn row(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+#[test]
+fn header_test() {
+    let joined = csv(ORDERS)
+        .join(csv(CUSTOMERS), &["customer"])
+        .run()
+        .unwrap();
+    assert_eq!(
+        joined.columns,
+        &[
+            "order",
+            "customer",
+            "total",
+            "note_left",
+            "name",
+            "note_right"
+        ]
+    );
+
+    let joined = csv(ORDERS)
+        .join(csv(CUSTOMERS), &["customer"])
+        .suffixes("", "_customer")
+        .run()
+        .unwrap();
+    assert_eq!(joined.columns[3], "note");
+    assert_eq!(joined.columns[5], "note_customer");
+}
+
+#[test]
+fn inner_join_test() {
+    let expected = vec![
+        row(&["1", "ada", "10", "", "Ada Lovelace", "vip"]),
+        row(&["2", "bob", "20", "gift", "Bob Smith", ""]),
+        row(&["3", "ada", "5", "", "Ada Lovelace", "vip"]),
+    ];
+    assert_eq!(join(JoinKind::Inner, JoinStrategy::Hash), expected);
+    assert_eq!(join(JoinKind::Inner, JoinStrategy::Merge), expected);
+}
+
+#[test]
+fn left_join_test() {
+    let expected = vec![
+        row(&["1", "ada", "10", "", "Ada Lovelace", "vip"]),
+        row(&["2", "bob", "20", "gift", "Bob Smith", ""]),
+        row(&["3", "ada", "5", "", "Ada Lovelace", "vip"]),
+        row(&["4", "eve", "7", "", "", ""]),
+    ];
+    assert_eq!(join(JoinKind::Left, JoinStrategy::Hash), expected);
+    assert_eq!(join(JoinKind::Left, JoinStrategy::Merge), expected);
+}
+
+#[test]
+fn full_outer_join_test() {
+    let expected = vec![
+        row(&["", "carl", "", "", "Carl Gauss", ""]),
+        row(&["1", "ada", "10", "", "Ada Lovelace", "vip"]),
+        row(&["2", "bob", "20", "gift", "Bob Smith", ""]),
+        row(&["3", "ada", "5", "", "Ada Lovelace", "vip"]),
+        row(&["4", "eve", "7", "", "", ""]),
+    ];
+    assert_eq!(join(JoinKind::FullOuter, JoinStrategy::Hash), expected);
+    assert_eq!(join(JoinKind::FullOuter, JoinStrategy::Merge), expected);
+}
+
+#[test]
+fn several_keys_test() {
+    let left = "a,b,x\n1,1,p\n1,2,q\n2,1,r\n";
+    let right = "b,a,y\n1,1,s\n1,2,t\n2,2,u\n";
+    for &strategy in &[JoinStrategy::Hash, JoinStrategy::Merge] {
+        let joined = csv(left)
+            .join(csv(right), &["a", "b"])
+            .strategy(strategy)
+            .run()
+            .unwrap();
+        assert_eq!(joined.columns, &["a", "b", "x", "y"]);
+        let mut rows = values(joined);
+        rows.sort();
+        assert_eq!(
+            rows,
+            vec![row(&["1", "1", "p", "s"]), row(&["2", "1", "r", "t"])]
+        );
+    }
+}
+
+#[test]
+fn duplicate_keys_on_both_sides_test() {
+    let left = "k,l\na,1\na,2\nb,3\n";
+    let right = "k,r\na,x\na,y\n";
+    let joined = csv(left)
+        .join(csv(right), &["k"])
+        .strategy(JoinStrategy::Merge)
+        .run()
+        .unwrap();
+    assert_eq!(
+        values(joined),
+        vec![
+            row(&["a", "1", "x"]),
+            row(&["a", "1", "y"]),
+            row(&["a", "2", "x"]),
+            row(&["a", "2", "y"]),
+        ]
+    );
+}
+
+#[test]
+fn numeric_merge_test() {
+    let sorted = |data: &'static str| csv(data).sort_by(&["-id:number"]).unwrap().run();
+    let left = "id,x\n9,a\n10,b\n100,c\n";
+    let right = "id,y\n100,p\n10.0,q\n2,r\n";
+    let joined = Csv::new(BufReader::new(write(sorted(left).unwrap())))
+        .unwrap()
+        .join(
+            Csv::new(BufReader::new(write(sorted(right).unwrap()))).unwrap(),
+            &["id"],
+        )
+        .strategy(JoinStrategy::Merge)
+        .sorted_by(&["-id:number"])
+        .run()
+        .unwrap();
+    assert_eq!(
+        values(joined),
+        vec![row(&["100", "c", "p"]), row(&["10", "b", "q"])]
+    );
+
+    // as text the keys are out of order
+    let unsorted = Csv::new(BufReader::new(write(sorted(left).unwrap())))
+        .unwrap()
+        .join(csv(right), &["id"])
+        .strategy(JoinStrategy::Merge)
+        .run()
+        .unwrap();
+    assert!(unsorted.filter_map(Result::err).next().is_some());
+
+    assert!(matches!(
+        csv(left)
+            .join(csv(right), &["id"])
+            .strategy(JoinStrategy::Merge)
+            .sorted_by(&["x"])
+            .run()
+            .err(),
+        Some(CsvError::InvalidColumn(column)) if column == "x"
+    ));
+}
+
+fn write(sorted: Sorted) -> &'static [u8] {
+    let mut output = Vec::new();
+    sorted.write_to(&mut output).unwrap();
+    Box::leak(output.into_boxed_slice())
+}
+
+#[test]
+fn write_to_test() {
+    let mut orders = csv(ORDERS);
+    orders.select_columns(&["order", "customer"]).unwrap();
+    let mut customers = csv(CUSTOMERS);
+    customers.select_columns(&["customer", "name"]).unwrap();
+    customers.apply_filter("customer == \"bob\"").unwrap();
+
+    let mut output = Vec::new();
+    orders
+        .join(customers, &["customer"])
+        .run()
+        .unwrap()
+        .write_to(&mut output)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "order, customer, name\n\"2\", \"bob\", \"Bob Smith\"\n"
+    );
+}
+
+#[test]
+fn errors_test() {
+    assert!(matches!(
+        csv(ORDERS).join(csv(CUSTOMERS), &["order"]).run().err(),
+        Some(CsvError::InvalidColumn(column)) if column == "order"
+    ));
+    assert!(matches!(
+        csv(ORDERS).join(csv(CUSTOMERS), &["customer"]).suffixes("", "").run().err(),
+        Some(CsvError::InvalidColumn(column)) if column == "note"
+    ));
+
+    let unsorted = csv(ORDERS)
+        .join(csv(CUSTOMERS), &["customer"])
+        .strategy(JoinStrategy::Merge)
+        .run()
+        .unwrap();
+    let error = unsorted.filter_map(Result::err).next();
+    match error {
+        Some(CsvError::InvalidRow(details)) => assert_eq!(details.excerpt.as_deref(), Some("ada")),
+        other => panic!("expected an invalid row, got {:?}", other),
+    }
+}
@@ -0,0 +1,145 @@
+use solution::*;
+use std::io::BufReader;
+
+#[test]
+fn quoted_field_with_line_break_test() {
+    let data = "name,address,age\n\"Ada\",\"12 Some Street\nLondon\",36\n\"Bob\",\"Sofia\",20\n"
+        .as_bytes();
+    let rows: Vec<_> = Csv::new(BufReader::new(data))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["address"], "12 Some Street\nLondon");
+    assert_eq!(rows[0]["age"], "36");
+    assert_eq!(rows[1]["name"], "Bob");
+}
+
+#[test]
+fn crlf_inside_quotes_is_kept_test() {
+    let data = "a,b\r\n\"one\r\ntwo\r\n\",x\r\n".as_bytes();
+    let csv = Csv::with_mode(BufReader::new(data), ParseMode::Strict).unwrap();
+    let rows: Vec<_> = csv.map(Result::unwrap).collect();
+
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0]["a"], "one\r\ntwo\r\n");
+    assert_eq!(rows[0]["b"], "x");
+}
+
+#[test]
+fn multiline_header_test() {
+    let data = "\"first\nname\",age\nAda,36\n".as_bytes();
+    let mut csv = Csv::new(BufReader::new(data)).unwrap();
+    assert_eq!(csv.columns(), &["first\nname", "age"]);
+    assert_eq!(csv.next().unwrap().unwrap()["first\nname"], "Ada");
+}
+
+#[test]
+fn write_multiline_test() {
+    let data = "name,address\nAda,\"12 Some Street\nLondon\"\n".as_bytes();
+    let mut output = Vec::new();
+    Csv::new(BufReader::new(data))
+        .unwrap()
+        .write_to(&mut output)
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "name, address\n\"Ada\", \"12 Some Street\nLondon\"\n"
+    );
+}
+
+#[test]
+fn unterminated_quote_reports_position_test() {
+    let data = "name,age\nAda,36\nBob,\"20\nCarl,40\n".as_bytes();
+    let mut csv = Csv::new(BufReader::new(data)).unwrap();
+    assert!(csv.next().unwrap().is_ok());
+
+    match csv.next() {
+        Some(Err(CsvError::ParseError(details))) => {
+            let position = details.position.unwrap();
+            assert_eq!(position.line, 3);
+            assert_eq!(position.byte, 16);
+        }
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn max_record_size_test() {
+    let long_field = "x".repeat(100);
+    let data = format!(
+        "name,notes\nAda,\"{}\n{}\"\nBob,short\n",
+        long_field, long_field
+    );
+
+    let rows: Vec<_> = Csv::new(BufReader::new(data.as_bytes())).unwrap().collect();
+    assert!(rows.iter().all(Result::is_ok));
+
+    let mut csv = Csv::new(BufReader::new(data.as_bytes())).unwrap();
+    csv.set_max_record_size(150);
+    match csv.next() {
+        Some(Err(CsvError::ParseError(details))) => {
+            assert!(details.message.contains("150 bytes"), "{}", details);
+            let position = details.position.unwrap();
+            assert_eq!(position.line, 2);
+            assert_eq!(position.byte, 11);
+        }
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn many_lines_test() {
+    let lines: Vec<String> = (0..2000).map(|i| format!("line \"\"{}\"\",", i)).collect();
+    let data = format!("a,b\n\"{}\",x\r\n1,2\n", lines.join("\r\n"));
+    let rows: Vec<Row> = Csv::new(BufReader::new(data.as_bytes()))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["a"], lines.join("\r\n").replace("\"\"", "\""));
+    assert_eq!(rows[0]["b"], "x");
+    assert_eq!(rows[1]["a"], "1");
+
+    // the excerpt of an error is still from the whole record
+    let data = "a,b\n\"1\n2\"x,3\n";
+    match Csv::new(BufReader::new(data.as_bytes())).unwrap().next() {
+        Some(Err(CsvError::ParseError(details))) => {
+            assert_eq!(details.excerpt.as_deref(), Some("\"1\n2\"x,3"));
+        }
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn limit_inside_character_test() {
+    // the limit cuts the line in the middle of 'ж', which is still a record too long
+    let data = "a\n\"1234\nжжжж\"\n";
+    let mut csv = Csv::new(BufReader::new(data.as_bytes())).unwrap();
+    csv.set_max_record_size(8);
+    match csv.next() {
+        Some(Err(CsvError::ParseError(details))) => {
+            assert!(details.message.contains("8 bytes"), "{}", details);
+        }
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn wrong_row_reports_position_test() {
+    let data = "a,b\n1,2\n\n\"x\ny\",2,3\n".as_bytes();
+    let mut csv = Csv::new(BufReader::new(data)).unwrap();
+    assert!(csv.next().unwrap().is_ok());
+
+    match csv.next() {
+        Some(Err(CsvError::ParseError(details))) => {
+            let position = details.position.unwrap();
+            assert_eq!(position.line, 4);
+            assert_eq!(position.byte, 9);
+        }
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+    assert!(csv.next().is_none());
+}
@@ -0,0 +1,311 @@
+use solution::*;
+use std::io::{BufReader, Read};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Records which span lines, comments, blank lines and a broken record
+fn data() -> String {
+    let mut data = String::from("id,name,note\n");
+    for i in 0..500 {
+        match i % 7 {
+            0 => data.push_str(&format!("{},\"multi\nline {}\",x\n", i, i)),
+            1 => data.push_str(&format!("{},\"quoted, with \"\"quotes\"\"\",y\n", i)),
+            2 => data.push('\n'),
+            3 => data.push_str(&format!("{},  spaced  ,\"\"\n", i)),
+            _ => data.push_str(&format!("{},name {},\r\n", i, i)),
+        }
+        if i == 250 {
+            data.push_str("broken,row\n");
+        }
+    }
+    data
+}
+
+fn csv(data: &str) -> Csv<BufReader<&[u8]>> {
+    Csv::new(BufReader::new(data.as_bytes())).unwrap()
+}
+
+/// The rows as text, the errors as their messages
+fn results(rows: impl Iterator<Item = Result<Row, CsvError>>) -> Vec<String> {
+    rows.map(|row| match row {
+        Ok(row) => row.values().join("|"),
+        Err(error) => error.to_string(),
+    })
+    .collect()
+}
+
+#[test]
+fn ordered_test() {
+    let data = data();
+    let expected = results(csv(&data));
+    assert!(expected.iter().any(|row| row.starts_with("Parse error")));
+
+    for chunk_size in &[1, 64, 1000, DEFAULT_CHUNK_SIZE] {
+        let rows = csv(&data)
+            .parallel()
+            .threads(4)
+            .chunk_size(*chunk_size)
+            .run()
+            .unwrap();
+        assert_eq!(rows.columns, &["id", "name", "note"]);
+        assert_eq!(results(rows), expected, "chunk size {}", chunk_size);
+    }
+}
+
+#[test]
+fn unordered_test() {
+    let data = data();
+    let mut expected = results(csv(&data));
+    let rows = csv(&data)
+        .parallel()
+        .threads(3)
+        .chunk_size(100)
+        .ordered(false)
+        .run()
+        .unwrap();
+    let mut rows = results(rows);
+    expected.sort();
+    rows.sort();
+    assert_eq!(rows, expected);
+}
+
+#[test]
+fn dialect_test() {
+    let data = "# a comment\na;b\n1;\\;x\n# another\n2;\"y\\\"\nz\"\n";
+    let dialect = || {
+        CsvBuilder::new()
+            .delimiter(';')
+            .escape(Some('\\'))
+            .comment("#")
+    };
+    let read = |data: &'static str| dialect().from_reader(BufReader::new(data.as_bytes()));
+    let expected = results(read(data).unwrap());
+    assert_eq!(expected, &["1|;x", "2|y\"\nz"]);
+    let rows = read(data).unwrap().parallel().chunk_size(1).run().unwrap();
+    assert_eq!(results(rows), expected);
+}
+
+#[test]
+fn record_ends_test() {
+    // a quote after unicode whitespace, an escaped 'é', text after a closing quote and
+    // a quote in an unquoted field, which breaks the record only in the strict mode
+    let data = "a,b\n\u{3000}\"x\ny\",\"é\"\u{A0}\n1,\\é\"\n2\"\n\"q\"r,\"s\nt\"\n3,4\n";
+    let dialects = vec![
+        CsvBuilder::new().escape(Some('\\')),
+        CsvBuilder::new().escape(Some('\\')).trim(false),
+        CsvBuilder::new().mode(ParseMode::Strict),
+        CsvBuilder::new().quote('«').escape(Some('\\')),
+    ];
+    for dialect in dialects {
+        let read = || {
+            dialect
+                .clone()
+                .from_reader(BufReader::new(data.as_bytes()))
+                .unwrap()
+        };
+        let expected = results(read());
+        let rows = read().parallel().chunk_size(1).run().unwrap();
+        assert_eq!(results(rows), expected, "{:?}", dialect);
+    }
+}
+
+#[test]
+fn quoted_line_breaks_test() {
+    // the lines in the quoted fields look like records, with quotes which open fields, so
+    // a block which starts in one of them is scanned from a wrong start at first
+    let mut data = String::from("id,text\n");
+    for i in 0..40 {
+        data.push_str(&format!("{},\"a\n{},\"\"b\n\n# c\nd,\"\"\"\n", i, i));
+        data.push_str(&format!("{},plain\n", i));
+    }
+    let expected = results(csv(&data));
+    assert_eq!(expected.len(), 80);
+    for chunk_size in 1..40 {
+        let rows = csv(&data)
+            .parallel()
+            .threads(3)
+            .chunk_size(chunk_size)
+            .run()
+            .unwrap();
+        assert_eq!(results(rows), expected, "chunk size {}", chunk_size);
+    }
+
+    // a record longer than the limit is found also when it is in several blocks
+    let limited = || {
+        let mut csv = csv(&data);
+        csv.set_max_record_size(16);
+        csv
+    };
+    let error = results(limited()).remove(0);
+    assert!(error.contains("record 2 starting at line 2"), "{}", error);
+    for chunk_size in &[1, 5, 12, 100] {
+        let rows = results(limited().parallel().chunk_size(*chunk_size).run().unwrap());
+        assert_eq!(rows, [error.as_str()], "chunk size {}", chunk_size);
+    }
+}
+
+#[test]
+fn pipeline_test() {
+    let data = data();
+    let seen = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&seen);
+    let with_pipeline = |data| {
+        let mut csv = csv(data);
+        csv.apply_filter("id != 3").unwrap();
+        csv.map_column("length", |row: &Row| {
+            Ok::<_, BoxError>(row["name"].len().to_string())
+        });
+        csv
+    };
+    let expected = results(with_pipeline(&data));
+
+    let mut csv = with_pipeline(&data);
+    csv.inspect_rows(move |_| {
+        counter.fetch_add(1, Ordering::SeqCst);
+    });
+    let rows = csv.parallel().threads(4).chunk_size(50).run().unwrap();
+    assert_eq!(rows.columns, &["id", "name", "note", "length"]);
+    let rows = results(rows);
+    assert_eq!(rows, expected);
+    // every row but the broken one
+    assert_eq!(seen.load(Ordering::SeqCst), rows.len() - 1);
+}
+
+#[test]
+fn stage_error_position_test() {
+    let data = data();
+    let failing = |data| {
+        let mut csv = csv(data);
+        csv.filter_rows(|row: &Row| match row["id"].as_str() {
+            "400" => Err("four hundred"),
+            _ => Ok(true),
+        });
+        csv
+    };
+    let expected = results(failing(&data));
+    assert!(expected.iter().any(|row| row.contains("four hundred")));
+    let rows = failing(&data).parallel().chunk_size(10).run().unwrap();
+    assert_eq!(results(rows), expected);
+}
+
+#[test]
+fn without_header_test() {
+    let data = "1,a\n2,b\n3,c\n";
+    let read = || {
+        CsvBuilder::new()
+            .has_header(false)
+            .from_reader(data.as_bytes())
+    };
+    let rows = read().unwrap().parallel().chunk_size(1).run().unwrap();
+    assert_eq!(rows.columns, &["0", "1"]);
+    assert_eq!(results(rows), &["1|a", "2|b", "3|c"]);
+}
+
+#[test]
+fn schema_test() {
+    let data = "n\n1\n2\n3\nx\n4\n";
+    let mut csv = csv(data);
+    let schema = csv.infer_schema(2).unwrap();
+    csv.set_schema(schema).unwrap();
+    let rows = results(csv.parallel().chunk_size(1).run().unwrap());
+    assert_eq!(rows.len(), 5);
+    assert_eq!(&rows[..3], &["1", "2", "3"]);
+    assert!(rows[3].contains("Expected an integer"), "{}", rows[3]);
+    assert!(
+        rows[3].contains("record 5 starting at line 5"),
+        "{}",
+        rows[3]
+    );
+    assert_eq!(rows[4], "4");
+}
+
+#[test]
+fn record_too_long_test() {
+    let data = "a,b\n1,2\n3,\"long\nlong\nlong\"\n5,6\n";
+    let mut csv = csv(data);
+    csv.set_max_record_size(10);
+    let rows = results(csv.parallel().run().unwrap());
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0], "1|2");
+    assert!(
+        rows[1].contains("longer than the limit of 10 bytes"),
+        "{}",
+        rows[1]
+    );
+    assert!(
+        rows[1].contains("record 3 starting at line 3"),
+        "{}",
+        rows[1]
+    );
+}
+
+/// A reader which fails after its data
+struct Failing(&'static [u8]);
+
+impl Read for Failing {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.0.is_empty() {
+            return Err(std::io::Error::other("disk on fire"));
+        }
+        self.0.read(buf)
+    }
+}
+
+#[test]
+fn io_error_test() {
+    let read = || Csv::new(BufReader::new(Failing(b"a,b\n1,2\n3,\"4\n5\"\n6,"))).unwrap();
+    // the reader fails again on every next row, the parallel rows end with the error
+    let expected = results(read().take(3));
+    assert_eq!(expected, &["1|2", "3|4\n5", "I/O error: disk on fire"]);
+    for chunk_size in &[1, 7, 1000] {
+        let rows = read().parallel().chunk_size(*chunk_size).run().unwrap();
+        assert_eq!(results(rows), expected, "chunk size {}", chunk_size);
+    }
+}
+
+#[test]
+fn write_test() {
+    let data = "a,b\n1,2\n3,4\n";
+    let mut output = Vec::new();
+    csv(data)
+        .parallel()
+        .chunk_size(1)
+        .run()
+        .unwrap()
+        .write_to(&mut output)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "a, b\n\"1\", \"2\"\n\"3\", \"4\"\n"
+    );
+}
+
+#[test]
+#[should_panic(expected = "a stage panicked")]
+fn panic_test() {
+    let mut csv = csv("a\n1\n2\n");
+    csv.inspect_rows(|row| {
+        if row["a"] == "2" {
+            panic!("a stage panicked");
+        }
+    });
+    csv.parallel()
+        .threads(2)
+        .chunk_size(1)
+        .run()
+        .unwrap()
+        .count();
+}
+
+#[test]
+fn drop_early_test() {
+    let data = data();
+    let mut rows = csv(&data)
+        .parallel()
+        .threads(4)
+        .chunk_size(64)
+        .run()
+        .unwrap();
+    assert_eq!(rows.next().unwrap().unwrap()["id"], "0");
+    drop(rows);
+}
@@ -0,0 +1,173 @@
+use solution::*;
+use std::io::BufReader;
+use std::sync::{Arc, Mutex};
+
+const DATA: &str = "first,last,age
+Ada,Lovelace,36
+Alan,Turing,41
+Bob,Smith,twenty
+";
+
+fn csv(data: &'static str) -> Csv<BufReader<&'static [u8]>> {
+    Csv::new(BufReader::new(data.as_bytes())).unwrap()
+}
+
+fn written(csv: Csv<BufReader<&[u8]>>) -> String {
+    let mut output = Vec::new();
+    csv.write_to(&mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn selections_are_combined_test() {
+    let mut csv = csv(DATA);
+    csv.apply_selection(|row| Ok(row["first"].starts_with('A')));
+    csv.apply_selection(|row| Ok(row["last"] != "Turing"));
+    let firsts: Vec<String> = csv.map(|row| row.unwrap()["first"].clone()).collect();
+    assert_eq!(firsts, &["Ada"]);
+}
+
+#[test]
+fn selection_error_test() {
+    let mut csv = csv(DATA);
+    csv.apply_selection(|row| match row["age"].parse::<u32>() {
+        Ok(age) => Ok(age > 40),
+        Err(_) => Err(CsvError::InvalidColumn("age".into())),
+    });
+    let rows: Vec<Result<Row, CsvError>> = csv.collect();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].as_ref().unwrap()["first"], "Alan");
+    // the error of the callback, not a `StageError`
+    match &rows[1] {
+        Err(CsvError::InvalidColumn(column)) => assert_eq!(column, "age"),
+        other => panic!("expected an invalid column, got {:?}", other),
+    }
+}
+
+#[test]
+fn map_column_test() {
+    let mut csv = csv(DATA);
+    csv.filter_rows(|row| Ok::<_, CsvError>(row["age"] != "twenty"))
+        .map_column("name", |row| {
+            Ok::<_, CsvError>(format!("{} {}", row["first"], row["last"]))
+        })
+        .map_column("age", |row| {
+            row["age"].parse::<u32>().map(|age| (age + 1).to_string())
+        });
+    assert_eq!(csv.output_columns(), &["first", "last", "age", "name"]);
+    assert_eq!(csv.columns(), &["first", "last", "age"]);
+
+    let rows: Vec<Row> = csv.map(Result::unwrap).collect();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].columns(), &["first", "last", "age", "name"]);
+    assert_eq!(rows[0]["name"], "Ada Lovelace");
+    assert_eq!(rows[1]["age"], "42");
+}
+
+#[test]
+fn write_to_extends_header_test() {
+    let mut csv = csv(DATA);
+    csv.select_columns(&["first"]).unwrap();
+    csv.map_column("initial", |row| {
+        Ok::<_, CsvError>(row["first"][..1].to_string())
+    });
+    assert_eq!(
+        written(csv),
+        "first, initial\n\"Ada\", \"A\"\n\"Alan\", \"A\"\n\"Bob\", \"B\"\n"
+    );
+}
+
+#[test]
+fn flat_map_test() {
+    let mut csv = csv("word,count\na,2\nb,0\nc,1\n");
+    csv.flat_map_rows(|row| {
+        let count: usize = row["count"].parse()?;
+        Ok::<_, std::num::ParseIntError>(vec![row; count])
+    })
+    .map_column("seen", |_| Ok::<_, CsvError>("yes".into()));
+
+    let words: Vec<(String, String)> = csv
+        .map(Result::unwrap)
+        .map(|row| (row["word"].clone(), row["seen"].clone()))
+        .collect();
+    let expected = [("a", "yes"), ("a", "yes"), ("c", "yes")];
+    assert_eq!(words.len(), expected.len());
+    for ((word, seen), (expected_word, expected_seen)) in words.iter().zip(&expected) {
+        assert_eq!(
+            (word.as_str(), seen.as_str()),
+            (*expected_word, *expected_seen)
+        );
+    }
+}
+
+#[test]
+fn flat_map_must_keep_columns_test() {
+    let mut csv = csv(DATA);
+    csv.flat_map_rows(|row| {
+        let first = row["first"].clone();
+        Ok::<_, CsvError>(vec![vec![("first", first)].into_iter().collect()])
+    });
+    match csv.next() {
+        Some(Err(CsvError::StageError(error))) => {
+            assert_eq!(error.stage, 0);
+            assert_eq!(error.kind, StageKind::FlatMap);
+        }
+        other => panic!("expected a stage error, got {:?}", other),
+    }
+}
+
+#[test]
+fn inspect_sees_rows_in_order_test() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let before = Arc::clone(&seen);
+    let after = Arc::clone(&seen);
+
+    let mut csv = csv(DATA);
+    csv.inspect_rows(move |row| {
+        before
+            .lock()
+            .unwrap()
+            .push(format!("before {}", row["first"]))
+    })
+    .filter_rows(|row| Ok::<_, CsvError>(row["first"] == "Alan"))
+    .inspect_rows(move |row| {
+        after
+            .lock()
+            .unwrap()
+            .push(format!("after {}", row["first"]))
+    });
+    assert_eq!(csv.count(), 1);
+    assert_eq!(
+        *seen.lock().unwrap(),
+        &["before Ada", "before Alan", "after Alan", "before Bob"]
+    );
+}
+
+#[test]
+fn stage_error_test() {
+    let mut csv = csv(DATA);
+    csv.inspect_rows(|_| {}).map_column("age", |row| {
+        row["age"].parse::<u32>().map(|age| age.to_string())
+    });
+
+    assert!(csv.next().unwrap().is_ok());
+    assert!(csv.next().unwrap().is_ok());
+    match csv.next() {
+        Some(Err(CsvError::StageError(error))) => {
+            assert_eq!(error.stage, 1);
+            assert_eq!(error.kind, StageKind::Map);
+            assert_eq!(error.position.unwrap().line, 4);
+            assert!(error
+                .source
+                .downcast_ref::<std::num::ParseIntError>()
+                .is_some());
+            assert_eq!(
+                CsvError::StageError(error).to_string(),
+                "Pipeline error: stage 1(map) failed on record 4 starting at line 4: \
+                 invalid digit found in string"
+            );
+        }
+        other => panic!("expected a stage error, got {:?}", other),
+    }
+    assert!(csv.next().is_none());
+}
@@ -0,0 +1,160 @@
+use solution::*;
+use std::io::BufReader;
+
+const DATA: &str = "city,population,code
+Sofia,1300000,SOF
+Plovdiv,340000,
+Sofia,1300000,SOF
+Varna,330000,VAR
+Sofia,,SOF
+";
+
+fn csv(data: &str) -> Csv<BufReader<&[u8]>> {
+    Csv::new(BufReader::new(data.as_bytes())).unwrap()
+}
+
+#[test]
+fn profile_test() {
+    let profile = csv(DATA).profile().unwrap();
+    assert_eq!(profile.rows, 5);
+    assert_eq!(profile.columns.len(), 3);
+
+    let city = profile.column("city").unwrap();
+    assert_eq!((city.count, city.empty, city.distinct), (5, 0, 3));
+    assert_eq!(city.min.as_deref(), Some("Plovdiv"));
+    assert_eq!(city.max.as_deref(), Some("Varna"));
+    assert_eq!(city.mean, None);
+    assert_eq!(city.stddev, None);
+    assert_eq!(
+        city.top,
+        &[
+            ("Sofia".to_string(), 3),
+            ("Plovdiv".to_string(), 1),
+            ("Varna".to_string(), 1)
+        ]
+    );
+    assert_eq!(
+        city.lengths,
+        &[LengthBucket {
+            min: 4,
+            max: 7,
+            count: 5
+        }]
+    );
+
+    let population = profile.column("population").unwrap();
+    assert_eq!((population.empty, population.distinct), (1, 3));
+    // compared as numbers, not as text
+    assert_eq!(population.min.as_deref(), Some("330000"));
+    assert_eq!(population.max.as_deref(), Some("1300000"));
+    assert_eq!(population.mean, Some(817500.0));
+    let stddev = population.stddev.unwrap();
+    assert!((stddev - 482512.953).abs() < 0.001, "{}", stddev);
+
+    let code = profile.column("code").unwrap();
+    assert_eq!(
+        code.lengths,
+        &[
+            LengthBucket {
+                min: 0,
+                max: 0,
+                count: 1
+            },
+            LengthBucket {
+                min: 2,
+                max: 3,
+                count: 4
+            },
+        ]
+    );
+    assert!(profile.column("missing").is_none());
+}
+
+#[test]
+fn pipeline_test() {
+    let mut csv = csv(DATA);
+    csv.apply_filter("city != \"Sofia\"").unwrap();
+    csv.map_column("name_length", |row: &Row| {
+        Ok::<_, BoxError>(row["city"].len().to_string())
+    });
+    let profile = csv.profile_top(1).unwrap();
+    assert_eq!(profile.rows, 2);
+    let names: Vec<&str> = profile.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, &["city", "population", "code", "name_length"]);
+    assert_eq!(profile.columns[0].top, &[("Plovdiv".to_string(), 1)]);
+    assert_eq!(profile.columns[3].mean, Some(6.0));
+}
+
+#[test]
+fn distinct_estimate_test() {
+    let mut data = String::from("n\n");
+    for i in 0..20000 {
+        data.push_str(&format!("{}\n", i % 10000));
+    }
+    let profile = csv(&data).profile().unwrap();
+    let n = &profile.columns[0];
+    assert_eq!(n.count, 20000);
+    let error = (n.distinct as f64 - 10000.0).abs() / 10000.0;
+    assert!(error < 0.05, "estimated {} distinct values", n.distinct);
+}
+
+#[test]
+fn top_values_test() {
+    // far more distinct values than counters, the frequent one still comes first
+    let mut data = String::from("v\n");
+    for i in 0..5000 {
+        data.push_str(&format!("{}\n", i));
+        if i % 3 == 0 {
+            data.push_str("frequent\n");
+        }
+    }
+    let profile = csv(&data).profile_top(2).unwrap();
+    let top = &profile.columns[0].top;
+    assert_eq!(top.len(), 2);
+    assert_eq!(top[0].0, "frequent");
+    assert!(top[0].1 >= 1667);
+}
+
+#[test]
+fn empty_test() {
+    let profile = csv("a,b\n").profile().unwrap();
+    assert_eq!(profile.rows, 0);
+    let a = &profile.columns[0];
+    assert_eq!((a.count, a.empty, a.distinct), (0, 0, 0));
+    assert_eq!((a.min.clone(), a.mean), (None, None));
+    assert!(a.top.is_empty() && a.lengths.is_empty());
+}
+
+#[test]
+fn table_test() {
+    let table = csv(DATA).profile().unwrap().to_string();
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines[0], "5 rows");
+    assert!(
+        lines[1].starts_with("column     | count | empty | distinct |"),
+        "{}",
+        table
+    );
+    assert!(lines[2].starts_with("-----------+-------+"), "{}", table);
+    assert!(
+        lines[3].contains("Sofia (3), Plovdiv (1), Varna (1)"),
+        "{}",
+        table
+    );
+    assert!(lines[4].contains("817500 | 482512.953"), "{}", table);
+    assert!(lines[5].ends_with("0:1 2-3:4"), "{}", table);
+    assert_eq!(lines.len(), 6);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn json_test() {
+    let json = csv(DATA).profile().unwrap().to_json();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["rows"], 5);
+    assert_eq!(value["columns"][0]["name"], "city");
+    assert_eq!(value["columns"][0]["top"][0][0], "Sofia");
+    assert_eq!(value["columns"][0]["mean"], serde_json::Value::Null);
+    assert_eq!(value["columns"][1]["mean"], 817500.0);
+    assert_eq!(value["columns"][2]["lengths"][1]["max"], 3);
+}
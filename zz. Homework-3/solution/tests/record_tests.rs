@@ -0,0 +1,133 @@
+use solution::*;
+use std::io::BufReader;
+
+const DATA: &str =
+    "name,age,notes\nAda,36,\"said \"\"hi\"\"\"\nBob, 20 ,\"two\nlines\"\n\nCarl,41,\n";
+
+#[test]
+fn read_record_test() {
+    let mut csv = Csv::new(BufReader::new(DATA.as_bytes())).unwrap();
+    let mut record = StringRecord::new();
+
+    assert!(csv.read_record(&mut record).unwrap());
+    assert_eq!(record.len(), 3);
+    assert_eq!(&record[0], "Ada");
+    assert_eq!(&record[2], r#"said "hi""#);
+    assert_eq!(record.get(3), None);
+
+    assert!(csv.read_record(&mut record).unwrap());
+    assert_eq!(
+        record.iter().collect::<Vec<_>>(),
+        &["Bob", "20", "two\nlines"]
+    );
+    assert_eq!(record.position().unwrap().line, 3);
+
+    assert!(csv.read_record(&mut record).unwrap());
+    assert_eq!(record.iter().collect::<Vec<_>>(), &["Carl", "41", ""]);
+    assert_eq!(
+        record.position(),
+        Some(Position {
+            record: 4,
+            line: 6,
+            byte: 58,
+        })
+    );
+
+    assert!(!csv.read_record(&mut record).unwrap());
+    assert!(record.is_empty());
+}
+
+#[test]
+fn buffers_are_reused_test() {
+    let data = "a,b\n".to_string() + &"xxxxxxxxxx,yyyyyyyyyy\n".repeat(100);
+    let mut csv = Csv::new(BufReader::new(data.as_bytes())).unwrap();
+    let mut record = StringRecord::with_capacity(64, 4);
+    let buffer = record.as_bytes().as_ptr();
+
+    let mut count = 0;
+    while csv.read_record(&mut record).unwrap() {
+        assert_eq!(record.as_bytes().as_ptr(), buffer);
+        count += 1;
+    }
+    assert_eq!(count, 100);
+}
+
+#[test]
+fn column_handle_test() {
+    let mut csv = Csv::new(BufReader::new(DATA.as_bytes())).unwrap();
+    let age = csv.column("age").unwrap();
+    assert_eq!(age.index(), 1);
+    assert!(csv.column("height").is_none());
+
+    let mut record = StringRecord::new();
+    let mut ages = Vec::new();
+    while csv.read_record(&mut record).unwrap() {
+        ages.push(record[age].parse::<u32>().unwrap());
+    }
+    assert_eq!(ages, &[36, 20, 41]);
+}
+
+#[test]
+fn byte_record_test() {
+    let mut csv = Csv::new(BufReader::new(DATA.as_bytes())).unwrap();
+    let name = csv.column("name").unwrap();
+    let mut record = ByteRecord::new();
+
+    let mut names = Vec::new();
+    while csv.read_byte_record(&mut record).unwrap() {
+        names.push(record[name].to_vec());
+    }
+    assert_eq!(names, &[&b"Ada"[..], b"Bob", b"Carl"]);
+}
+
+#[test]
+fn without_header_test() {
+    let data = "1,2\n3,4\n".as_bytes();
+    let mut csv = CsvBuilder::new()
+        .has_header(false)
+        .from_reader(BufReader::new(data))
+        .unwrap();
+    let mut record = StringRecord::new();
+
+    assert!(csv.read_record(&mut record).unwrap());
+    assert_eq!(record.iter().collect::<Vec<_>>(), &["1", "2"]);
+    assert!(csv.read_record(&mut record).unwrap());
+    assert_eq!(record.iter().collect::<Vec<_>>(), &["3", "4"]);
+    assert!(!csv.read_record(&mut record).unwrap());
+}
+
+#[test]
+fn wrong_width_test() {
+    let data = "a,b\n1,2\n3\n5,6\n".as_bytes();
+    let mut csv = Csv::new(BufReader::new(data)).unwrap();
+    let mut record = StringRecord::new();
+
+    assert!(csv.read_record(&mut record).unwrap());
+    match csv.read_record(&mut record) {
+        Err(CsvError::ParseError(details)) => {
+            assert_eq!(details.position.unwrap().line, 3);
+            assert_eq!(details.column_name.as_deref(), Some("b"));
+        }
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+    assert!(csv.read_record(&mut record).unwrap());
+    assert_eq!(&record[1], "6");
+}
+
+#[test]
+fn same_as_iterator_test() {
+    let rows: Vec<_> = Csv::new(BufReader::new(DATA.as_bytes()))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+
+    let mut csv = Csv::new(BufReader::new(DATA.as_bytes())).unwrap();
+    let columns = csv.columns().to_vec();
+    let mut record = StringRecord::new();
+    for row in rows {
+        assert!(csv.read_record(&mut record).unwrap());
+        for (i, column) in columns.iter().enumerate() {
+            assert_eq!(row[column], &record[i]);
+        }
+    }
+}
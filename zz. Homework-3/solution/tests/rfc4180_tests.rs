@@ -0,0 +1,120 @@
+use solution::*;
+use std::io::BufReader;
+
+fn fields(input: &str, mode: ParseMode) -> Vec<String> {
+    parse_record(input, mode).unwrap()
+}
+
+#[test]
+fn unquoted_and_empty_fields_test() {
+    for &mode in [ParseMode::Strict, ParseMode::Lenient].iter() {
+        assert_eq!(fields("a,b,c", mode), &["a", "b", "c"]);
+        assert_eq!(fields("a,,c", mode), &["a", "", "c"]);
+        assert_eq!(fields(",,", mode), &["", "", ""]);
+        assert_eq!(fields("a,", mode), &["a", ""]);
+        assert_eq!(fields("", mode), &[""]);
+        assert_eq!(fields(r#""",x"#, mode), &["", "x"]);
+    }
+}
+
+#[test]
+fn escaped_quotes_test() {
+    for &mode in [ParseMode::Strict, ParseMode::Lenient].iter() {
+        assert_eq!(fields(r#""say ""hi""",2"#, mode), &[r#"say "hi""#, "2"]);
+        assert_eq!(fields(r#""""""#, mode), &[r#"""#]);
+        assert_eq!(
+            fields(r#""Ada, Countess of Lovelace","a ""quoted"", field""#, mode),
+            &["Ada, Countess of Lovelace", r#"a "quoted", field"#]
+        );
+        assert_eq!(fields("\"two\nlines\",x", mode), &["two\nlines", "x"]);
+    }
+}
+
+#[test]
+fn whitespace_test() {
+    assert_eq!(
+        fields(r#" a , "b" ,c "#, ParseMode::Lenient),
+        &["a", "b", "c"]
+    );
+    assert_eq!(
+        fields(r#" a b ,"  b  ""#, ParseMode::Lenient),
+        &["a b", "  b  "]
+    );
+    assert_eq!(fields(" a , b", ParseMode::Strict), &[" a ", " b"]);
+}
+
+#[test]
+fn strict_rejects_malformed_test() {
+    assert!(parse_record(r#"ab"c,d"#, ParseMode::Strict).is_err());
+    assert!(parse_record(r#""ab" ,d"#, ParseMode::Strict).is_err());
+    assert!(parse_record(r#""ab"c,d"#, ParseMode::Strict).is_err());
+    assert!(parse_record(r#""ab,d"#, ParseMode::Strict).is_err());
+}
+
+#[test]
+fn lenient_accepts_stray_quotes_test() {
+    assert_eq!(fields(r#"5'10",d"#, ParseMode::Lenient), &[r#"5'10""#, "d"]);
+    assert!(parse_record(r#""ab"c,d"#, ParseMode::Lenient).is_err());
+    assert!(parse_record(r#""ab,d"#, ParseMode::Lenient).is_err());
+}
+
+#[test]
+fn strip_line_end_test() {
+    let data = "name,age\r\n\"Ada \"\"The Countess\"\"\",36\r\nBob,\r\n".as_bytes();
+    let csv = Csv::with_mode(BufReader::new(data), ParseMode::Strict).unwrap();
+    assert_eq!(csv.columns(), &["name", "age"]);
+
+    let rows: Vec<_> = csv.map(Result::unwrap).collect();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["name"], r#"Ada "The Countess""#);
+    assert_eq!(rows[0]["age"], "36");
+    assert_eq!(rows[1]["name"], "Bob");
+    assert_eq!(rows[1]["age"], "");
+}
+
+#[test]
+fn excel_export_test() {
+    let data = r#"id,"full name",notes
+1,Ada Lovelace,"said ""hello, world"""
+
+2,,
+"#
+    .as_bytes();
+    let csv = Csv::new(BufReader::new(data)).unwrap();
+    assert_eq!(csv.columns(), &["id", "full name", "notes"]);
+
+    let rows: Vec<_> = csv.map(Result::unwrap).collect();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["notes"], r#"said "hello, world""#);
+    assert_eq!(rows[1]["full name"], "");
+    assert_eq!(rows[1]["notes"], "");
+}
+
+#[test]
+fn strict_blank_line_test() {
+    let data = "a,b\n1,2\n\n3,4\n".as_bytes();
+    let mut csv = Csv::with_mode(BufReader::new(data), ParseMode::Strict).unwrap();
+    assert!(csv.next().unwrap().is_ok());
+    assert!(csv.next().unwrap().is_err());
+}
+
+#[test]
+fn write_escapes_quotes_test() {
+    let data = "name,quote\nAda,\"I said \"\"hi\"\"\"\n".as_bytes();
+    let csv = Csv::new(BufReader::new(data)).unwrap();
+    let mut output = Vec::new();
+    csv.write_to(&mut output).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "name, quote\n\"Ada\", \"I said \"\"hi\"\"\"\n"
+    );
+
+    // what was written can be read back
+    let data = "name, quote\n\"Ada\", \"I said \"\"hi\"\"\"\n".as_bytes();
+    let row = Csv::new(BufReader::new(data))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(row["quote"], r#"I said "hi""#);
+}
@@ -0,0 +1,75 @@
+use solution::*;
+use std::io::BufReader;
+
+fn rows(data: &str) -> Vec<Row> {
+    Csv::new(BufReader::new(data.as_bytes()))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+}
+
+#[test]
+fn keeps_header_order_test() {
+    let rows = rows("zeta,alpha,mid\n1,2,3\n4,5,6\n");
+    let pairs: Vec<_> = rows[0].iter().collect();
+    assert_eq!(pairs, &[("zeta", "1"), ("alpha", "2"), ("mid", "3")]);
+    assert_eq!(rows[1].columns(), &["zeta", "alpha", "mid"]);
+    assert_eq!(rows[1].values(), &["4", "5", "6"]);
+    assert_eq!(rows[1].len(), 3);
+}
+
+#[test]
+fn index_test() {
+    let rows = rows("name,age\nAda,36\n");
+    let row = &rows[0];
+    assert_eq!(row[0], "Ada");
+    assert_eq!(row[1], "36");
+    assert_eq!(row["age"], "36");
+    assert_eq!(row[&String::from("name")], "Ada");
+    assert_eq!(row.get("name").map(String::as_str), Some("Ada"));
+    assert_eq!(row.get("height"), None);
+    assert_eq!(row.position("age"), Some(1));
+    assert!(!row.contains_column("height"));
+}
+
+#[test]
+#[should_panic]
+fn missing_column_test() {
+    let rows = rows("name,age\nAda,36\n");
+    let _ = &rows[0]["height"];
+}
+
+#[test]
+fn from_pairs_test() {
+    let mut row: Row = vec![("b", "2"), ("a", "1")].into_iter().collect();
+    assert_eq!(row.columns(), &["b", "a"]);
+    assert_eq!(row["a"], "1");
+
+    *row.get_mut("a").unwrap() = "10".into();
+    assert_eq!(row.into_values(), &["2", "10"]);
+}
+
+#[test]
+fn renamed_columns_test() {
+    let mut csv = Csv::new(BufReader::new("a,b\n1,2\n3,4\n".as_bytes())).unwrap();
+    assert_eq!(csv.next().unwrap().unwrap()["a"], "1");
+
+    csv.columns[0] = "first".into();
+    let row = csv.next().unwrap().unwrap();
+    assert_eq!(row["first"], "3");
+    assert!(!row.contains_column("a"));
+}
+
+#[test]
+fn write_keeps_order_test() {
+    let data = "z,y,x\n1,2,3\n".as_bytes();
+    let mut output = Vec::new();
+    CsvBuilder::new()
+        .trim(false)
+        .quote_style(QuoteStyle::Necessary)
+        .from_reader(BufReader::new(data))
+        .unwrap()
+        .write_to(&mut output)
+        .unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "z,y,x\n1,2,3\n");
+}
@@ -0,0 +1,147 @@
+use solution::*;
+use std::io::BufReader;
+
+const DATA: &str = "id,price,active,since,name,note
+1,9.5,true,2020-01-31,Ada,
+2,10,FALSE,2019-12-01,Bob,x
+3,-1.25,false,2021-06-15 08:00,Cid,
+";
+
+fn csv(data: &'static str) -> Csv<BufReader<&'static [u8]>> {
+    Csv::new(BufReader::new(data.as_bytes())).unwrap()
+}
+
+fn column(
+    name: &str,
+    column_type: ColumnType,
+    nullable: bool,
+    min: &str,
+    max: &str,
+) -> ColumnSchema {
+    ColumnSchema {
+        name: name.into(),
+        column_type,
+        nullable,
+        min: Some(min.into()),
+        max: Some(max.into()),
+    }
+}
+
+#[test]
+fn infer_test() {
+    let mut csv = csv(DATA);
+    let schema = csv.infer_schema(10).unwrap();
+    assert_eq!(
+        schema.columns,
+        &[
+            column("id", ColumnType::Integer, false, "1", "3"),
+            column("price", ColumnType::Float, false, "-1.25", "10"),
+            column("active", ColumnType::Bool, false, "FALSE", "true"),
+            column(
+                "since",
+                ColumnType::Date,
+                false,
+                "2019-12-01",
+                "2021-06-15 08:00"
+            ),
+            column("name", ColumnType::String, false, "Ada", "Cid"),
+            column("note", ColumnType::String, true, "x", "x"),
+        ]
+    );
+
+    // the sampled rows are still given out
+    let ids: Vec<String> = csv.map(|row| row.unwrap()["id"].clone()).collect();
+    assert_eq!(ids, &["1", "2", "3"]);
+}
+
+#[test]
+fn sample_size_test() {
+    let mut csv = csv("n\n1\n2\nthree\n");
+    let schema = csv.infer_schema(2).unwrap();
+    assert_eq!(schema.column("n").unwrap().column_type, ColumnType::Integer);
+    assert_eq!(csv.count(), 3);
+
+    let mut csv = self::csv("n,m\n,1\n");
+    let schema = csv.infer_schema(10).unwrap();
+    let n = schema.column("n").unwrap();
+    assert_eq!(
+        (n.column_type, n.nullable, n.min.clone()),
+        (ColumnType::String, true, None)
+    );
+    let empty = self::csv("n\n").infer_schema(10).unwrap();
+    assert!(empty.column("n").unwrap().nullable);
+}
+
+#[test]
+fn sample_error_test() {
+    let mut csv = csv("n,m\n1,a\n2,b\n3\n4,d\n");
+    assert!(matches!(csv.infer_schema(5), Err(CsvError::ParseError(_))));
+    // the rows sampled before the error are not lost
+    let rows: Vec<String> = csv.map(|row| row.unwrap()["n"].clone()).collect();
+    assert_eq!(rows, &["1", "2", "4"]);
+}
+
+#[test]
+fn validate_test() {
+    let mut csv = csv("n,name\n1,Ada\n2,Bob\nthree,Cid\n,Dan\n");
+    let schema = csv.infer_schema(2).unwrap();
+    csv.set_schema(schema).unwrap();
+
+    assert_eq!(csv.next().unwrap().unwrap()["name"], "Ada");
+    assert_eq!(csv.next().unwrap().unwrap()["name"], "Bob");
+    match csv.next() {
+        Some(Err(CsvError::InvalidRow(details))) => {
+            assert_eq!(details.column, Some(0));
+            assert_eq!(details.column_name.as_deref(), Some("n"));
+            assert_eq!(details.position.unwrap().line, 4);
+            assert_eq!(
+                details.to_string(),
+                "Expected an integer, got \"three\" \
+                 (record 4 starting at line 4, byte offset 19, column 0 \"n\") near \"three\""
+            );
+        }
+        other => panic!("expected an invalid row, got {:?}", other),
+    }
+    match csv.next() {
+        Some(Err(CsvError::InvalidRow(details))) => {
+            assert_eq!(
+                details.message,
+                "Expected an integer, but the value is empty"
+            );
+        }
+        other => panic!("expected an invalid row, got {:?}", other),
+    }
+    assert!(csv.next().is_none());
+}
+
+#[test]
+fn set_schema_test() {
+    let mut csv = csv(DATA);
+    let mut schema = Schema::default();
+    schema.columns.push(ColumnSchema {
+        name: "height".into(),
+        column_type: ColumnType::Float,
+        nullable: true,
+        min: None,
+        max: None,
+    });
+    assert!(matches!(
+        csv.set_schema(schema),
+        Err(CsvError::InvalidColumn(name)) if name == "height"
+    ));
+    assert!(csv.schema().is_none());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn json_test() {
+    let schema = csv(DATA).infer_schema(10).unwrap();
+    let json = schema.to_json().unwrap();
+    assert!(json.contains("\"type\": \"integer\""), "{}", json);
+    assert_eq!(Schema::from_json(&json).unwrap(), schema);
+
+    assert!(matches!(
+        Schema::from_json("{\"columns\": [{\"name\": \"a\"}]}"),
+        Err(CsvError::InvalidSchema(_))
+    ));
+}
@@ -0,0 +1,221 @@
+#![cfg(feature = "serde")]
+
+use serde::{Deserialize, Serialize};
+use solution::*;
+use std::io::BufReader;
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Person {
+    name: String,
+    age: u32,
+    height: Option<f64>,
+    student: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+enum Level {
+    Low,
+    High,
+}
+
+const PEOPLE: &str = "name,age,height,student,city
+Ada,36,1.65,false,London
+Bob,20,,1,Sofia
+";
+
+fn csv(data: &str) -> Csv<BufReader<&[u8]>> {
+    Csv::new(BufReader::new(data.as_bytes())).unwrap()
+}
+
+#[test]
+fn struct_test() {
+    let people: Vec<Person> = csv(PEOPLE).deserialize().map(Result::unwrap).collect();
+    assert_eq!(
+        people,
+        &[
+            Person {
+                name: "Ada".into(),
+                age: 36,
+                height: Some(1.65),
+                student: false,
+            },
+            Person {
+                name: "Bob".into(),
+                age: 20,
+                height: None,
+                student: true,
+            },
+        ]
+    );
+}
+
+#[test]
+fn columns_in_any_order_test() {
+    let data = "student,height,age,name\ntrue,1.8,41,Carl\n";
+    let person: Person = csv(data).deserialize().next().unwrap().unwrap();
+    assert_eq!(person.name, "Carl");
+    assert_eq!(person.age, 41);
+    assert_eq!(person.height, Some(1.8));
+}
+
+#[test]
+fn tuple_and_enum_test() {
+    let data = "name,level,score\nAda,High,7\nBob,Low,-2\n";
+    let rows: Vec<(String, Level, i8)> = csv(data).deserialize().map(Result::unwrap).collect();
+    assert_eq!(
+        rows,
+        &[
+            ("Ada".into(), Level::High, 7),
+            ("Bob".into(), Level::Low, -2)
+        ]
+    );
+
+    let values: Vec<Vec<String>> = csv(data).deserialize().map(Result::unwrap).collect();
+    assert_eq!(values[1], &["Bob", "Low", "-2"]);
+}
+
+#[test]
+fn conversion_error_test() {
+    let data = "name,age,height,student\nAda,36,,false\nBob,twenty,,true\n";
+    let mut csv = csv(data);
+    let mut people = csv.deserialize::<Person>();
+    assert!(people.next().unwrap().is_ok());
+
+    match people.next() {
+        Some(Err(CsvError::InvalidRow(details))) => {
+            assert_eq!(details.column, Some(1));
+            assert_eq!(details.column_name.as_deref(), Some("age"));
+            assert_eq!(details.excerpt.as_deref(), Some("twenty"));
+            assert_eq!(details.position.unwrap().line, 3);
+            assert!(details.message.contains("u32"), "{}", details);
+        }
+        other => panic!("expected an invalid row, got {:?}", other),
+    }
+    assert!(people.next().is_none());
+}
+
+#[test]
+fn missing_field_test() {
+    let data = "name,age\nAda,36\n";
+    match csv(data).deserialize::<Person>().next() {
+        Some(Err(CsvError::InvalidRow(details))) => {
+            // a missing `Option` is None, but `student` is required
+            assert!(details.message.contains("student"), "{}", details);
+            assert_eq!(details.column, None);
+        }
+        other => panic!("expected an invalid row, got {:?}", other),
+    }
+}
+
+#[test]
+fn selection_test() {
+    let mut csv = csv(PEOPLE);
+    csv.apply_selection(|row| Ok(row["city"] == "Sofia"));
+    let people: Vec<Person> = csv.deserialize().map(Result::unwrap).collect();
+    assert_eq!(people.len(), 1);
+    assert_eq!(people[0].name, "Bob");
+}
+
+#[test]
+fn record_test() {
+    let mut csv = csv(PEOPLE);
+    let mut record = StringRecord::new();
+    assert!(csv.read_record(&mut record).unwrap());
+    let person: Person = record.deserialize(csv.columns()).unwrap();
+    assert_eq!(person.age, 36);
+}
+
+fn serialized<F: FnOnce(&mut CsvWriter<Vec<u8>>)>(write: F) -> String {
+    let mut writer = CsvWriter::new(Vec::new());
+    write(&mut writer);
+    String::from_utf8(writer.into_inner().unwrap()).unwrap()
+}
+
+#[test]
+fn serialize_struct_test() {
+    let people: Vec<Person> = csv(PEOPLE).deserialize().map(Result::unwrap).collect();
+    let output = serialized(|writer| {
+        for person in &people {
+            writer.serialize(person).unwrap();
+        }
+    });
+    assert_eq!(
+        output,
+        "name, age, height, student\nAda, 36, 1.65, false\nBob, 20, \"\", true\n"
+    );
+
+    // and back
+    let again: Vec<Person> = csv(&output).deserialize().map(Result::unwrap).collect();
+    assert_eq!(again, people);
+}
+
+#[test]
+fn serialize_tuple_test() {
+    let output = serialized(|writer| {
+        writer.write_header(["name", "level"]).unwrap();
+        writer.serialize(&("Ada, Countess", Level::High)).unwrap();
+        writer.serialize(&vec!["Bob", "Low"]).unwrap();
+    });
+    assert_eq!(output, "name, level\n\"Ada, Countess\", High\nBob, Low\n");
+}
+
+#[test]
+fn serialize_error_test() {
+    #[derive(Serialize)]
+    struct Nested {
+        id: u32,
+        tags: Vec<String>,
+    }
+
+    let mut writer = CsvWriter::new(Vec::new());
+    let nested = Nested {
+        id: 1,
+        tags: vec!["a".into()],
+    };
+    match writer.serialize(&nested) {
+        Err(CsvError::InvalidRow(details)) => {
+            assert_eq!(details.column, Some(1));
+            assert_eq!(details.column_name.as_deref(), Some("tags"));
+        }
+        other => panic!("expected an invalid row, got {:?}", other),
+    }
+    assert!(writer.into_inner().unwrap().is_empty());
+}
+
+#[test]
+fn projected_columns_test() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Place {
+        who: String,
+        city: String,
+    }
+
+    let mut selected = csv(PEOPLE);
+    selected.rename_column("name", "who").unwrap();
+    selected.select_columns(&["city", "who"]).unwrap();
+    let places: Vec<(String, String)> = selected.deserialize().map(Result::unwrap).collect();
+    assert_eq!(places[0], ("London".to_string(), "Ada".to_string()));
+
+    let mut renamed = csv(PEOPLE);
+    renamed.rename_column("name", "who").unwrap();
+    let place: Place = renamed.deserialize().next().unwrap().unwrap();
+    assert_eq!(place.who, "Ada");
+}
+
+#[test]
+fn pipeline_test() {
+    #[derive(Debug, Deserialize)]
+    struct Greeting {
+        name: String,
+        greeting: String,
+    }
+
+    let mut csv = csv(PEOPLE);
+    csv.select_columns(&["name"]).unwrap();
+    csv.map_column("greeting", |row| {
+        Ok::<_, CsvError>(format!("Hello, {}", row["name"]))
+    });
+    let greetings: Vec<Greeting> = csv.deserialize().map(Result::unwrap).collect();
+    assert_eq!(greetings[0].name, "Ada");
+    assert_eq!(greetings[0].greeting, "Hello, Ada");
+}
@@ -0,0 +1,73 @@
+use solution::*;
+use std::io::{BufReader, Read};
+
+fn sniff(data: &str) -> Sniffed<BufReader<&[u8]>> {
+    Csv::sniff(BufReader::new(data.as_bytes()), DEFAULT_SNIFF_SIZE).unwrap()
+}
+
+#[test]
+fn comma_with_header_test() {
+    let sniffed = sniff("name,age,city\nAda,36,London\nBob,20,Sofia\nCarl,41,Paris\n");
+    assert_eq!(sniffed.dialect.delimiter, ',');
+    assert!(sniffed.dialect.has_header);
+    assert_eq!(sniffed.dialect.line_terminator, LineTerminator::Lf);
+    assert!(sniffed.confidence > 0.5);
+
+    let rows: Vec<_> = sniffed.into_csv().unwrap().map(Result::unwrap).collect();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[2]["city"], "Paris");
+}
+
+#[test]
+fn semicolon_crlf_test() {
+    let sniffed = sniff("name;price\r\nCafé;3,50\r\nTea;2,00\r\nCake;4,75\r\n");
+    assert_eq!(sniffed.dialect.delimiter, ';');
+    assert_eq!(sniffed.dialect.line_terminator, LineTerminator::CrLf);
+    assert!(sniffed.dialect.has_header);
+}
+
+#[test]
+fn tab_without_header_test() {
+    let sniffed = sniff("1\t2.5\tx\n2\t3.5\ty\n3\t4.5\tz\n");
+    assert_eq!(sniffed.dialect.delimiter, '\t');
+    assert!(!sniffed.dialect.has_header);
+
+    let csv = sniffed.into_csv().unwrap();
+    assert_eq!(csv.columns(), &["0", "1", "2"]);
+    assert_eq!(csv.count(), 3);
+}
+
+#[test]
+fn quoting_test() {
+    let sniffed = sniff("'a'|'b'\n'x|y'|'1'\n'z'|'2'\n");
+    assert_eq!(sniffed.dialect.delimiter, '|');
+    assert_eq!(sniffed.dialect.quote, '\'');
+    assert_eq!(sniffed.dialect.quote_style, QuoteStyle::Always);
+
+    let sniffed = sniff("name,notes\nAda,\"a, b\"\nBob,plain\n");
+    assert_eq!(sniffed.dialect.delimiter, ',');
+    assert_eq!(sniffed.dialect.quote, '"');
+    assert_eq!(sniffed.dialect.quote_style, QuoteStyle::Necessary);
+}
+
+#[test]
+fn sample_is_not_consumed_test() {
+    let data = "a;b\n".to_string() + &"1;2\n".repeat(1000);
+    let sniffed = Csv::sniff(BufReader::new(data.as_bytes()), 100).unwrap();
+    assert_eq!(sniffed.dialect.delimiter, ';');
+
+    let mut everything = String::new();
+    sniffed
+        .into_reader()
+        .read_to_string(&mut everything)
+        .unwrap();
+    assert_eq!(everything, data);
+}
+
+#[test]
+fn unknown_format_test() {
+    let sniffed = sniff("just some words\nwithout any structure\n");
+    assert_eq!(sniffed.dialect.delimiter, ',');
+    assert_eq!(sniffed.dialect.quote, '"');
+    assert!(sniffed.confidence < 0.1);
+}
@@ -0,0 +1,290 @@
+use solution::*;
+use std::io::BufReader;
+
+const DATA: &str = "name,country,age,born
+Ada,UK,36,1815-12-10
+Bob,BG,20,2000-01-01
+Eve,UK,,1990-05-05 10:30
+Dan,BG,9,2011-03-03
+Cid,UK,36,1984-07-07
+";
+
+fn csv(data: &'static str) -> Csv<BufReader<&'static [u8]>> {
+    Csv::new(BufReader::new(data.as_bytes())).unwrap()
+}
+
+fn names(sorted: Sorted) -> Vec<String> {
+    sorted.map(|row| row.unwrap()["name"].clone()).collect()
+}
+
+fn sorted(keys: &[&str]) -> Vec<String> {
+    names(csv(DATA).sort_by(keys).unwrap().run().unwrap())
+}
+
+#[test]
+fn parse_key_test() {
+    assert_eq!(
+        SortKey::parse("-age:number"),
+        SortKey {
+            column: "age".into(),
+            descending: true,
+            sort_type: SortType::Number,
+        }
+    );
+    assert_eq!(SortKey::parse("a:b").column, "a:b");
+    assert_eq!(SortKey::parse("born:date").sort_type, SortType::Date);
+}
+
+#[test]
+fn typed_sort_test() {
+    // as text "9" is after "36", the empty age of Eve is first
+    assert_eq!(
+        sorted(&["age:string"]),
+        &["Eve", "Bob", "Ada", "Cid", "Dan"]
+    );
+    assert_eq!(
+        sorted(&["age:number"]),
+        &["Eve", "Dan", "Bob", "Ada", "Cid"]
+    );
+    assert_eq!(
+        sorted(&["-age:number"]),
+        &["Ada", "Cid", "Bob", "Dan", "Eve"]
+    );
+    assert_eq!(sorted(&["born:date"]), &["Ada", "Cid", "Eve", "Bob", "Dan"]);
+}
+
+#[test]
+fn auto_sort_test() {
+    // numbers as numbers without a type
+    assert_eq!(sorted(&["-age"]), &["Ada", "Cid", "Bob", "Dan", "Eve"]);
+    assert_eq!(sorted(&["age"]), sorted(&["age:number"]));
+    assert_eq!(
+        sorted(&["country", "-age"]),
+        &["Bob", "Dan", "Ada", "Cid", "Eve"]
+    );
+
+    // the numbers come before the text
+    let data = "v\nb\n100\na\n9\n";
+    let values: Vec<String> = csv(data)
+        .sort_by(&["v"])
+        .unwrap()
+        .run()
+        .unwrap()
+        .map(|row| row.unwrap()["v"].clone())
+        .collect();
+    assert_eq!(values, &["9", "100", "a", "b"]);
+
+    // "NaN" and "inf" are text, not numbers
+    let data = "v\ninf\n2\nNaN\n-1.5e1\n";
+    let values: Vec<String> = csv(data)
+        .sort_by(&["v"])
+        .unwrap()
+        .run()
+        .unwrap()
+        .map(|row| row.unwrap()["v"].clone())
+        .collect();
+    assert_eq!(values, &["-1.5e1", "2", "NaN", "inf"]);
+}
+
+#[test]
+fn several_keys_test() {
+    assert_eq!(
+        sorted(&["country", "-age:number"]),
+        &["Bob", "Dan", "Ada", "Cid", "Eve"]
+    );
+    // stable: Ada and Cid have the same age and stay in input order
+    assert_eq!(sorted(&["-country"]), &["Ada", "Eve", "Cid", "Bob", "Dan"]);
+}
+
+#[test]
+fn external_sort_test() {
+    let mut data = String::from("id,group\n");
+    for i in 0..500 {
+        data.push_str(&format!("{},\"g, {}\"\n", i, (i * 7) % 13));
+    }
+    let data: &'static str = Box::leak(data.into_boxed_str());
+
+    let in_memory: Vec<Vec<String>> = csv(data)
+        .sort_by(&["group", "-id:number"])
+        .unwrap()
+        .run()
+        .unwrap()
+        .map(|row| row.unwrap().into_values())
+        .collect();
+
+    let dir = std::env::temp_dir().join(format!("sort-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let external = csv(data)
+        .sort_by(&["group", "-id:number"])
+        .unwrap()
+        .memory_budget(1000)
+        .temp_dir(&dir)
+        .run()
+        .unwrap();
+    assert!(std::fs::read_dir(&dir).unwrap().count() > 1);
+
+    let external: Vec<Vec<String>> = external.map(|row| row.unwrap().into_values()).collect();
+    assert_eq!(external.len(), 500);
+    assert_eq!(external, in_memory);
+    assert_eq!(external[0], &["494", "g, 0"]);
+
+    // the runs are removed once the rows are read
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+    std::fs::remove_dir(&dir).unwrap();
+}
+
+#[test]
+fn stable_external_sort_test() {
+    let mut data = String::from("key,order\n");
+    for i in 0..300 {
+        data.push_str(&format!("{},{}\n", i % 3, i));
+    }
+    let data: &'static str = Box::leak(data.into_boxed_str());
+    let rows: Vec<Row> = csv(data)
+        .sort_by(&["key"])
+        .unwrap()
+        .memory_budget(500)
+        .run()
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    for pair in rows.windows(2) {
+        if pair[0]["key"] == pair[1]["key"] {
+            let first: u32 = pair[0]["order"].parse().unwrap();
+            let second: u32 = pair[1]["order"].parse().unwrap();
+            assert!(first < second);
+        }
+    }
+}
+
+#[test]
+fn many_runs_test() {
+    let dir = std::env::temp_dir().join(format!("sort-runs-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut data = String::from("key,order\n");
+    for i in 0..1000 {
+        data.push_str(&format!("{},{}\n", (i * 7) % 10, i));
+    }
+    let data: &'static str = Box::leak(data.into_boxed_str());
+    // every row is a run of its own, they are merged a few at a time
+    let sorted = csv(data)
+        .sort_by(&["key:number"])
+        .unwrap()
+        .memory_budget(0)
+        .temp_dir(&dir)
+        .run()
+        .unwrap();
+    assert!(std::fs::read_dir(&dir).unwrap().count() < 20);
+
+    let rows: Vec<Vec<String>> = sorted.map(|row| row.unwrap().into_values()).collect();
+    let mut expected: Vec<Vec<String>> = csv(data).map(|row| row.unwrap().into_values()).collect();
+    expected.sort_by_key(|values| values[0].parse::<u32>().unwrap());
+    assert_eq!(rows, expected);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+    std::fs::remove_dir(&dir).unwrap();
+}
+
+#[test]
+fn key_memory_test() {
+    let dir = std::env::temp_dir().join(format!("sort-key-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let data = format!("text\n{}\n", "x".repeat(1000));
+    let data: &'static str = Box::leak(data.into_boxed_str());
+    // the value fits in the budget, but not with its copy in the key
+    let sorted = csv(data)
+        .sort_by(&["text"])
+        .unwrap()
+        .memory_budget(1500)
+        .temp_dir(&dir)
+        .run()
+        .unwrap();
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    assert_eq!(sorted.count(), 1);
+    std::fs::remove_dir(&dir).unwrap();
+}
+
+#[test]
+fn long_rows_in_runs_test() {
+    let long = "x".repeat(2 * 1024 * 1024);
+    let data = format!("text\nb{}\na{}\n", long, long);
+    let sorted: Vec<String> = CsvBuilder::new()
+        .max_record_size(usize::MAX)
+        .from_reader(BufReader::new(data.as_bytes()))
+        .unwrap()
+        .sort_by(&["text"])
+        .unwrap()
+        .memory_budget(1000)
+        .run()
+        .unwrap()
+        .map(|row| row.unwrap()["text"][..1].to_string())
+        .collect();
+    assert_eq!(sorted, &["a", "b"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn taken_run_names_test() {
+    let dir = std::env::temp_dir().join(format!("sort-links-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let target = dir.join("target");
+    std::fs::write(&target, "keep").unwrap();
+    // links at the names of the first runs of this process
+    for i in 0..2000 {
+        let link = dir.join(format!("csv-sort-{}-{}.csv", std::process::id(), i));
+        std::os::unix::fs::symlink(&target, link).unwrap();
+    }
+
+    let mut data = String::from("n\n");
+    for i in (0..100).rev() {
+        data.push_str(&format!("{}\n", i));
+    }
+    let data: &'static str = Box::leak(data.into_boxed_str());
+    let rows: Vec<String> = csv(data)
+        .sort_by(&["n"])
+        .unwrap()
+        .memory_budget(200)
+        .temp_dir(&dir)
+        .run()
+        .unwrap()
+        .map(|row| row.unwrap()["n"].clone())
+        .collect();
+    let expected: Vec<String> = (0..100).map(|i| i.to_string()).collect();
+    assert_eq!(rows, expected);
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "keep");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn write_to_test() {
+    let mut csv = csv(DATA);
+    csv.select_columns(&["name", "age"]).unwrap();
+    let mut output = Vec::new();
+    csv.sort_by(&["-name"])
+        .unwrap()
+        .run()
+        .unwrap()
+        .write_to(&mut output)
+        .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(
+        output.lines().take(2).collect::<Vec<_>>(),
+        &["name, age", "\"Eve\", \"\""]
+    );
+}
+
+#[test]
+fn errors_test() {
+    assert!(matches!(
+        csv(DATA).sort_by(&["height"]).err(),
+        Some(CsvError::InvalidColumn(column)) if column == "height"
+    ));
+    match csv(DATA).sort_by(&["name:number"]).unwrap().run() {
+        Err(CsvError::InvalidRow(details)) => {
+            assert_eq!(details.column_name.as_deref(), Some("name"));
+            assert_eq!(details.excerpt.as_deref(), Some("Ada"));
+        }
+        other => panic!("expected an invalid row, got {:?}", other.err()),
+    }
+    assert!(csv(DATA).sort_by(&["age:date"]).unwrap().run().is_err());
+}
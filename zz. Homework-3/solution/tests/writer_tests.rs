@@ -0,0 +1,141 @@
+use solution::*;
+use std::io::{self, BufReader, Write};
+
+fn written<F: FnOnce(&mut CsvWriter<Vec<u8>>)>(writer: CsvWriter<Vec<u8>>, write: F) -> String {
+    let mut writer = writer;
+    write(&mut writer);
+    String::from_utf8(writer.into_inner().unwrap()).unwrap()
+}
+
+#[test]
+fn necessary_quotes_test() {
+    let output = written(CsvWriter::new(Vec::new()), |writer| {
+        writer.write_header(["name", "notes"]).unwrap();
+        writer.write_record(["Ada", "plain"]).unwrap();
+        writer.write_record(["Bob", "a, \"b\""]).unwrap();
+        writer.write_record(["", " padded "]).unwrap();
+    });
+    assert_eq!(
+        output,
+        "name, notes\nAda, plain\nBob, \"a, \"\"b\"\"\"\n\"\", \" padded \"\n"
+    );
+}
+
+#[test]
+fn always_quote_test() {
+    let writer = CsvWriter::with_dialect(Vec::new(), Dialect::default()).unwrap();
+    let output = written(writer, |writer| {
+        writer.write_header(vec!["name".to_string()]).unwrap();
+        writer.write_record(vec!["Ada".to_string()]).unwrap();
+    });
+    assert_eq!(output, "name\n\"Ada\"\n");
+}
+
+#[test]
+fn dialect_test() {
+    let dialect = Dialect {
+        line_terminator: LineTerminator::CrLf,
+        quote: '\'',
+        escape: Some('\\'),
+        quote_style: QuoteStyle::Necessary,
+        ..Dialect::tsv()
+    };
+    let writer = CsvWriter::with_dialect(Vec::new(), dialect.clone()).unwrap();
+    let output = written(writer, |writer| {
+        writer.write_record(["a b", "it's", "x\ty"]).unwrap();
+    });
+    assert_eq!(output, "a b\t'it\\'s'\t'x\ty'\r\n");
+
+    let invalid = Dialect {
+        delimiter: '\\',
+        ..dialect
+    };
+    assert!(matches!(
+        CsvWriter::with_dialect(Vec::new(), invalid),
+        Err(CsvError::InvalidDialect(_))
+    ));
+}
+
+#[test]
+fn round_trip_test() {
+    let data = "id\tname\n1\tAda\n2\t'it''s'\n";
+    let dialect = Dialect {
+        quote: '\'',
+        quote_style: QuoteStyle::Necessary,
+        ..Dialect::tsv()
+    };
+    let csv = Csv::with_dialect(BufReader::new(data.as_bytes()), dialect.clone()).unwrap();
+
+    let mut writer = CsvWriter::with_dialect(Vec::new(), dialect).unwrap();
+    writer.write_header(csv.columns()).unwrap();
+    for row in csv {
+        writer.write_row(&row.unwrap()).unwrap();
+    }
+    let output = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+    assert_eq!(output, "id\tname\n1\tAda\n2\t'it''s'\n");
+}
+
+/// Accepts at most a couple of bytes per call, like a slow pipe
+struct ShortWriter {
+    written: Vec<u8>,
+    flushed: bool,
+}
+
+impl Write for ShortWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(2);
+        self.written.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flushed = true;
+        Ok(())
+    }
+}
+
+#[test]
+fn short_writes_test() {
+    let short = ShortWriter {
+        written: Vec::new(),
+        flushed: false,
+    };
+    let mut writer = CsvWriter::new(short);
+    writer.write_record(["one", "two", "three"]).unwrap();
+    let short = writer.into_inner().unwrap();
+    assert!(short.flushed);
+    assert_eq!(short.written, b"one, two, three\n");
+
+    let data = "a,b\n1,2\n".as_bytes();
+    let mut short = ShortWriter {
+        written: Vec::new(),
+        flushed: false,
+    };
+    Csv::new(BufReader::new(data))
+        .unwrap()
+        .write_to(&mut short)
+        .unwrap();
+    assert_eq!(short.written, b"a, b\n\"1\", \"2\"\n");
+}
+
+struct FailingWriter;
+
+impl Write for FailingWriter {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Ok(0)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Err(io::Error::other("cannot flush"))
+    }
+}
+
+#[test]
+fn errors_test() {
+    let mut writer = CsvWriter::new(FailingWriter);
+    match writer.write_record(["x"]) {
+        Err(CsvError::IO(error)) => assert_eq!(error.kind(), io::ErrorKind::WriteZero),
+        other => panic!("expected an I/O error, got {:?}", other.err()),
+    }
+    assert!(matches!(writer.flush(), Err(CsvError::IO(_))));
+}